@@ -1,3 +1,4 @@
+use chess::{book::DEFAULT_BOOK_DEPTH, EvaluatorKind};
 use clap::{Args, Parser, Subcommand};
 
 /// Chess game for two-player, single-player, and reinforcement learning
@@ -18,13 +19,49 @@ pub enum GameType {
     
     /// Self-play reinforcement learning
     SelfPlay(SelfPlayArgs),
+
+    /// Universal Chess Interface engine mode
+    Uci(UciArgs),
+
+    /// Step through a game from a PGN file
+    Replay(ReplayArgs),
+
+    /// Count the move generator's leaf nodes to a given depth
+    Perft(PerftArgs),
+
+    /// Work with polyglot opening books
+    Book(BookArgs),
 }
 
 #[derive(Args, Debug)]
 pub struct TwoPlayerArgs {
     /// allow players to get best move hints
     #[arg(short, long, default_value_t = false)]
-    pub allow_hints: bool
+    pub allow_hints: bool,
+
+    /// seconds on each player's clock for the whole game, or for each period with --moves-per-period
+    #[arg(long)]
+    pub time: Option<u64>,
+
+    /// seconds added to a player's clock after each of their moves (fischer)
+    #[arg(long, requires = "time", conflicts_with = "delay")]
+    pub increment: Option<u64>,
+
+    /// seconds of each move given back to the player's clock (bronstein delay)
+    #[arg(long, requires = "time")]
+    pub delay: Option<u64>,
+
+    /// moves in each period of the time control, after which a player gets the --time again
+    #[arg(long, requires = "time")]
+    pub moves_per_period: Option<u32>,
+
+    /// start from the position described by a FEN string
+    #[arg(long)]
+    pub fen: Option<String>,
+
+    /// append finished games to a PGN file
+    #[arg(long)]
+    pub pgn_out: Option<String>
 }
 
 #[derive(Args, Debug)]
@@ -33,24 +70,92 @@ pub struct OnePlayerArgs {
     #[arg(short, long, default_value_t = false)]
     pub black: bool,
     
-    /// use heuristic evaluation function in minimax
-    #[arg(short = 'p', long, default_value_t = false)]
+    /// use heuristic evaluation function in minimax, the same as --eval heuristic
+    #[arg(short = 'p', long, default_value_t = false, conflicts_with = "eval")]
     pub heuristic: bool,
+
+    /// evaluation function in minimax: material, heuristic, model or blend (the model and heuristic evenly)
+    #[arg(long)]
+    pub eval: Option<EvaluatorKind>,
+
+    /// file of hand-crafted heuristic terms to use in place of the defaults
+    #[arg(long)]
+    pub eval_file: Option<String>,
+
+    /// polyglot opening book to play the first moves from
+    #[arg(long)]
+    pub book: Option<String>,
+
+    /// how many moves of each game to play from the book
+    #[arg(long, default_value_t = DEFAULT_BOOK_DEPTH, requires = "book")]
+    pub book_depth: u32,
+
+    /// directories of syzygy endgame tablebases, separated like PATH
+    #[arg(long)]
+    pub syzygy_path: Option<String>,
     
     /// search depth for minimax algorithm
     #[arg(short = 'd', long = "depth")]
     pub search_depth: Option<u8>,
+
+    /// milliseconds to think about each move, searching as deep as time allows up to --depth
+    #[arg(long, conflicts_with = "time")]
+    pub movetime: Option<u64>,
+
+    /// seconds on each player's clock for the whole game, or for each period with --moves-per-period
+    #[arg(long)]
+    pub time: Option<u64>,
+
+    /// seconds added to a player's clock after each of their moves (fischer)
+    #[arg(long, requires = "time", conflicts_with = "delay")]
+    pub increment: Option<u64>,
+
+    /// seconds of each move given back to the player's clock (bronstein delay)
+    #[arg(long, requires = "time")]
+    pub delay: Option<u64>,
+
+    /// moves in each period of the time control, after which a player gets the --time again
+    #[arg(long, requires = "time")]
+    pub moves_per_period: Option<u32>,
     
     /// directory for evaluation model
     #[arg(long)]
-    pub model_dir: Option<String>
+    pub model_dir: Option<String>,
+
+    /// start from the position described by a FEN string
+    #[arg(long)]
+    pub fen: Option<String>,
+
+    /// append finished games to a PGN file
+    #[arg(long)]
+    pub pgn_out: Option<String>
 }
 
 #[derive(Args, Debug)]
 pub struct SelfPlayArgs {
-    /// use heuristic evaluation function in minimax
-    #[arg(short = 'p', long, default_value_t = false)]
+    /// use heuristic evaluation function in minimax, the same as --eval heuristic
+    #[arg(short = 'p', long, default_value_t = false, conflicts_with = "eval")]
     pub heuristic: bool,
+
+    /// evaluation function in minimax: material, heuristic, model or blend (the model and heuristic evenly)
+    #[arg(long)]
+    pub eval: Option<EvaluatorKind>,
+
+    /// file of hand-crafted heuristic terms to use in place of the defaults
+    #[arg(long)]
+    pub eval_file: Option<String>,
+
+    /// polyglot opening book to play the first moves from
+    #[arg(long)]
+    pub book: Option<String>,
+
+    /// how many moves of each game to play from the book
+    #[arg(long, default_value_t = DEFAULT_BOOK_DEPTH, requires = "book")]
+    pub book_depth: u32,
+
+    /// directories of syzygy endgame tablebases, separated like PATH
+    #[arg(long)]
+    pub syzygy_path: Option<String>,
     
     /// directory for evaluation model
     #[arg(long)]
@@ -64,13 +169,116 @@ pub struct SelfPlayArgs {
     #[arg(short = 'd', long = "depth")]
     pub search_depth: Option<u8>,
 
+    /// milliseconds to think about each move, searching as deep as time allows up to --depth
+    #[arg(long, conflicts_with = "time")]
+    pub movetime: Option<u64>,
+
+    /// seconds on the computer's clock for the whole game
+    #[arg(long)]
+    pub time: Option<u64>,
+
+    /// seconds added to the computer's clock after each of its moves
+    #[arg(long, requires = "time")]
+    pub increment: Option<u64>,
+
     /// use epsilon-greedy algorithm for reinforcement learning
     #[arg(short, long)]
     pub epsilon_greedy: bool,
     
     /// epsilon decay rate for epsilon-greedy algorithm
     #[arg(long = "decay")]
-    pub epsilon_decay: Option<f64>
+    pub epsilon_decay: Option<f64>,
+
+    /// append finished games to a PGN file
+    #[arg(long)]
+    pub pgn_out: Option<String>
+}
+
+#[derive(Args, Debug)]
+pub struct UciArgs {
+    /// use heuristic evaluation function in minimax, the same as --eval heuristic
+    #[arg(short = 'p', long, default_value_t = false, conflicts_with = "eval")]
+    pub heuristic: bool,
+
+    /// evaluation function in minimax: material, heuristic, model or blend (the model and heuristic evenly)
+    #[arg(long)]
+    pub eval: Option<EvaluatorKind>,
+
+    /// file of hand-crafted heuristic terms to use in place of the defaults
+    #[arg(long)]
+    pub eval_file: Option<String>,
+
+    /// polyglot opening book to play the first moves from
+    #[arg(long)]
+    pub book: Option<String>,
+
+    /// how many moves of each game to play from the book
+    #[arg(long, default_value_t = DEFAULT_BOOK_DEPTH, requires = "book")]
+    pub book_depth: u32,
+
+    /// directories of syzygy endgame tablebases, separated like PATH
+    #[arg(long)]
+    pub syzygy_path: Option<String>,
+
+    /// default search depth for minimax algorithm
+    #[arg(short = 'd', long = "depth")]
+    pub search_depth: Option<u8>,
+
+    /// directory for evaluation model
+    #[arg(long)]
+    pub model_dir: Option<String>
+}
+
+#[derive(Args, Debug)]
+pub struct ReplayArgs {
+    /// PGN file to replay
+    pub path: String,
+
+    /// which game in the file to replay, starting from 1
+    #[arg(short, long, default_value_t = 1)]
+    pub game: usize
+}
+
+#[derive(Args, Debug)]
+pub struct PerftArgs {
+    /// number of plies to count moves to
+    #[arg(short = 'd', long = "depth")]
+    pub depth: u8,
+
+    /// position to count from as a FEN string, defaults to the starting position
+    #[arg(long)]
+    pub fen: Option<String>,
+
+    /// show the count below each legal move
+    #[arg(long, default_value_t = false)]
+    pub divide: bool
+}
+
+#[derive(Args, Debug)]
+pub struct BookArgs {
+    #[clap(subcommand)]
+    pub command: BookCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BookCommand {
+    /// Build a book from the games in PGN files
+    Build(BookBuildArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct BookBuildArgs {
+    /// PGN files to read games from
+    #[arg(required = true)]
+    pub pgn: Vec<String>,
+
+    /// where to write the book
+    #[arg(short, long)]
+    pub output: String,
+
+    /// how many moves of each game go in the book
+    #[arg(short = 'd', long = "depth", default_value_t = DEFAULT_BOOK_DEPTH)]
+    pub depth: u32
 }
 
 // #[derive(Parser, Debug)]
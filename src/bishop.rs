@@ -2,7 +2,7 @@ use std::{any::Any, fmt::{Display, Formatter}};
 
 use colored::Colorize;
 
-use crate::{game::Game, piece::{Construct, Move, Piece, DynClone}, player::Player};
+use crate::{game::Game, piece::{Construct, MoveKind, Piece, DynClone}, player::Player};
 
 #[derive(Clone, Debug)]
 pub struct Bishop {
@@ -22,7 +22,7 @@ impl Piece for Bishop {
                     break;
                 }
                 let new_pos = (x as u8, y as u8);
-                if game.is_not_player(new_pos, self.player)  && !game.try_move_for_check(position, new_pos, self.player) {
+                if game.is_not_player(new_pos, self.player)  && !game.try_move_for_check(position, new_pos) {
                     moves.push(new_pos);
                 }
                 if game.square_is_none(new_pos) {
@@ -34,10 +34,10 @@ impl Piece for Bishop {
         moves
     }
 
-    fn valid_move(&self, from: (u8,u8), to: (u8,u8), game: &mut Game) -> Move {
+    fn valid_move(&self, from: (u8,u8), to: (u8,u8), game: &mut Game) -> MoveKind {
         let (x, y) = (to.0 as i8 - from.0 as i8, to.1 as i8 - from.1 as i8);
         let valid = x.abs() == y.abs() && game.check_diag(from, (x,y));
-        if valid {Move::Normal} else {Move::Invalid}
+        if valid {MoveKind::Normal} else {MoveKind::Invalid}
     }
 
     fn player(&self) -> Player {
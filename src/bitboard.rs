@@ -0,0 +1,129 @@
+// a bitboard has a bit for every square, square y * 8 + x, so a8 is bit 0 and h1 is bit 63
+pub type Bitboard = u64;
+
+pub const KNIGHT_ATTACKS: [Bitboard; 64] = leaper_attacks(&[(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)]);
+pub const KING_ATTACKS: [Bitboard; 64] = leaper_attacks(&[(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)]);
+// the squares a pawn attacks from each square, for player one then player two, player one's pawns move towards y = 0
+pub const PAWN_ATTACKS: [[Bitboard; 64]; 2] = [leaper_attacks(&[(-1, -1), (1, -1)]), leaper_attacks(&[(-1, 1), (1, 1)])];
+// the squares where x + y is even, a8 and h1 among them
+pub const LIGHT_SQUARES: Bitboard = 0xAA55_AA55_AA55_AA55;
+
+// rook directions first, then bishop directions
+const DIRECTIONS: [(i8, i8); 8] = [(0, -1), (1, 0), (0, 1), (-1, 0), (1, -1), (1, 1), (-1, 1), (-1, -1)];
+const RAYS: [[Bitboard; 64]; 8] = rays();
+
+const fn leaper_attacks(deltas: &[(i8, i8)]) -> [Bitboard; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let (x, y) = ((square % 8) as i8, (square / 8) as i8);
+        let mut i = 0;
+        while i < deltas.len() {
+            let (x, y) = (x + deltas[i].0, y + deltas[i].1);
+            if x >= 0 && x < 8 && y >= 0 && y < 8 {
+                table[square] |= 1 << (y * 8 + x);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
+
+// every square from each square to the edge of the board in each direction
+const fn rays() -> [[Bitboard; 64]; 8] {
+    let mut rays = [[0; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        let mut square = 0;
+        while square < 64 {
+            let (mut x, mut y) = ((square % 8) as i8, (square / 8) as i8);
+            loop {
+                x += DIRECTIONS[direction].0;
+                y += DIRECTIONS[direction].1;
+                if x < 0 || x >= 8 || y < 0 || y >= 8 {
+                    break;
+                }
+                rays[direction][square] |= 1 << (y * 8 + x);
+            }
+            square += 1;
+        }
+        direction += 1;
+    }
+    rays
+}
+
+// the ray up to and including the first occupied square
+fn ray_attacks(direction: usize, square: u8, occupied: Bitboard) -> Bitboard {
+    let ray = RAYS[direction][square as usize];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+    // rays heading towards h1 meet their nearest blocker at the lowest bit, the others at the highest
+    let (x, y) = DIRECTIONS[direction];
+    let blocker = if y > 0 || (y == 0 && x > 0) {
+        blockers.trailing_zeros()
+    } else {
+        63 - blockers.leading_zeros()
+    };
+    ray ^ RAYS[direction][blocker as usize]
+}
+
+pub fn rook_attacks(square: u8, occupied: Bitboard) -> Bitboard {
+    (0..4).fold(0, |attacks, direction| attacks | ray_attacks(direction, square, occupied))
+}
+
+pub fn bishop_attacks(square: u8, occupied: Bitboard) -> Bitboard {
+    (4..8).fold(0, |attacks, direction| attacks | ray_attacks(direction, square, occupied))
+}
+
+pub fn bit(square: u8) -> Bitboard {
+    1 << square
+}
+
+// the squares set in a bitboard, lowest first
+pub fn squares(mut bitboard: Bitboard) -> impl Iterator<Item = u8> {
+    std::iter::from_fn(move || {
+        if bitboard == 0 {
+            return None;
+        }
+        let square = bitboard.trailing_zeros() as u8;
+        bitboard &= bitboard - 1;
+        Some(square)
+    })
+}
+
+pub fn square((x, y): (u8, u8)) -> u8 {
+    y * 8 + x
+}
+
+pub fn coords(square: u8) -> (u8, u8) {
+    (square % 8, square / 8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attack_tables() {
+        let e4 = square((4, 4));
+        assert_eq!(KNIGHT_ATTACKS[0].count_ones(), 2);
+        assert_eq!(KNIGHT_ATTACKS[e4 as usize].count_ones(), 8);
+        assert_eq!(KING_ATTACKS[63].count_ones(), 3);
+        assert_eq!(PAWN_ATTACKS[0][e4 as usize], bit(square((3, 3))) | bit(square((5, 3))));
+        assert_eq!(PAWN_ATTACKS[1][square((0, 1)) as usize], bit(square((1, 2))));
+
+        assert_eq!(rook_attacks(e4, 0).count_ones(), 14);
+        // a blocker is attacked, the squares behind it aren't
+        let blockers = bit(square((4, 2))) | bit(square((6, 4)));
+        let attacks = rook_attacks(e4, blockers);
+        assert!(attacks & bit(square((4, 2))) != 0 && attacks & bit(square((4, 1))) == 0);
+        assert!(attacks & bit(square((6, 4))) != 0 && attacks & bit(square((7, 4))) == 0);
+        assert_eq!(attacks.count_ones(), 11);
+        assert_eq!(bishop_attacks(0, 0).count_ones(), 7);
+        assert_eq!(bishop_attacks(0, bit(square((2, 2)))).count_ones(), 2);
+        assert!(LIGHT_SQUARES & bit(0) != 0 && LIGHT_SQUARES & bit(63) != 0 && LIGHT_SQUARES & bit(7) == 0);
+    }
+}
@@ -0,0 +1,314 @@
+use std::sync::{Arc, OnceLock};
+
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    evaluator::Evaluator,
+    player::Player,
+    position::{PieceType, Position},
+    search::tablebase_score,
+    syzygy::Wdl,
+};
+
+// endgame databases the engine works out for itself by retrograde analysis, from every checkmate backwards over the
+// moves of its own move generator. a position where the lone king is to move is lost once every move it has leads to
+// a lost position, and one where the other side is to move is won once any of its moves does. positions are indexed
+// with the side that has the extra piece as white, and black positions are mirrored onto them
+
+// the pieces the tables cover next to the two kings, kpk last since its promotions go into the others
+pub const ENDGAME_PIECES: [PieceType; 3] = [PieceType::Queen, PieceType::Rook, PieceType::Pawn];
+
+// whose move it is, then the squares of the stronger king, its piece and the lone king
+const TABLE_SIZE: usize = 2 * 64 * 64 * 64;
+// positions that are drawn, and ones that can't come up
+const DRAW: u8 = u8::MAX;
+
+static TABLES: OnceLock<EndgameTables> = OnceLock::new();
+
+// what a position in the tables comes to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EndgameResult {
+    Draw,
+    // the player who gives checkmate, and in how many plies with the best play from both sides
+    Mate(Player, u32),
+}
+
+// the plies to checkmate of every kqk, krk and kpk position
+pub struct EndgameTables {
+    // one table for each of ENDGAME_PIECES
+    tables: Vec<Vec<u8>>,
+}
+
+impl EndgameTables {
+    pub fn generate() -> Self {
+        let mut tables = Self { tables: Vec::with_capacity(ENDGAME_PIECES.len()) };
+        for piece_type in ENDGAME_PIECES {
+            let table = tables.solve(piece_type);
+            tables.tables.push(table);
+        }
+        tables
+    }
+
+    // the tables shared by every game, generated the first time they are needed
+    pub fn get() -> &'static Self {
+        TABLES.get_or_init(Self::generate)
+    }
+
+    // None unless the position is a king and a queen, rook or pawn against a lone king
+    pub fn probe(&self, position: &Position) -> Option<EndgameResult> {
+        let (strong, piece_type) = signature(position)?;
+        let table = self.tables.get(ENDGAME_PIECES.iter().position(|&endgame| endgame == piece_type)?)?;
+        // black's pieces mirrored onto white's side of the board
+        let flip = if strong == Player::One { 0 } else { 56 };
+        let piece = position.pieces(strong, piece_type).trailing_zeros() as u8;
+        let index = index_of(
+            position.side() == strong,
+            position.king(strong) ^ flip,
+            piece ^ flip,
+            position.king(strong.other()) ^ flip,
+        );
+        Some(match table[index] {
+            DRAW => EndgameResult::Draw,
+            plies => EndgameResult::Mate(strong, plies as u32),
+        })
+    }
+
+    // every legal position is linked to the ones it can be reached from, then the checkmates are spread back through
+    // those links a ply at a time, so that each position is reached first by its quickest mate
+    fn solve(&self, piece_type: PieceType) -> Vec<u8> {
+        let links = (0..TABLE_SIZE)
+            .into_par_iter()
+            .filter_map(|index| self.links(piece_type, index))
+            .collect::<Vec<Links>>();
+        let mut predecessors = vec![Vec::new(); TABLE_SIZE];
+        // the lone king's moves that aren't known to lose yet
+        let mut unproven = vec![0u8; TABLE_SIZE];
+        // the positions found to be mated in each number of plies
+        let mut queue = vec![Vec::new()];
+        for links in links {
+            if links.checkmate {
+                queue[0].push(links.from);
+            }
+            unproven[links.from as usize] = links.moves;
+            for to in links.successors {
+                predecessors[to as usize].push(links.from);
+            }
+            if let Some(plies) = links.promotion {
+                push(&mut queue, plies as usize + 1, links.from as usize);
+            }
+        }
+
+        let mut table = vec![DRAW; TABLE_SIZE];
+        let mut plies = 0;
+        while plies < queue.len() {
+            for index in std::mem::take(&mut queue[plies]) {
+                let index = index as usize;
+                if table[index] != DRAW {
+                    continue;
+                }
+                table[index] = plies as u8;
+                for &previous in &predecessors[index] {
+                    let previous = previous as usize;
+                    if previous < TABLE_SIZE / 2 {
+                        // the stronger side only needs one move that mates
+                        if table[previous] == DRAW {
+                            push(&mut queue, plies + 1, previous);
+                        }
+                    } else {
+                        unproven[previous] -= 1;
+                        if unproven[previous] == 0 {
+                            push(&mut queue, plies + 1, previous);
+                        }
+                    }
+                }
+            }
+            plies += 1;
+        }
+        table
+    }
+
+    // None if the position can't come up
+    fn links(&self, piece_type: PieceType, index: usize) -> Option<Links> {
+        let strong_to_move = index < TABLE_SIZE / 2;
+        let (strong_king, piece, weak_king) = ((index / 4096 % 64) as u8, (index / 64 % 64) as u8, (index % 64) as u8);
+        let mut position = setup(piece_type, strong_to_move, strong_king, piece, weak_king)?;
+        let moves = position.legal_moves();
+        let mut links = Links {
+            from: index as u32,
+            moves: moves.len() as u8,
+            checkmate: moves.is_empty() && position.in_check(Player::Two),
+            successors: Vec::with_capacity(moves.len()),
+            promotion: None,
+        };
+        for mov in moves {
+            let undo = position.make_move(mov);
+            let pieces = position.pieces(Player::One, piece_type);
+            if pieces != 0 {
+                let (king, weak_king) = (position.king(Player::One), position.king(Player::Two));
+                links.successors.push(index_of(!strong_to_move, king, pieces.trailing_zeros() as u8, weak_king) as u32);
+            } else if let Some(EndgameResult::Mate(_, plies)) = self.probe(&position) {
+                // a promotion to a queen or rook, whose tables are already solved
+                links.promotion = Some(links.promotion.map_or(plies, |quickest| quickest.min(plies)));
+            }
+            // otherwise the lone king took the piece or the pawn promoted to a minor piece, both draws
+            position.unmake_move(mov, undo);
+        }
+        Some(links)
+    }
+}
+
+// a legal position's moves
+struct Links {
+    from: u32,
+    moves: u8,
+    checkmate: bool,
+    // the positions the moves lead to that are in the same table
+    successors: Vec<u32>,
+    // the plies to mate after the quickest winning promotion
+    promotion: Option<u32>,
+}
+
+// consults the tables before another evaluation, scoring the positions they cover by how soon they are mated
+pub struct EndgameEvaluator<'a> {
+    evaluator: Arc<dyn Evaluator + 'a>,
+}
+
+impl<'a> EndgameEvaluator<'a> {
+    pub fn new(evaluator: Arc<dyn Evaluator + 'a>) -> Self {
+        Self { evaluator }
+    }
+
+    // the tables are only generated once a position needs them
+    fn known(&self, position: &Position) -> Option<f32> {
+        signature(position)?;
+        match EndgameTables::get().probe(position)? {
+            EndgameResult::Draw => Some(0.0),
+            EndgameResult::Mate(player, plies) => {
+                let score = tablebase_score(Wdl::Win, plies, self.evaluator.pawn_scale());
+                Some(if player.is_maximizing() { score } else { -score })
+            }
+        }
+    }
+}
+
+impl Evaluator for EndgameEvaluator<'_> {
+    fn evaluate(&self, position: &Position) -> f32 {
+        self.known(position).unwrap_or_else(|| self.evaluator.evaluate(position))
+    }
+
+    fn evaluate_batch(&self, positions: &[Position]) -> Vec<f32> {
+        let mut scores = self.evaluator.evaluate_batch(positions);
+        for (score, position) in scores.iter_mut().zip(positions) {
+            if let Some(known) = self.known(position) {
+                *score = known;
+            }
+        }
+        scores
+    }
+
+    fn pawn_scale(&self) -> f32 {
+        self.evaluator.pawn_scale()
+    }
+
+    fn prefers_batches(&self) -> bool {
+        self.evaluator.prefers_batches()
+    }
+}
+
+// the player with the extra piece and what it is, if the position is one the tables cover
+fn signature(position: &Position) -> Option<(Player, PieceType)> {
+    let kings = position.pieces(Player::One, PieceType::King) != 0 && position.pieces(Player::Two, PieceType::King) != 0;
+    if !kings || position.occupied().count_ones() != 3 {
+        return None;
+    }
+    let strong = if position.occupied_by(Player::One).count_ones() == 2 { Player::One } else { Player::Two };
+    ENDGAME_PIECES
+        .into_iter()
+        .find(|&piece_type| position.pieces(strong, piece_type) != 0)
+        .map(|piece_type| (strong, piece_type))
+}
+
+fn index_of(strong_to_move: bool, strong_king: u8, piece: u8, weak_king: u8) -> usize {
+    ((usize::from(!strong_to_move) * 64 + strong_king as usize) * 64 + piece as usize) * 64 + weak_king as usize
+}
+
+// the position with the stronger side as white, None if pieces share a square, the pawn is on the first or last
+// rank or the side that just moved is left in check
+fn setup(piece_type: PieceType, strong_to_move: bool, strong_king: u8, piece: u8, weak_king: u8) -> Option<Position> {
+    let overlapping = strong_king == piece || strong_king == weak_king || piece == weak_king;
+    if overlapping || (piece_type == PieceType::Pawn && matches!(piece / 8, 0 | 7)) {
+        return None;
+    }
+    let side = if strong_to_move { Player::One } else { Player::Two };
+    let pieces = [
+        (strong_king, Player::One, PieceType::King),
+        (piece, Player::One, piece_type),
+        (weak_king, Player::Two, PieceType::King),
+    ];
+    let position = Position::from_pieces(&pieces, side);
+    (!position.in_check(side.other())).then_some(position)
+}
+
+fn push(queue: &mut Vec<Vec<u32>>, plies: usize, index: usize) {
+    if queue.len() <= plies {
+        queue.resize_with(plies + 1, Vec::new);
+    }
+    queue[plies].push(index as u32);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluator::Material, game::Game, search::Score};
+
+    // the longest mates with the stronger side to move, mate in 10, 16 and 28, as found by earlier endgame databases
+    #[test]
+    fn matches_known_endgame_statistics() {
+        let tables = EndgameTables::get();
+        let longest = tables
+            .tables
+            .iter()
+            .map(|table| table[..TABLE_SIZE / 2].iter().filter(|&&plies| plies != DRAW).max().copied())
+            .collect::<Vec<Option<u8>>>();
+        assert_eq!(longest, vec![Some(19), Some(31), Some(55)]);
+        let game = Game::from_fen("8/8/8/8/8/8/8/K1k5 w - - 0 1").unwrap();
+        assert_eq!(tables.probe(game.position()), None);
+    }
+
+    #[test]
+    fn probes_both_colors() {
+        let tables = EndgameTables::get();
+        let probe = |fen: &str| tables.probe(Game::from_fen(fen).unwrap().position());
+        // the rook mates on the back rank
+        assert_eq!(probe("6k1/8/6K1/8/8/8/8/R7 w - - 0 1"), Some(EndgameResult::Mate(Player::One, 1)));
+        assert_eq!(probe("r7/8/8/8/8/6k1/8/6K1 b - - 0 1"), Some(EndgameResult::Mate(Player::Two, 1)));
+        // the lone king takes the undefended queen
+        assert_eq!(probe("8/8/8/8/8/8/1q6/K5k1 w - - 0 1"), Some(EndgameResult::Draw));
+        // a king on a key square in front of its pawn wins whoever is to move
+        assert!(matches!(probe("3k4/8/3K4/8/3P4/8/8/8 b - - 0 1"), Some(EndgameResult::Mate(Player::One, _))));
+        assert!(matches!(probe("8/8/8/3p4/8/3k4/8/3K4 b - - 0 1"), Some(EndgameResult::Mate(Player::Two, _))));
+        // but not with a rook pawn once the lone king is in the corner
+        assert_eq!(probe("k7/8/8/P7/8/8/8/K7 w - - 0 1"), Some(EndgameResult::Draw));
+        // and stalemate is a draw
+        assert_eq!(probe("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1"), Some(EndgameResult::Draw));
+    }
+
+    #[test]
+    fn agrees_with_the_search() {
+        for fen in ["7k/8/5K2/8/8/8/8/1Q6 w - - 0 1", "k7/8/2K5/8/8/8/8/7R b - - 0 1", "8/8/8/8/8/8/k7/2K4R w - - 0 1"] {
+            let game = Game::from_fen(fen).unwrap();
+            let Some(EndgameResult::Mate(_, plies)) = EndgameTables::get().probe(game.position()) else {
+                panic!("{fen} should be won");
+            };
+            let result = game.iterative_deepening(plies as u8 + 1, None, |_| ()).unwrap();
+            let moves = plies.div_ceil(2) as i32;
+            let expected = if game.position().side() == Player::One { moves } else { -moves };
+            assert_eq!(result.score, Score::Mate(expected));
+        }
+        let evaluator = EndgameEvaluator::new(Arc::new(Material));
+        let game = Game::from_fen("7k/8/5K2/8/8/8/8/1Q6 w - - 0 1").unwrap();
+        assert!(evaluator.evaluate(game.position()) > 100.0);
+        let game = Game::from_fen("4k3/8/8/8/8/8/PPP5/4K3 w - - 0 1").unwrap();
+        assert_eq!(evaluator.evaluate(game.position()), 3.0);
+    }
+}
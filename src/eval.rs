@@ -0,0 +1,512 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    ops::{Add, AddAssign, Mul, Sub},
+};
+
+use crate::{
+    evaluator::Evaluator,
+    bitboard::{bishop_attacks, bit, coords, rook_attacks, squares, Bitboard, KING_ATTACKS, KNIGHT_ATTACKS, PAWN_ATTACKS},
+    player::Player,
+    position::{PieceType, Position},
+};
+
+const PIECE_TYPES: [PieceType; 6] = [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King];
+const PIECE_NAMES: [&str; 6] = ["pawn", "knight", "bishop", "rook", "queen", "king"];
+// how much each piece counts towards the middlegame, the starting position adds up to the maximum
+const PHASE_WEIGHTS: [i32; 6] = [0, 1, 1, 2, 4, 0];
+const MAX_PHASE: i32 = 24;
+const FILE_A: Bitboard = 0x0101_0101_0101_0101;
+
+// a term's weight in the middlegame and in the endgame, in centipawns
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Weight {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+const fn w(mg: i32, eg: i32) -> Weight {
+    Weight { mg, eg }
+}
+
+impl Add for Weight {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        w(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl AddAssign for Weight {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Weight {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        w(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl Mul<i32> for Weight {
+    type Output = Self;
+
+    fn mul(self, count: i32) -> Self {
+        w(self.mg * count, self.eg * count)
+    }
+}
+
+// the terms of the hand-crafted evaluation, tables are indexed by square from a8 to h1 as white sees the board
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvalParams {
+    pub piece_values: [Weight; 6],
+    pub tables: [[Weight; 64]; 6],
+    // for each square a knight, bishop, rook or queen can move to that isn't guarded by an enemy pawn
+    pub mobility: [Weight; 6],
+    pub doubled_pawn: Weight,
+    pub isolated_pawn: Weight,
+    // by the pawn's rank as its owner sees the board, from the first rank
+    pub passed_pawn: [Weight; 8],
+    // for each pawn on the three files around the king within two ranks of it
+    pub pawn_shield: Weight,
+    // for each of the files around the king without a pawn of its own
+    pub king_open_file: Weight,
+    // for each square next to the enemy king that a knight, bishop, rook or queen attacks
+    pub king_attack: [Weight; 6],
+    pub bishop_pair: Weight,
+    pub rook_open_file: Weight,
+    pub rook_semi_open_file: Weight,
+}
+
+#[rustfmt::skip]
+const PAWN_TABLE_MG: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    10,  10,  20,  30,  30,  20,  10,  10,
+     5,   5,  10,  25,  25,  10,   5,   5,
+     0,   0,   0,  20,  20,   0,   0,   0,
+     5,  -5, -10,   0,   0, -10,  -5,   5,
+     5,  10,  10, -20, -20,  10,  10,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const PAWN_TABLE_EG: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    80,  80,  80,  80,  80,  80,  80,  80,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    30,  30,  30,  30,  30,  30,  30,  30,
+    20,  20,  20,  20,  20,  20,  20,  20,
+    10,  10,  10,  10,  10,  10,  10,  10,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+   -50, -40, -30, -30, -30, -30, -40, -50,
+   -40, -20,   0,   0,   0,   0, -20, -40,
+   -30,   0,  10,  15,  15,  10,   0, -30,
+   -30,   5,  15,  20,  20,  15,   5, -30,
+   -30,   0,  15,  20,  20,  15,   0, -30,
+   -30,   5,  10,  15,  15,  10,   5, -30,
+   -40, -20,   0,   5,   5,   0, -20, -40,
+   -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+   -20, -10, -10, -10, -10, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,   5,  10,  10,   5,   0, -10,
+   -10,   5,   5,  10,  10,   5,   5, -10,
+   -10,   0,  10,  10,  10,  10,   0, -10,
+   -10,  10,  10,  10,  10,  10,  10, -10,
+   -10,   5,   0,   0,   0,   0,   5, -10,
+   -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+     5,  10,  10,  10,  10,  10,  10,   5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+     0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,   5,   5,   5,   5,   0, -10,
+    -5,   0,   5,   5,   5,   5,   0,  -5,
+     0,   0,   5,   5,   5,   5,   0,  -5,
+   -10,   5,   5,   5,   5,   5,   0, -10,
+   -10,   0,   5,   0,   0,   0,   0, -10,
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const KING_TABLE_MG: [i32; 64] = [
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -20, -30, -30, -40, -40, -30, -30, -20,
+   -10, -20, -20, -20, -20, -20, -20, -10,
+    20,  20,   0,   0,   0,   0,  20,  20,
+    20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+#[rustfmt::skip]
+const KING_TABLE_EG: [i32; 64] = [
+   -50, -40, -30, -20, -20, -30, -40, -50,
+   -30, -20, -10,   0,   0, -10, -20, -30,
+   -30, -10,  20,  30,  30,  20, -10, -30,
+   -30, -10,  30,  40,  40,  30, -10, -30,
+   -30, -10,  30,  40,  40,  30, -10, -30,
+   -30, -10,  20,  30,  30,  20, -10, -30,
+   -30, -30,   0,   0,   0,   0, -30, -30,
+   -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+fn table(mg: &[i32; 64], eg: &[i32; 64]) -> [Weight; 64] {
+    std::array::from_fn(|square| w(mg[square], eg[square]))
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        Self {
+            piece_values: [w(100, 120), w(320, 300), w(330, 320), w(500, 540), w(950, 980), w(0, 0)],
+            tables: [
+                table(&PAWN_TABLE_MG, &PAWN_TABLE_EG),
+                table(&KNIGHT_TABLE, &KNIGHT_TABLE),
+                table(&BISHOP_TABLE, &BISHOP_TABLE),
+                table(&ROOK_TABLE, &ROOK_TABLE),
+                table(&QUEEN_TABLE, &QUEEN_TABLE),
+                table(&KING_TABLE_MG, &KING_TABLE_EG),
+            ],
+            mobility: [w(0, 0), w(4, 4), w(5, 5), w(2, 4), w(1, 2), w(0, 0)],
+            doubled_pawn: w(-10, -20),
+            isolated_pawn: w(-10, -15),
+            passed_pawn: [w(0, 0), w(5, 10), w(10, 20), w(20, 40), w(35, 70), w(60, 120), w(100, 200), w(0, 0)],
+            pawn_shield: w(12, 0),
+            king_open_file: w(-20, 0),
+            king_attack: [w(0, 0), w(6, 0), w(6, 0), w(8, 0), w(10, 0), w(0, 0)],
+            bishop_pair: w(30, 50),
+            rook_open_file: w(25, 15),
+            rook_semi_open_file: w(12, 8),
+        }
+    }
+}
+
+// why an evaluation file can't be read
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    Read(String),
+    MissingTerm(usize),
+    UnknownTerm(usize, String),
+    InvalidValue(usize, String),
+    ValueCount { line: usize, term: String, expected: usize, found: usize },
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            EvalError::Read(err) => write!(f, "{err}"),
+            EvalError::MissingTerm(line) => write!(f, "line {line}: values without a term"),
+            EvalError::UnknownTerm(line, term) => write!(f, "line {line}: unknown term '{term}'"),
+            EvalError::InvalidValue(line, value) => write!(f, "line {line}: invalid value '{value}'"),
+            EvalError::ValueCount { line, term, expected, found } => {
+                write!(f, "line {line}: {term} takes {expected} values, found {found}")
+            }
+        }
+    }
+}
+
+impl Error for EvalError {}
+
+// which of a term's weights the values in the file are for
+enum Part {
+    Both,
+    Mg,
+    Eg,
+}
+
+impl EvalParams {
+    pub fn from_file(path: &str) -> Result<Self, EvalError> {
+        let text = std::fs::read_to_string(path).map_err(|err| EvalError::Read(format!("{path}: {err}")))?;
+        Self::parse(&text)
+    }
+
+    // reads lines of `term = values`, a term's values can carry on over the following lines and `#` starts a comment,
+    // a single weight takes a middlegame and an endgame value, and the terms that aren't given keep their defaults
+    pub fn parse(text: &str) -> Result<Self, EvalError> {
+        let mut params = Self::default();
+        let mut terms: Vec<(usize, String, Vec<i32>)> = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let values = match line.split_once('=') {
+                Some((term, values)) => {
+                    terms.push((i + 1, term.trim().to_string(), Vec::new()));
+                    values
+                }
+                None => line,
+            };
+            for value in values.split(|c: char| c.is_whitespace() || c == ',').filter(|value| !value.is_empty()) {
+                let (_, _, parsed) = terms.last_mut().ok_or(EvalError::MissingTerm(i + 1))?;
+                parsed.push(value.parse().map_err(|_| EvalError::InvalidValue(i + 1, value.to_string()))?);
+            }
+        }
+        for (line, term, values) in terms {
+            let (weights, part) = params.term(&term).ok_or_else(|| EvalError::UnknownTerm(line, term.clone()))?;
+            let expected = match part {
+                Part::Both => weights.len() * 2,
+                Part::Mg | Part::Eg => weights.len(),
+            };
+            if values.len() != expected {
+                return Err(EvalError::ValueCount { line, term, expected, found: values.len() });
+            }
+            match part {
+                Part::Both => weights.iter_mut().zip(values.chunks(2)).for_each(|(weight, pair)| *weight = w(pair[0], pair[1])),
+                Part::Mg => weights.iter_mut().zip(values).for_each(|(weight, value)| weight.mg = value),
+                Part::Eg => weights.iter_mut().zip(values).for_each(|(weight, value)| weight.eg = value),
+            }
+        }
+        Ok(params)
+    }
+
+    fn term(&mut self, term: &str) -> Option<(&mut [Weight], Part)> {
+        match term {
+            "doubled_pawn" => return Some(single(&mut self.doubled_pawn)),
+            "isolated_pawn" => return Some(single(&mut self.isolated_pawn)),
+            "passed_pawn_mg" => return Some((&mut self.passed_pawn[..], Part::Mg)),
+            "passed_pawn_eg" => return Some((&mut self.passed_pawn[..], Part::Eg)),
+            "pawn_shield" => return Some(single(&mut self.pawn_shield)),
+            "king_open_file" => return Some(single(&mut self.king_open_file)),
+            "bishop_pair" => return Some(single(&mut self.bishop_pair)),
+            "rook_open_file" => return Some(single(&mut self.rook_open_file)),
+            "rook_semi_open_file" => return Some(single(&mut self.rook_semi_open_file)),
+            _ => (),
+        }
+        let (piece, term) = term.split_once('_')?;
+        let piece = PIECE_NAMES.iter().position(|&name| name == piece)?;
+        // only the pieces that move more than a square at a time have mobility and attack the king from afar
+        let ranged = (1..=4).contains(&piece);
+        match term {
+            "value" if piece != 5 => Some(single(&mut self.piece_values[piece])),
+            "table_mg" => Some((&mut self.tables[piece][..], Part::Mg)),
+            "table_eg" => Some((&mut self.tables[piece][..], Part::Eg)),
+            "mobility" if ranged => Some(single(&mut self.mobility[piece])),
+            "king_attack" if ranged => Some(single(&mut self.king_attack[piece])),
+            _ => None,
+        }
+    }
+}
+
+impl Evaluator for EvalParams {
+    fn evaluate(&self, position: &Position) -> f32 {
+        evaluate(position, self) as f32
+    }
+
+    fn pawn_scale(&self) -> f32 {
+        100.0
+    }
+}
+
+fn single(weight: &mut Weight) -> (&mut [Weight], Part) {
+    (std::slice::from_mut(weight), Part::Both)
+}
+
+// the evaluation in centipawns from white's point of view, blending the middlegame and endgame weights by the material left
+pub fn evaluate(position: &Position, params: &EvalParams) -> i32 {
+    let score = side_score(position, Player::One, params) - side_score(position, Player::Two, params);
+    let phase = PIECE_TYPES
+        .iter()
+        .map(|&piece_type| {
+            let count = position.pieces(Player::One, piece_type) | position.pieces(Player::Two, piece_type);
+            count.count_ones() as i32 * PHASE_WEIGHTS[piece_type as usize]
+        })
+        .sum::<i32>()
+        .min(MAX_PHASE);
+    (score.mg * phase + score.eg * (MAX_PHASE - phase)) / MAX_PHASE
+}
+
+fn side_score(position: &Position, player: Player, params: &EvalParams) -> Weight {
+    let opponent = player.other();
+    let occupied = position.occupied();
+    let own_pawns = position.pieces(player, PieceType::Pawn);
+    let enemy_pawns = position.pieces(opponent, PieceType::Pawn);
+    let guarded = squares(enemy_pawns).fold(0, |guarded, square| guarded | PAWN_ATTACKS[opponent.index()][square as usize]);
+    let enemy_king = position.pieces(opponent, PieceType::King).trailing_zeros() as usize;
+    let king_zone = KING_ATTACKS.get(enemy_king).map_or(0, |&attacks| attacks | bit(enemy_king as u8));
+
+    let mut score = Weight::default();
+    for piece_type in PIECE_TYPES {
+        let piece = piece_type as usize;
+        for square in squares(position.pieces(player, piece_type)) {
+            score += params.piece_values[piece] + params.tables[piece][relative(player, square)];
+            let attacks = match piece_type {
+                PieceType::Knight => KNIGHT_ATTACKS[square as usize],
+                PieceType::Bishop => bishop_attacks(square, occupied),
+                PieceType::Rook => rook_attacks(square, occupied),
+                PieceType::Queen => bishop_attacks(square, occupied) | rook_attacks(square, occupied),
+                PieceType::Pawn | PieceType::King => continue,
+            };
+            score += params.mobility[piece] * (attacks & !position.occupied_by(player) & !guarded).count_ones() as i32;
+            score += params.king_attack[piece] * (attacks & king_zone).count_ones() as i32;
+            if piece_type == PieceType::Rook {
+                let file = FILE_A << coords(square).0;
+                if file & own_pawns == 0 {
+                    score += if file & enemy_pawns == 0 { params.rook_open_file } else { params.rook_semi_open_file };
+                }
+            }
+        }
+    }
+    if position.pieces(player, PieceType::Bishop).count_ones() >= 2 {
+        score += params.bishop_pair;
+    }
+    score + pawn_structure(player, own_pawns, enemy_pawns, params) + king_safety(position, player, own_pawns, params)
+}
+
+fn pawn_structure(player: Player, own_pawns: Bitboard, enemy_pawns: Bitboard, params: &EvalParams) -> Weight {
+    let mut score = Weight::default();
+    for x in 0..8 {
+        let count = (own_pawns & FILE_A << x).count_ones() as i32;
+        if count > 1 {
+            score += params.doubled_pawn * (count - 1);
+        }
+    }
+    for square in squares(own_pawns) {
+        let (x, y) = coords(square);
+        let files = adjacent_files(x);
+        if own_pawns & files & !(FILE_A << x) == 0 {
+            score += params.isolated_pawn;
+        }
+        if enemy_pawns & files & ranks_ahead(player, y) == 0 {
+            let rank = if player == Player::One { 7 - y } else { y };
+            score += params.passed_pawn[rank as usize];
+        }
+    }
+    score
+}
+
+fn king_safety(position: &Position, player: Player, own_pawns: Bitboard, params: &EvalParams) -> Weight {
+    let Some(king) = squares(position.pieces(player, PieceType::King)).next() else {
+        return Weight::default();
+    };
+    let (x, y) = coords(king);
+    let files = adjacent_files(x);
+    let near = ranks_ahead(player, y) & !ranks_ahead(player, if player == Player::One { y.saturating_sub(2) } else { (y + 2).min(7) });
+    let mut score = params.pawn_shield * (own_pawns & files & near).count_ones() as i32;
+    for file in x.saturating_sub(1)..=(x + 1).min(7) {
+        if own_pawns & FILE_A << file == 0 {
+            score += params.king_open_file;
+        }
+    }
+    score
+}
+
+// the file and the ones beside it
+fn adjacent_files(x: u8) -> Bitboard {
+    let file = FILE_A << x;
+    file | (file << 1 & !FILE_A) | (file >> 1 & !(FILE_A << 7))
+}
+
+// the ranks in front of the given one as the player sees the board
+fn ranks_ahead(player: Player, y: u8) -> Bitboard {
+    match player {
+        Player::One => (1u64 << (y * 8)) - 1,
+        Player::Two => u64::MAX.checked_shl((y as u32 + 1) * 8).unwrap_or(0),
+    }
+}
+
+// the square as white sees it, black's pieces use the tables upside down
+fn relative(player: Player, square: u8) -> usize {
+    match player {
+        Player::One => square as usize,
+        Player::Two => square as usize ^ 56,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    fn score(fen: &str) -> i32 {
+        evaluate(Game::from_fen(fen).unwrap().position(), &EvalParams::default())
+    }
+
+    // the same position with the colors swapped
+    fn mirror(fen: &str) -> String {
+        let mut fields = fen.split_whitespace();
+        let placement = fields.next().unwrap().split('/').rev().collect::<Vec<&str>>().join("/");
+        let placement = placement.chars().map(|c| if c.is_ascii_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() });
+        let side = if fields.next() == Some("w") { "b" } else { "w" };
+        format!("{} {side} - - 0 1", placement.collect::<String>())
+    }
+
+    #[test]
+    fn evaluation_is_symmetric() {
+        assert_eq!(score(crate::START_FEN), 0);
+        for fen in [
+            "r1bqk2r/pp2bppp/2n1pn2/3p4/2PP4/2N2N2/PP3PPP/R1BQKB1R w - - 0 1",
+            "8/5pk1/6p1/1P6/8/6P1/5PK1/4R3 w - - 0 1",
+            "2r3k1/1q3ppp/8/8/8/8/5PPP/2R1Q1K1 w - - 0 1",
+        ] {
+            assert_eq!(score(fen), -score(&mirror(fen)), "{fen}");
+        }
+    }
+
+    #[test]
+    fn positional_terms_count() {
+        // a passed pawn against a blocked one
+        assert!(score("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1") > score("4k3/8/3p4/3P4/8/8/8/4K3 w - - 0 1") + 100);
+        // doubled and isolated pawns against connected ones
+        assert!(score("4k3/pp6/8/8/8/P7/P7/4K3 w - - 0 1") < score("4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1"));
+        // a bishop pair against bishop and knight
+        assert!(score("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1") > score("4k3/8/8/8/8/8/8/2B1KN2 w - - 0 1"));
+        // a rook on an open file against one behind its own pawn
+        assert!(score("4k3/1p6/8/8/8/8/1P6/R3K3 w - - 0 1") > score("4k3/1p6/8/8/8/8/P7/1R2K3 w - - 0 1"));
+        // a castled king behind its pawns against one without them
+        assert!(score("3qk3/8/8/8/8/8/5PPP/3Q2K1 w - - 0 1") > score("3qk3/8/8/8/8/8/PPP5/3Q2K1 w - - 0 1"));
+    }
+
+    #[test]
+    fn weights_taper_with_material() {
+        // the king belongs in the center once the pieces are gone, but not with the queens on
+        assert!(score("4k3/8/8/8/3K4/8/8/8 w - - 0 1") > score("4k3/8/8/8/8/8/8/6K1 w - - 0 1"));
+        assert!(score("r2qk2r/8/8/8/3K4/8/8/RR1Q4 w - - 0 1") < score("r2qk2r/8/8/8/8/8/8/RR1Q2K1 w - - 0 1"));
+    }
+
+    #[test]
+    fn parses_evaluation_files() {
+        let params = EvalParams::parse(
+            "# knights are worth more\nknight_value = 400 350\nbishop_pair = 0 0\npassed_pawn_eg = 0 1 2 3\n  4 5 6 7\n",
+        )
+        .unwrap();
+        assert_eq!(params.piece_values[1], w(400, 350));
+        assert_eq!(params.bishop_pair, w(0, 0));
+        assert_eq!(params.passed_pawn.map(|weight| weight.eg), [0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(params.passed_pawn[3].mg, EvalParams::default().passed_pawn[3].mg);
+        assert_eq!(params.tables, EvalParams::default().tables);
+
+        assert_eq!(EvalParams::parse("king_mobility = 1 2"), Err(EvalError::UnknownTerm(1, "king_mobility".to_string())));
+        assert_eq!(EvalParams::parse("\nrook_open_file = 10"), Err(EvalError::ValueCount {
+            line: 2,
+            term: "rook_open_file".to_string(),
+            expected: 2,
+            found: 1,
+        }));
+        assert_eq!(EvalParams::parse("pawn_value = 100 x"), Err(EvalError::InvalidValue(1, "x".to_string())));
+        assert_eq!(EvalParams::parse("100 120"), Err(EvalError::MissingTerm(1)));
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::{eval::EvalParams, model::Model, position::Position};
+
+// scores positions for the search from white's point of view, on a scale of its own
+pub trait Evaluator: Send + Sync {
+    fn evaluate(&self, position: &Position) -> f32;
+
+    // scores many positions at once, which is much quicker for some evaluators
+    fn evaluate_batch(&self, positions: &[Position]) -> Vec<f32> {
+        positions.iter().map(|position| self.evaluate(position)).collect()
+    }
+
+    // how many points of the evaluation make a pawn
+    fn pawn_scale(&self) -> f32;
+
+    // whether the search should score the last ply in one batch instead of position by position
+    fn prefers_batches(&self) -> bool {
+        false
+    }
+}
+
+impl<E: Evaluator + ?Sized> Evaluator for &E {
+    fn evaluate(&self, position: &Position) -> f32 {
+        (**self).evaluate(position)
+    }
+
+    fn evaluate_batch(&self, positions: &[Position]) -> Vec<f32> {
+        (**self).evaluate_batch(positions)
+    }
+
+    fn pawn_scale(&self) -> f32 {
+        (**self).pawn_scale()
+    }
+
+    fn prefers_batches(&self) -> bool {
+        (**self).prefers_batches()
+    }
+}
+
+// counts the players' material in pawns
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Material;
+
+impl Evaluator for Material {
+    fn evaluate(&self, position: &Position) -> f32 {
+        position.material() as f32
+    }
+
+    fn pawn_scale(&self) -> f32 {
+        1.0
+    }
+}
+
+// a weighted sum of other evaluations, in centipawns
+#[derive(Clone)]
+pub struct Blend<'a> {
+    parts: Vec<(Arc<dyn Evaluator + 'a>, f32)>,
+}
+
+impl<'a> Blend<'a> {
+    pub fn new(parts: Vec<(Arc<dyn Evaluator + 'a>, f32)>) -> Self {
+        Self { parts }
+    }
+}
+
+impl Evaluator for Blend<'_> {
+    fn evaluate(&self, position: &Position) -> f32 {
+        self.parts
+            .iter()
+            .map(|(evaluator, weight)| evaluator.evaluate(position) / evaluator.pawn_scale() * 100.0 * weight)
+            .sum()
+    }
+
+    fn evaluate_batch(&self, positions: &[Position]) -> Vec<f32> {
+        let mut scores = vec![0.0; positions.len()];
+        for (evaluator, weight) in &self.parts {
+            let scale = 100.0 / evaluator.pawn_scale() * weight;
+            for (score, part) in scores.iter_mut().zip(evaluator.evaluate_batch(positions)) {
+                *score += part * scale;
+            }
+        }
+        scores
+    }
+
+    fn pawn_scale(&self) -> f32 {
+        100.0
+    }
+
+    fn prefers_batches(&self) -> bool {
+        self.parts.iter().any(|(evaluator, _)| evaluator.prefers_batches())
+    }
+}
+
+// the evaluations that can be picked by name
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EvaluatorKind {
+    Material,
+    Heuristic,
+    Model,
+    Blend,
+}
+
+pub const EVALUATOR_KINDS: [EvaluatorKind; 4] = [EvaluatorKind::Material, EvaluatorKind::Heuristic, EvaluatorKind::Model, EvaluatorKind::Blend];
+
+impl EvaluatorKind {
+    pub fn uses_model(self) -> bool {
+        matches!(self, EvaluatorKind::Model | EvaluatorKind::Blend)
+    }
+
+    // the blend weighs the model and the hand-crafted evaluation evenly. panics without a model if the evaluation
+    // uses one
+    pub fn evaluator<'a>(self, model: Option<&'a Model>, params: &Arc<EvalParams>) -> Arc<dyn Evaluator + 'a> {
+        match self {
+            EvaluatorKind::Material => Arc::new(Material),
+            EvaluatorKind::Heuristic => params.clone(),
+            EvaluatorKind::Model => Arc::new(model.expect("the model evaluation needs a model")),
+            EvaluatorKind::Blend => Arc::new(Blend::new(vec![
+                (Arc::new(model.expect("the blended evaluation needs a model")), 0.5),
+                (params.clone(), 0.5),
+            ])),
+        }
+    }
+}
+
+impl Display for EvaluatorKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            EvaluatorKind::Material => "material",
+            EvaluatorKind::Heuristic => "heuristic",
+            EvaluatorKind::Model => "model",
+            EvaluatorKind::Blend => "blend",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for EvaluatorKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        EVALUATOR_KINDS
+            .into_iter()
+            .find(|kind| kind.to_string().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("unknown evaluation '{name}', expected material, heuristic, model or blend"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    #[test]
+    fn blends_evaluations_in_centipawns() {
+        let game = Game::from_fen("4k3/8/8/8/8/8/PPP5/4K3 w - - 0 1").unwrap();
+        let params = Arc::new(EvalParams::default());
+        let heuristic = params.evaluate(game.position());
+        assert_eq!(Material.evaluate(game.position()), 3.0);
+
+        let blend = Blend::new(vec![(Arc::new(Material), 0.25), (params, 0.75)]);
+        let expected = 0.25 * 300.0 + 0.75 * heuristic;
+        assert!((blend.evaluate(game.position()) - expected).abs() < 0.01);
+        assert_eq!(blend.evaluate_batch(&[*game.position(); 2]), vec![blend.evaluate(game.position()); 2]);
+        assert!(!blend.prefers_batches());
+    }
+
+    #[test]
+    fn evaluations_by_name() {
+        for kind in EVALUATOR_KINDS {
+            assert_eq!(kind.to_string().parse(), Ok(kind));
+        }
+        assert_eq!("Heuristic".parse(), Ok(EvaluatorKind::Heuristic));
+        assert!("stockfish".parse::<EvaluatorKind>().is_err());
+        assert!(EvaluatorKind::Blend.uses_model() && !EvaluatorKind::Heuristic.uses_model());
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+};
+
+use crate::{
+    bishop::Bishop,
+    game::Board,
+    king::King,
+    knight::Knight,
+    pawn::Pawn,
+    piece::{Construct, Piece},
+    player::Player,
+    queen::Queen,
+    rook::Rook,
+};
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenError {
+    MissingField(&'static str),
+    TooManyFields,
+    RankCount(usize),
+    RankLength(usize),
+    InvalidPiece(char),
+    ActiveColor(String),
+    Castling(String),
+    EnPassant(String),
+    HalfMoveClock(String),
+    FullMoveClock(String),
+    KingCount(Player, usize),
+    PawnOnBackRank(String),
+    TooManyPieces(Player),
+    OpponentInCheck,
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            FenError::MissingField(field) => write!(f, "missing {field} field"),
+            FenError::TooManyFields => write!(f, "too many fields"),
+            FenError::RankCount(count) => write!(f, "expected 8 ranks, found {count}"),
+            FenError::RankLength(rank) => write!(f, "rank {rank} does not have 8 squares"),
+            FenError::InvalidPiece(c) => write!(f, "invalid piece '{c}'"),
+            FenError::ActiveColor(color) => write!(f, "invalid active color '{color}'"),
+            FenError::Castling(castling) => write!(f, "invalid castling rights '{castling}'"),
+            FenError::EnPassant(square) => write!(f, "invalid en passant square '{square}'"),
+            FenError::HalfMoveClock(clock) => write!(f, "invalid halfmove clock '{clock}'"),
+            FenError::FullMoveClock(clock) => write!(f, "invalid fullmove number '{clock}'"),
+            FenError::KingCount(player, count) => write!(f, "{player} has {count} kings"),
+            FenError::PawnOnBackRank(square) => write!(f, "pawn on back rank at {square}"),
+            FenError::TooManyPieces(player) => write!(f, "{player} has too many pieces"),
+            FenError::OpponentInCheck => write!(f, "the side not to move is in check"),
+        }
+    }
+}
+
+impl Error for FenError {}
+
+pub(crate) fn piece_from_fen(c: char) -> Option<Box<dyn Piece>> {
+    let player = if c.is_ascii_uppercase() {
+        Player::One
+    } else {
+        Player::Two
+    };
+    let piece: Box<dyn Piece> = match c.to_ascii_lowercase() {
+        'p' => Box::new(Pawn::new(player)),
+        'n' => Box::new(Knight::new(player)),
+        'b' => Box::new(Bishop::new(player)),
+        'r' => Box::new(Rook::new(player)),
+        'q' => Box::new(Queen::new(player)),
+        'k' => Box::new(King::new(player)),
+        _ => return None,
+    };
+    Some(piece)
+}
+
+pub(crate) fn piece_to_fen(piece: &dyn Piece) -> char {
+    let c = match piece.name() {
+        "pawn" => 'p',
+        "knight" => 'n',
+        "bishop" => 'b',
+        "rook" => 'r',
+        "queen" => 'q',
+        "king" => 'k',
+        _ => unreachable!("Invalid piece name!"),
+    };
+    match piece.player() {
+        Player::One => c.to_ascii_uppercase(),
+        Player::Two => c,
+    }
+}
+
+pub(crate) fn parse_placement(placement: &str) -> Result<Board, FenError> {
+    let ranks = placement.split('/').collect::<Vec<&str>>();
+    if ranks.len() != 8 {
+        return Err(FenError::RankCount(ranks.len()));
+    }
+    let mut board: Board = vec![vec![None; 8]; 8];
+    for (y, rank) in ranks.iter().enumerate() {
+        let mut x = 0;
+        for c in rank.chars() {
+            if let Some(empty) = c.to_digit(10) {
+                if empty == 0 || empty > 8 {
+                    return Err(FenError::InvalidPiece(c));
+                }
+                x += empty as usize;
+            } else {
+                let piece = piece_from_fen(c).ok_or(FenError::InvalidPiece(c))?;
+                if x >= 8 {
+                    return Err(FenError::RankLength(8 - y));
+                }
+                board[y][x] = Some(piece);
+                x += 1;
+            }
+            if x > 8 {
+                return Err(FenError::RankLength(8 - y));
+            }
+        }
+        if x != 8 {
+            return Err(FenError::RankLength(8 - y));
+        }
+    }
+    Ok(board)
+}
@@ -1,66 +1,119 @@
 use crate::{
     bishop::Bishop,
+    book::Book,
+    bitboard::{coords, square, squares},
+    endgame::EndgameEvaluator,
+    evaluator::{Evaluator, Material},
+    fen::{self, FenError},
     king::King,
     knight::Knight,
     model::Model,
+    outcome::{Draw, GameResult, Outcome, Termination},
     pawn::Pawn,
-    piece::{Construct, Move, Piece},
+    piece::{self, Construct, Piece},
     player::Player,
+    position::{Move, PieceType, Position},
     queen::Queen,
     rook::Rook,
+    san,
+    search::{plies_to_mate, tablebase_score, CutoffTables, Score, SearchConfig, SearchResult, SearchStats, Searcher},
+    syzygy::Tablebase,
+    time_manager::TimeControl,
+    tt::TranspositionTable,
 };
 use colored::Colorize;
 use rand::{Rng, seq::SliceRandom};
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use std::{
     cmp::{max, min},
+    collections::HashSet,
     fmt::{Display, Error, Formatter},
-    io, collections::HashMap, sync::{Mutex, Arc},
+    sync::{Arc, OnceLock, atomic::{AtomicBool, Ordering}},
+    time::{Duration, Instant},
 };
 
 pub type Square = Option<Box<dyn Piece>>;
 pub type Board = Vec<Vec<Square>>;
 pub type Matrix = [[[f32; 8]; 8]; 13];
-pub type Cache = Arc<Mutex<HashMap<String,(f32, u8)>>>;
+pub type Cache = Arc<TranspositionTable>;
 // const NUM_THREADS: usize = 4;
-const DEFAULT_SEARCH_DEPTH: u8 = 2;
-const HALF_MOVE_LIMIT: u8 = 100;
+pub const DEFAULT_SEARCH_DEPTH: u8 = 2;
+// how deep a search limited by time can go
+pub const MAX_SEARCH_DEPTH: u8 = 64;
+// the fifty-move rule, which the search treats as a draw since a player can claim it
+pub(crate) const HALF_MOVE_LIMIT: u8 = 100;
+// the seventy-five-move rule, which ends the game without a claim
+const HALF_MOVE_HARD_LIMIT: u8 = 150;
 pub const DEFAULT_EPSILON_DECAY: f64 = 0.98;
 
 #[derive(Clone)]
 pub struct Game<'a> {
-    board: Vec<Vec<Square>>,
-    current_player: Player,
-    last_double: Option<(u8, u8)>,
-    king_one: (u8, u8),
-    king_two: (u8, u8),
-    has_p1_king_moved: bool,
-    has_p1_left_rook_moved: bool,
-    has_p1_right_rook_moved: bool,
-    has_p2_king_moved: bool,
-    has_p2_left_rook_moved: bool,
-    has_p2_right_rook_moved: bool,
-    p1_pieces: Vec<(u8, u8)>,
-    p2_pieces: Vec<(u8, u8)>,
+    position: Position,
     p1_taken: [u8; 5],
     p2_taken: [u8; 5],
-    half_move_clock: u8,
-    full_move_clock: u32,
-    in_simulation: bool,
     two_player: bool,
+    // the model the engine learns in self-play
     model: Option<&'a Model>,
+    evaluator: Arc<dyn Evaluator + 'a>,
     computer_player: Option<Player>,
-    cache: Cache,
+    // allocated on the first search unless one is passed in, so games that are never searched don't pay for it
+    cache: OnceLock<Cache>,
+    // the killer moves and history of the engine's searches, which order the moves of the next search
+    cutoffs: CutoffTables,
     rl_training: bool,
     search_depth: u8,
+    search_config: SearchConfig,
+    time_control: Option<TimeControl>,
+    // what is left of each player's time control, which their moves are timed by and the engine budgets its
+    // searches by
+    clocks: [Option<TimeControl>; 2],
     epsilon_greedy: bool,
     epsilon: f64,
     epsilon_decay_rate: f64,
     allow_hints: bool,
-    winner: Option<Player>
-    // TODO: opening_book
+    result: Option<GameResult>,
+    stop: Arc<AtomicBool>,
+    history: Vec<String>,
+    // the hash of the position before each move, for spotting repetitions
+    positions: Vec<u64>,
+    states: Vec<Arc<GameState>>,
+    undone: Vec<(String, Arc<GameState>)>,
+    start_fen: String,
+    book: Option<Arc<Book>>,
+    // how many moves of the game are played from the book
+    book_depth: u32,
+    tablebase: Option<Arc<Tablebase>>,
 }
 
+// the rule state of a position, kept for every move so that moves can be taken back
+struct GameState {
+    position: Position,
+    p1_taken: [u8; 5],
+    p2_taken: [u8; 5],
+}
+
+// why a move entered by a player can't be played
+#[derive(Debug, PartialEq)]
+pub enum MoveError {
+    NotYourPiece,
+    Invalid,
+    OwnPiece,
+    IntoCheck,
+}
+
+impl Display for MoveError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            MoveError::NotYourPiece => write!(f, "You must move one of your own pieces"),
+            MoveError::Invalid => write!(f, "Invalid move"),
+            MoveError::OwnPiece => write!(f, "You can't take your own piece"),
+            MoveError::IntoCheck => write!(f, "You can't put yourself in check"),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
 impl<'a> Game<'a> {
     pub fn new(
         two_player: bool,
@@ -75,35 +128,37 @@ impl<'a> Game<'a> {
         cache: Option<Cache>
     ) -> Self {
         Self {
-            board: setup_board(),
-            current_player: Player::One,
-            last_double: None,
-            king_one: (4, 7),
-            king_two: (4, 0),
-            has_p1_king_moved: false,
-            has_p1_left_rook_moved: false,
-            has_p1_right_rook_moved: false,
-            has_p2_king_moved: false,
-            has_p2_left_rook_moved: false,
-            has_p2_right_rook_moved: false,
-            p1_pieces: vec![(0, 7), (1, 7), (2, 7), (3, 7), (4, 7), (5, 7), (6, 7), (7, 7), (0, 6), (1, 6), (2, 6), (3, 6), (4, 6), (5, 6), (6, 6), (7, 6)],
-            p2_pieces: vec![(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0), (6, 0), (7, 0), (0, 1), (1, 1), (2, 1), (3, 1), (4, 1), (5, 1), (6, 1), (7, 1)],
+            position: Position::from_board(&setup_board(), Player::One, 0b1111, None, 0, 1),
             p1_taken: [0; 5],
             p2_taken: [0; 5],
-            half_move_clock: 0,
-            full_move_clock: 1,
-            in_simulation: false,
             two_player,
             model,
+            evaluator: Arc::new(EndgameEvaluator::new(match model {
+                Some(model) => Arc::new(model),
+                None => Arc::new(Material),
+            })),
             computer_player,
-            cache: cache.unwrap_or(Arc::new(Mutex::new(HashMap::new()))),
+            cache: cache.map(OnceLock::from).unwrap_or_default(),
+            cutoffs: CutoffTables::default(),
             rl_training,
             search_depth: search_depth.unwrap_or(DEFAULT_SEARCH_DEPTH),
+            search_config: SearchConfig::default(),
+            time_control: None,
+            clocks: [None; 2],
             epsilon_greedy,
             epsilon: epsilon.unwrap_or(0.0),
             epsilon_decay_rate: epsilon_decay_rate.unwrap_or(DEFAULT_EPSILON_DECAY),
             allow_hints,
-            winner: None,
+            result: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            history: Vec::new(),
+            positions: Vec::new(),
+            states: Vec::new(),
+            undone: Vec::new(),
+            start_fen: fen::START_FEN.to_string(),
+            book: None,
+            book_depth: 0,
+            tablebase: None,
         }
     }
 
@@ -123,27 +178,444 @@ impl<'a> Game<'a> {
         Self::new(false, None, true, model, search_depth, epsilon_greedy, false, initial_epsilon, epsilon_decay, cache)
     }
 
-    fn get_best_move(&mut self) -> Option<((u8, u8), (u8, u8))> {
-        println!("Thinking...");
-        self.in_simulation = true;
-        let now = std::time::SystemTime::now();
-        // let possible_moves = self.get_moves_sorted(true);
-        let possible_moves = self.get_possible_moves(self.current_player);
+    pub fn engine(model: Option<&'a Model>, search_depth: Option<u8>, cache: Option<Cache>) -> Self {
+        Self::new(false, None, false, model, search_depth, false, false, None, None, cache)
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let mut game = Self::new(true, None, false, None, None, false, false, None, None, None);
+        game.load_fen(fen)?;
+        Ok(game)
+    }
+
+    // replaces the position with the one described by the fen, leaving the game settings untouched
+    pub fn load_fen(&mut self, fen: &str) -> Result<(), FenError> {
+        let mut fields = fen.split_whitespace();
+        let placement = fields.next().ok_or(FenError::MissingField("piece placement"))?;
+        let active = fields.next().ok_or(FenError::MissingField("active color"))?;
+        let castling = fields.next().ok_or(FenError::MissingField("castling"))?;
+        let en_passant = fields.next().ok_or(FenError::MissingField("en passant"))?;
+        let half_move = fields.next().unwrap_or("0");
+        let full_move = fields.next().unwrap_or("1");
+        if fields.next().is_some() {
+            return Err(FenError::TooManyFields);
+        }
+
+        let board = fen::parse_placement(placement)?;
+        let side = match active {
+            "w" => Player::One,
+            "b" => Player::Two,
+            _ => return Err(FenError::ActiveColor(active.to_string())),
+        };
+        let half_move_clock = half_move.parse().map_err(|_| FenError::HalfMoveClock(half_move.to_string()))?;
+        let full_move_clock = match full_move.parse() {
+            Ok(clock) if clock > 0 => clock,
+            _ => return Err(FenError::FullMoveClock(full_move.to_string())),
+        };
+        let rights = ['K', 'Q', 'k', 'q']
+            .iter()
+            .enumerate()
+            .fold(0, |rights, (i, &right)| rights | (castling.contains(right) as u8) << i);
+        let mut game = self.clone();
+        game.position = Position::from_board(&board, side, rights, None, half_move_clock, full_move_clock);
+        game.result = None;
+        game.history = Vec::new();
+        game.positions = Vec::new();
+        game.states = Vec::new();
+        game.undone = Vec::new();
+
+        for y in 0..8 {
+            for x in 0..8 {
+                if game.get((x, y)).is_some_and(|piece| piece.is_type::<Pawn>() && (y == 0 || y == 7)) {
+                    return Err(FenError::PawnOnBackRank(format_coord(&(x, y))));
+                }
+            }
+        }
+        for player in [Player::One, Player::Two] {
+            let kings = game.position.pieces(player, PieceType::King).count_ones() as usize;
+            if kings != 1 {
+                return Err(FenError::KingCount(player, kings));
+            }
+        }
+        game.p1_taken = game.count_taken(Player::Two)?;
+        game.p2_taken = game.count_taken(Player::One)?;
+
+        if castling != "-" && (castling.is_empty() || !castling.chars().all(|c| "KQkq".contains(c))) {
+            return Err(FenError::Castling(castling.to_string()));
+        }
+        for player in [Player::One, Player::Two] {
+            let y = match player {
+                Player::One => 7,
+                Player::Two => 0,
+            };
+            let is_rook = |x| game.get((x, y)).is_some_and(|piece| piece.is_type::<Rook>() && piece.player() == player);
+            if (!game.has_king_moved(player) && game.get_king(player) != (4, y))
+                || (!game.has_left_rook_moved(player) && !is_rook(0))
+                || (!game.has_right_rook_moved(player) && !is_rook(7))
+            {
+                return Err(FenError::Castling(castling.to_string()));
+            }
+        }
+
+        if en_passant != "-" {
+            let target = parse_coord(en_passant).ok_or(FenError::EnPassant(en_passant.to_string()))?;
+            let (target_y, pawn_y) = match side {
+                Player::One => (2, 3),
+                Player::Two => (5, 4),
+            };
+            let pawn = (target.0, pawn_y);
+            let pawn_is_valid = game
+                .get(pawn)
+                .is_some_and(|piece| piece.is_type::<Pawn>() && piece.player() != side);
+            let behind = (target.0, 2 * target_y - pawn_y);
+            if target.1 != target_y || !pawn_is_valid || !game.square_is_none(target) || !game.square_is_none(behind) {
+                return Err(FenError::EnPassant(en_passant.to_string()));
+            }
+            game.position.set_en_passant(Some(square(target)));
+        }
+
+        if game.in_check(side.other()) {
+            return Err(FenError::OpponentInCheck);
+        }
+        *self = game;
+        self.start_fen = self.to_fen();
+        Ok(())
+    }
+
+    // sets the board back to the position the game started from
+    pub fn restart(&mut self) {
+        let fen = self.start_fen.clone();
+        self.load_fen(&fen).expect("Invalid start position!");
+        self.clocks = [self.time_control; 2];
+    }
+
+    pub fn set_search_config(&mut self, search_config: SearchConfig) {
+        self.search_config = search_config;
+    }
+
+    pub fn search_config(&self) -> SearchConfig {
+        self.search_config
+    }
+
+    // how the search scores positions, the model if the game has one and material otherwise. whichever it is defers
+    // to the endgame tables in the positions they solve
+    pub fn set_evaluator(&mut self, evaluator: Arc<dyn Evaluator + 'a>) {
+        self.evaluator = Arc::new(EndgameEvaluator::new(evaluator));
+    }
+
+    pub fn set_book(&mut self, book: Option<Arc<Book>>, book_depth: u32) {
+        self.book = book;
+        self.book_depth = book_depth;
+    }
+
+    // endgame tablebases for the search to probe
+    pub fn set_tablebase(&mut self, tablebase: Option<Arc<Tablebase>>) {
+        self.tablebase = tablebase;
+    }
+
+    // a move picked from the opening book, while the game is within the book depth and the book has the position
+    pub fn book_move(&self) -> Option<piece::Move> {
+        let book = self.book.as_ref()?;
+        let plies = (self.position.full_move_clock() - 1) * 2 + (self.current_player() == Player::Two) as u32;
+        if plies >= self.book_depth * 2 {
+            return None;
+        }
+        book.pick(&self.position).map(|mov| mov.in_position(&self.position))
+    }
+
+    // starts both players with the same time control, which limits how long the engine thinks about each move on top
+    // of the search depth, and which a player loses on when it is a clock that runs out
+    pub fn set_time_control(&mut self, time_control: Option<TimeControl>) {
+        self.time_control = time_control;
+        self.clocks = [time_control; 2];
+    }
+
+    pub fn time_control(&self) -> Option<TimeControl> {
+        self.time_control
+    }
+
+    // what is left of the player's time control
+    pub fn clock(&self, player: Player) -> Option<TimeControl> {
+        self.clocks[player.index()]
+    }
+
+    // takes the time the current player spent on their move off their clock, before the move is played. if the time
+    // ran out first they lose, or draw if their opponent couldn't have checkmated them, and false is returned
+    pub fn punch_clock(&mut self, elapsed: Duration) -> bool {
+        let player = self.current_player();
+        let Some(clock) = self.clocks[player.index()] else {
+            return true;
+        };
+        self.clocks[player.index()] = clock.after_move(elapsed);
+        if self.clocks[player.index()].is_some() {
+            return true;
+        }
+        self.result = Some(if self.position.has_mating_material(player.other()) {
+            GameResult::Win(player.other(), Termination::Timeout)
+        } else {
+            GameResult::Draw(Draw::TimeoutVsInsufficientMaterial)
+        });
+        false
+    }
+
+    // the player whose time ran out, if that ended the game
+    pub fn flagged(&self) -> Option<Player> {
+        match self.result {
+            Some(GameResult::Win(winner, Termination::Timeout)) => Some(winner.other()),
+            Some(GameResult::Draw(Draw::TimeoutVsInsufficientMaterial)) => Some(self.current_player()),
+            _ => None,
+        }
+    }
+
+    // infers how many of the opponent's pieces were captured from what is left on the board
+    fn count_taken(&self, player: Player) -> Result<[u8; 5], FenError> {
+        let mut counts = [0u8; 5];
+        for position in self.get_pieces(player) {
+            match self.get(position).unwrap().name() {
+                "pawn" => counts[0] += 1,
+                "rook" => counts[1] += 1,
+                "bishop" => counts[2] += 1,
+                "knight" => counts[3] += 1,
+                "queen" => counts[4] += 1,
+                _ => (),
+            }
+        }
+        let initial = [8u8, 2, 2, 2, 1];
+        let promoted: u8 = (1..5).map(|i| counts[i].saturating_sub(initial[i])).sum();
+        if counts[0] + promoted > 8 || self.get_pieces(player).len() > 16 {
+            return Err(FenError::TooManyPieces(player));
+        }
+        let mut taken = [0u8; 5];
+        taken[0] = 8 - counts[0] - promoted;
+        for i in 1..5 {
+            taken[i] = initial[i].saturating_sub(counts[i]);
+        }
+        Ok(taken)
+    }
+
+    // searches every move to the given depth, returning None if there are no moves or the search was stopped
+    pub fn search(&mut self, depth: u8) -> Option<(piece::Move, f32)> {
+        let possible_moves = self.position.legal_moves();
         if possible_moves.is_empty() {
             return None;
         }
+        let (move_evals, _) = self.search_moves(&possible_moves, depth, None, false)?;
+        Some(self.pick_best(&move_evals))
+    }
+
+    // searches one ply deeper at a time until the depth or the time budget is reached, each completed depth
+    // ordering the moves for the next, and returns what the deepest search that finished found.
+    // report is called with the result so far whenever a depth is completed
+    pub fn iterative_deepening(
+        &self,
+        max_depth: u8,
+        budget: Option<Duration>,
+        report: impl FnMut(&SearchResult),
+    ) -> Option<SearchResult> {
+        self.deepen(max_depth, budget, false, report).map(|(_, result)| result)
+    }
 
-        let move_evals: Vec<(((u8, u8), (u8, u8)), f32)> = possible_moves.par_iter().map(|&mov| {
-            let mut game = self.clone();
-            game.move_piece(mov.0, mov.1);
-            let score = game.minimax_search(self.search_depth - 1, game.current_player.is_maximizing(), f32::MIN, f32::MAX);
-            (mov, score)
+    // searches with iterative deepening to the search depth or for as long as the current player's time control allows.
+    // the time spent is left for punch_clock to take off their clock
+    pub fn think(&mut self, report: impl FnMut(&SearchResult)) -> Option<SearchResult> {
+        self.timed_deepen(false, report).map(|(_, result)| result)
+    }
+
+    fn timed_deepen(&mut self, every_score: bool, report: impl FnMut(&SearchResult)) -> Option<(Vec<(Move, f32)>, SearchResult)> {
+        self.deepen(self.search_depth, self.budget(self.current_player()), every_score, report)
+    }
+
+    // how long the engine can think for the player, by what is left of their time control
+    fn budget(&self, player: Player) -> Option<Duration> {
+        self.clocks[player.index()].map(|clock| clock.budget())
+    }
+
+    fn deepen(
+        &self,
+        max_depth: u8,
+        budget: Option<Duration>,
+        every_score: bool,
+        mut report: impl FnMut(&SearchResult),
+    ) -> Option<(Vec<(Move, f32)>, SearchResult)> {
+        let start = Instant::now();
+        let deadline = budget.map(|budget| start + budget);
+        let mut position = self.position;
+        let mut moves = position.legal_moves();
+        if moves.is_empty() {
+            return None;
+        }
+        if let Some(result) = self.tablebase_moves(start) {
+            report(&result.1);
+            return Some(result);
+        }
+        let mut result = None;
+        let mut stats = SearchStats::default();
+        for depth in 1..=max_depth.max(1) {
+            // the first depth always finishes so that there is a move to play
+            let deadline = if depth == 1 { None } else { deadline };
+            self.cutoffs.age();
+            let Some((mut move_evals, depth_stats)) = self.search_moves(&moves, depth, deadline, every_score) else {
+                break;
+            };
+            stats += depth_stats;
+            let (best_move, score) = self.pick_best(&move_evals);
+            let pv = self.principal_variation(best_move, depth);
+            let search_result = SearchResult {
+                best_move,
+                score: self.score(score),
+                pv,
+                depth,
+                nodes: stats.nodes,
+                elapsed: start.elapsed(),
+                tt_probes: stats.tt_probes,
+                tt_hits: stats.tt_hits,
+            };
+            report(&search_result);
+            // the best moves of this depth are searched first at the next
+            let sign = if self.is_maximizing() { -1.0 } else { 1.0 };
+            move_evals.sort_by(|(_, score1), (_, score2)| (sign * score1).total_cmp(&(sign * score2)));
+            moves = move_evals.iter().map(|&(mov, _)| mov).collect();
+            result = Some((move_evals, search_result));
+            // another depth is unlikely to finish in the time that is left
+            if budget.is_some_and(|budget| start.elapsed() >= budget / 2) {
+                break;
+            }
+        }
+        result
+    }
+
+    fn cache(&self) -> &Cache {
+        self.cache.get_or_init(Cache::default)
+    }
+
+    // scores each of the moves by searching to the given depth along with what the search took, or returns None if
+    // the search was stopped or ran out of time. the first move is searched in full, and unless every score is wanted
+    // the rest only far enough to show they are worse than it, so the search is quickest with the best move first
+    fn search_moves(
+        &self,
+        moves: &[Move],
+        depth: u8,
+        deadline: Option<Instant>,
+        every_score: bool,
+    ) -> Option<(Vec<(Move, f32)>, SearchStats)> {
+        let (&first, rest) = moves.split_first()?;
+        let (first_score, mut stats) = self.search_root_move(first, depth, deadline, (f32::MIN, f32::MAX))?;
+        let maximizing = self.is_maximizing();
+        let zero_window = self.search_config.principal_variation && !every_score;
+        let move_evals: Option<Vec<(Move, f32, SearchStats)>> = rest.par_iter().map(|&mov| {
+            if !zero_window {
+                let (score, move_stats) = self.search_root_move(mov, depth, deadline, (f32::MIN, f32::MAX))?;
+                return Some((mov, score, move_stats));
+            }
+            // a window just short of the first move's score, so that a move as good as it is searched again for
+            // its exact score and can be picked at random with it
+            let window = if maximizing {
+                (first_score.next_down(), first_score)
+            } else {
+                (first_score, first_score.next_up())
+            };
+            let (score, mut move_stats) = self.search_root_move(mov, depth, deadline, window)?;
+            let worse = if maximizing { score < first_score } else { score > first_score };
+            if worse {
+                return Some((mov, score, move_stats));
+            }
+            let window = if maximizing { (window.0, f32::MAX) } else { (f32::MIN, window.1) };
+            let (score, research_stats) = self.search_root_move(mov, depth, deadline, window)?;
+            move_stats += research_stats;
+            Some((mov, score, move_stats))
         }).collect();
+        if self.is_stopped() {
+            return None;
+        }
+        let move_evals = std::iter::once((first, first_score))
+            .chain(move_evals?.into_iter().map(|(mov, score, move_stats)| {
+                stats += move_stats;
+                (mov, score)
+            }))
+            .collect();
+        Some((move_evals, stats))
+    }
+
+    // searches a move from the current position within the window, or returns None if the search was stopped or ran
+    // out of time
+    fn search_root_move(&self, mov: Move, depth: u8, deadline: Option<Instant>, (alpha, beta): (f32, f32)) -> Option<(f32, SearchStats)> {
+        let mut position = self.position;
+        let mut searcher = Searcher::new(
+            &*self.evaluator,
+            self.cache(),
+            self.tablebase.as_deref(),
+            &self.stop,
+            deadline,
+            self.positions.clone(),
+            self.search_config,
+        )
+        .with_cutoffs(self.cutoffs.take());
+        searcher.make_move(&mut position, mov);
+        let maximizing = position.side().is_maximizing();
+        let score = searcher.minimax(&mut position, depth.max(1) - 1, maximizing, alpha, beta);
+        let result = (!searcher.was_stopped()).then_some((score, searcher.stats()));
+        self.cutoffs.put_back(searcher.into_cutoffs());
+        result
+    }
+
+    // scores every move from the tablebases when the position is in them, so that the best is the quickest win that
+    // the fifty-move rule allows, or failing that a draw, or the slowest loss
+    fn tablebase_moves(&self, start: Instant) -> Option<(Vec<(Move, f32)>, SearchResult)> {
+        let sign = if self.is_maximizing() { 1.0 } else { -1.0 };
+        let move_evals = self
+            .tablebase
+            .as_ref()?
+            .root_moves(&self.position)?
+            .into_iter()
+            .map(|(mov, wdl, dtz)| (mov, sign * tablebase_score(wdl, dtz, self.evaluator.pawn_scale())))
+            .collect::<Vec<(Move, f32)>>();
+        let (best_move, score) = self.pick_best(&move_evals);
+        let result = SearchResult {
+            best_move,
+            score: self.score(score),
+            pv: vec![best_move],
+            depth: 1,
+            nodes: move_evals.len() as u64,
+            elapsed: start.elapsed(),
+            tt_probes: 0,
+            tt_hits: 0,
+        };
+        Some((move_evals, result))
+    }
+
+    // the line the search expects after the best move, following the best moves stored in the transposition table
+    fn principal_variation(&self, best_move: piece::Move, depth: u8) -> Vec<piece::Move> {
+        let mut position = self.position;
+        let mut pv = Vec::new();
+        let mut seen = HashSet::new();
+        let mut next = Some(Move::from(best_move));
+        while let Some(mov) = next {
+            // a line that repeats a position would go round forever
+            if pv.len() >= depth as usize || !seen.insert(position.hash()) || !position.legal_moves().contains(&mov) {
+                break;
+            }
+            pv.push(mov.in_position(&position));
+            position.make_move(mov);
+            next = self.cache().probe(position.hash()).and_then(|entry| entry.best_move);
+        }
+        pv
+    }
 
-        // move_evals.iter().for_each(|(mov, score)| {
-        //     print!("{} -> {} : {}\n", format_coord(mov.0), format_coord(mov.1), score);
-        // });
+    // converts a search score into a score for the current player, in moves to mate if it is a mate
+    pub fn score(&self, score: f32) -> Score {
+        let Some(plies) = plies_to_mate(score) else {
+            return Score::Centipawns(self.centipawns(score));
+        };
+        let moves = plies.div_ceil(2) as i32;
+        if (score > 0.0) == self.is_maximizing() {
+            Score::Mate(moves)
+        } else {
+            Score::Mate(-moves)
+        }
+    }
 
+    // the best scoring move, picking at random between moves that score the same
+    fn pick_best(&self, move_evals: &[(Move, f32)]) -> (piece::Move, f32) {
         let best_score = if self.is_maximizing() {
             move_evals.iter()
                 .max_by(|(_, score1), (_, score2)| score1.partial_cmp(score2).unwrap())
@@ -153,605 +625,353 @@ impl<'a> Game<'a> {
                 .min_by(|(_, score1), (_, score2)| score1.partial_cmp(score2).unwrap())
                 .unwrap().1
         };
-        let best_moves = move_evals.par_iter()
+        let best_moves = move_evals.iter()
             .filter(|(_, score)| *score == best_score)
             .map(|(mov, _)| mov)
-            .collect::<Vec<&((u8, u8), (u8, u8))>>();
+            .collect::<Vec<&Move>>();
         let best_move = best_moves.choose(&mut rand::thread_rng()).unwrap();
-
-        let elapsed = now.elapsed().unwrap();
-        println!(
-            "Time to evaluate best move to depth of {}: {:?}",
-            self.search_depth, elapsed
-        );
-        self.in_simulation = false;
-        Some(**best_move)
+        (best_move.in_position(&self.position), best_score)
     }
 
-    fn get_best_move_and_back_propagate(&mut self) -> ((u8, u8), (u8, u8)) {
-        let now = std::time::SystemTime::now();
-        let possible_moves = self.get_possible_moves(self.current_player);
+    pub fn set_stop_flag(&mut self, stop: Arc<AtomicBool>) {
+        self.stop = stop;
+    }
 
-        let games: Vec<Game> = possible_moves
-            .par_iter()
-            .map(|&(from, to)| {
-                let mut game = self.clone();
-                game.move_piece(from, to);
-                game
-            })
-            .collect();
+    fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
 
-        let matrices = games
-            .par_iter()
-            .map(|game| game.clone().to_matrix())
-            .collect::<Vec<Matrix>>();
+    // converts a search score into centipawns from the point of view of the current player
+    pub fn centipawns(&self, score: f32) -> i32 {
+        let scale = 100.0 / self.evaluator.pawn_scale();
+        let sign = if self.is_maximizing() { 1.0 } else { -1.0 };
+        (score * scale * sign) as i32
+    }
 
-        let amplified_scores = games
-            .par_iter()
-            .map(|game| {
-                // TODO: use one hashmap the whole time?
-                game.clone()
-                    .minimax_search(self.search_depth - 1, game.current_player.is_maximizing(), f32::MIN, f32::MAX)
-            })
-            .collect::<Vec<f32>>();
-        // cache amplified_scores
-        let mut best_move = possible_moves[0];
-        let mut best_score = if self.is_maximizing() {
-            f32::MIN
-        } else {
-            f32::MAX
-        };
-        for i in 0..possible_moves.len() {
-            if self.current_player == Player::One {
-                if amplified_scores[i] > best_score {
-                    best_score = amplified_scores[i];
-                    best_move = possible_moves[i];
-                }
-            } else {
-                if amplified_scores[i] < best_score {
-                    best_score = amplified_scores[i];
-                    best_move = possible_moves[i];
-                }
+    // picks the move to play in self-play, training the model on the search scores of every move, returns the move and the loss
+    pub fn training_move(&mut self) -> (piece::Move, f32) {
+        let (mut best_move, loss) = self.get_best_move_and_back_propagate();
+        if self.epsilon_greedy {
+            let mut rng = rand::thread_rng();
+            if rng.gen_bool(self.epsilon) {
+                let moves = self.get_possible_moves(self.current_player());
+                best_move = *moves.choose(&mut rng).unwrap();
             }
+            self.update_epsilon();
         }
-        let elapsed = now.elapsed().unwrap();
-        println!(
-            "Time to evaluate best move to depth of {}: {:?}",
-            self.search_depth, elapsed
-        );
+        (best_move, loss)
+    }
+
+    fn get_best_move_and_back_propagate(&mut self) -> (piece::Move, f32) {
+        // the model learns from the score of every move, not just from the best
+        let (move_evals, SearchResult { best_move, .. }) = self.timed_deepen(true, |_| ()).expect("No moves to train on!");
 
-        self.model
+        let matrices = move_evals
+            .par_iter()
+            .map(|&(mov, _)| {
+                let mut position = self.position;
+                position.make_move(mov);
+                position.to_matrix()
+            })
+            .collect::<Vec<Matrix>>();
+        let amplified_scores = move_evals.iter().map(|&(_, score)| score).collect::<Vec<f32>>();
+        let loss = self.model
         .as_ref()
         .unwrap()
         .back_propagate(&matrices, &amplified_scores);
-        best_move
-    }
-
-    fn minimax_search(&mut self, depth: u8, maximizing: bool, mut alpha: f32, mut beta: f32) -> f32 {
-        // the algorithm assumes a good heuristic or evaluation model to approximate game state evaluations
-        if self.draw() {
-            return 0.0;
-        }
-        let fen = self.to_short_fen();
-        if let Some(&(score, stored_depth)) = self.cache.lock().unwrap().get(&fen) {
-            if stored_depth >= depth {
-                return score;
-            }
-        }
-        if depth <= 1 && self.model.is_some() {
-            return self.last_level_minimax(maximizing, alpha, beta);
-        }
-        if depth == 0 {
-            return self.evaluate()
-        }
-        let moves = self.get_possible_moves(self.current_player);
-        let mut best_score;
-        if maximizing {
-            best_score = f32::MIN;
-            for (from, to) in moves {
-                let mut game = self.clone();
-                game.move_piece(from, to);
-                best_score = f32::max(best_score, game.minimax_search(depth - 1, false, alpha, beta));
-                alpha = f32::max(alpha, best_score);
-                if alpha >= beta {
-                    break;
-                }
-            }
+        (best_move, loss)
+    }
+
+    // the reason the game is drawn by the rules without either player claiming it, if it is
+    pub fn draw_reason(&mut self) -> Option<Draw> {
+        if self.half_move_clock_expired() {
+            Some(Draw::SeventyFiveMoveRule)
+        } else if self.repetitions() >= 5 {
+            Some(Draw::FivefoldRepetition)
+        } else if self.insufficient_material() {
+            Some(Draw::InsufficientMaterial)
+        } else if !self.in_check(self.current_player()) && self.stalemate() {
+            Some(Draw::Stalemate)
         } else {
-            best_score = f32::MAX;
-            for (from, to) in moves {
-                let mut game = self.clone();
-                game.move_piece(from, to);
-                best_score = f32::min(best_score, game.minimax_search(depth - 1, true, alpha, beta));
-                beta = f32::min(beta, best_score);
-                if alpha >= beta {
-                    break;
-                }
-            }
+            None
         }
-        self.cache.lock().unwrap().insert(fen, (best_score, depth));
-        best_score
     }
 
-    fn last_level_minimax(&mut self, maximizing: bool, alpha: f32, beta: f32) -> f32 {
-        let moves = self.get_possible_moves(self.current_player);
-        if moves.is_empty() {
-            return 0.0;
-        };
-        let move_evals = self.evaluate_moves(&moves);
-        if beta <= alpha {
-            move_evals[0]
-        } else if maximizing {
-            move_evals
-                .iter()
-                .max_by(|a, b| a.partial_cmp(b).unwrap())
-                .unwrap()
-                .clone()
+    // the draw the current player could claim, by the fifty-move rule or threefold repetition
+    pub fn claimable_draw(&self) -> Option<Draw> {
+        if self.position.half_move_clock() >= HALF_MOVE_LIMIT {
+            Some(Draw::FiftyMoveRule)
+        } else if self.repetitions() >= 3 {
+            Some(Draw::ThreefoldRepetition)
         } else {
-            move_evals
-                .iter()
-                .min_by(|a, b| a.partial_cmp(b).unwrap())
-                .unwrap()
-                .clone()
+            None
         }
     }
 
-    fn draw(&mut self) -> bool {
-        self.half_move_clock_expired() || (self.stalemate() && !self.checkmate())
+    // ends the game in a draw if the current player can claim one, returning the reason
+    pub fn claim_draw(&mut self) -> Option<Draw> {
+        let reason = self.claimable_draw().filter(|_| self.result.is_none())?;
+        self.result = Some(GameResult::Draw(reason));
+        Some(reason)
     }
 
-    pub fn turn(&mut self) -> bool {
-        println!("{}", self);
-        println!("It's {}'s turn.", self.current_player);
+    // ends the game in a draw both players agreed to
+    pub fn agree_draw(&mut self) {
+        self.result.get_or_insert(GameResult::Draw(Draw::Agreement));
+    }
 
-        if self.rl_training {
-            if self.model.is_some() {
-                return self.rl_training_move();
-            } else {
-                return self.algorithm_move();
-            }
-        }
-        if !self.two_player && self.current_player == self.computer_player.unwrap() {
-            return self.algorithm_move();
-        }
-        if self.stalemate() {
-            println!(
-                "No possible moves for player {}!",
-                self.current_player.number()
-            );
-            println!("Stalemate!");
+    // whether the engine playing for the player would take a draw, which it does unless its search of the position
+    // finds it ahead
+    pub fn accepts_draw(&self, player: Player) -> bool {
+        let Some(result) = self.iterative_deepening(self.search_depth, self.budget(player), |_| ()) else {
             return true;
-        }
-        let in_check = self.player_in_check();
-        if in_check {
-            println!("You're in check!");
-        }
-        if self.tick() {
-            return true;
-        }
+        };
+        // the score is the current player's
+        let score = match result.score {
+            Score::Centipawns(centipawns) => centipawns,
+            Score::Mate(moves) => moves.signum() * i32::MAX,
+        };
+        let score = if player == self.current_player() { score } else { -score };
+        score <= 0
+    }
 
-        loop {
-            let (from, to) = self.get_move();
-            let mut half_move = false;
-            if !self.is_current_player(from) {
-                println!("You must move one of your own pieces! go again.");
-                continue;
-            }
-            let piece = self.get(from);
-            let conquered = self.get(to);
-            let move_status = piece.clone().unwrap().valid_move(from, to, self);
-            if !move_status.is_valid() {
-                println!("Invalid move! go again.");
-                continue;
-            } else if self.is_current_player(to) {
-                println!("You can't take your own piece! go again.");
-                continue;
-            } else if self.try_move_for_check(from, to, self.current_player) {
-                if in_check {
-                    println!("Invalid move while you are in check! go again");
-                } else {
-                    println!("Wait you can't put yourself in check! go again.");
-                }
-                continue;
-            }
-            if conquered.is_some() {
-                half_move = true;
-                self.take(to, piece.clone());
-            } else {
-                self.set(to, piece.clone());
-            }
-            self.set(from, None);
-            self.set_moved(piece.clone(), from, to);
-            self.set_last_double(None);
-            match move_status {
-                Move::Normal => (),
-                Move::Double(position) => {
-                    self.set_last_double(Some(position));
-                }
-                Move::Castle => {
-                    self.castle(to);
-                }
-                Move::EnPassant(position) => {
-                    self.take(position, None);
-                }
-                Move::Invalid => unreachable!(),
-            }
-            assert!(!self.player_in_check());
-            if piece.clone().unwrap().is_type::<Pawn>() {
-                half_move = true;
-                if to.1 == 7 || to.1 == 0 {
-                    self.promote_piece(to);
-                }
-            }
-            if half_move {
-                self.half_move_clock = 0;
-            }
-            break;
-        }
-        self.current_player = self.current_player.other();
-        if self.checkmate() {
-            println!("{self}");
-            println!("Game over!");
-            println!(
-                "Player {} is in checkmate, {} wins!",
-                self.current_player.number(),
-                self.current_player.other()
-            );
-            return true;
-        }
-        return false;
+    // how many times the current position has occurred, counting back to the last capture or pawn move
+    fn repetitions(&self) -> usize {
+        let reversible = min(self.position.half_move_clock() as usize, self.positions.len());
+        let earlier = &self.positions[self.positions.len() - reversible..];
+        1 + earlier.iter().filter(|&&earlier| earlier == self.hash()).count()
+    }
+
+    fn insufficient_material(&self) -> bool {
+        self.position.insufficient_material()
     }
 
     #[cfg(test)]
     fn assert_pieces(&mut self) {
-        for position in self.p1_pieces.clone() {
-            let piece = self.get(position).expect("assertion failed: piece position is empty");
-            assert_eq!(piece.player(), Player::One);
-            for target in piece.get_legal_moves(position, self) {
-                assert!(piece.valid_move(position, target, self).is_valid());
-            }
-        }
-        for position in self.p2_pieces.clone() {
-            let piece = self.get(position).expect("assertion failed: piece position is empty");
-            assert_eq!(piece.player(), Player::Two);
-            for target in piece.get_legal_moves(position, self) {
-                assert!(piece.valid_move(position, target, self).is_valid());
+        for player in [Player::One, Player::Two] {
+            for position in self.get_pieces(player) {
+                let piece = self.get(position).expect("assertion failed: piece position is empty");
+                assert_eq!(piece.player(), player);
+                for target in piece.get_legal_moves(position, self) {
+                    assert!(piece.valid_move(position, target, self).is_valid());
+                }
             }
+            assert!(self.get(self.get_king(player)).unwrap().is_type::<King>());
         }
-        assert!(self.get(self.king_one).unwrap().is_type::<King>());
-        assert!(self.get(self.king_two).unwrap().is_type::<King>());
-        assert!(self.p2_taken.iter().sum::<u8>() + self.p1_pieces.len() as u8 == 16);
-        assert!(self.p1_taken.iter().sum::<u8>() + self.p2_pieces.len() as u8 == 16);
+        assert!(self.p2_taken.iter().sum::<u8>() + self.get_pieces(Player::One).len() as u8 == 16);
+        assert!(self.p1_taken.iter().sum::<u8>() + self.get_pieces(Player::Two).len() as u8 == 16);
     }
 
-    fn move_piece(&mut self, from: (u8, u8), to: (u8, u8)) -> bool {
-        if self.tick() {
-            return true;
-        }
-        let piece = self.get(from);
-        let conquered = self.get(to);
-        let move_status = piece.clone().unwrap().valid_move(from, to, self);
-        assert!(move_status.is_valid(), "Invalid move!");
-        assert_eq!(
-            piece.clone().unwrap().player(),
-            self.current_player,
-            "You must move one of your own pieces!"
-        );
-        assert!(self.get_pieces(self.current_player).contains(&from));
-        assert!(!self.is_current_player(to));
-        let mut half_move = false;
-        if conquered.is_some() {
-            half_move = true;
-            self.take(to, piece.clone());
-        } else {
-            self.set(to, piece.clone());
-        }
-        self.set(from, None);
-        self.set_moved(piece.clone(), from, to);
-        assert!(!self.get_pieces(self.current_player).contains(&from));
-        assert!(self.get_pieces(self.current_player).contains(&to));
-        self.set_last_double(None);
-        match move_status {
-            Move::Normal => (),
-            Move::Double(position) => {
-                self.set_last_double(Some(position));
-            }
-            Move::Castle => {
-                self.castle(to);
-            }
-            Move::EnPassant(position) => {
-                self.take(position, None);
-            }
-            Move::Invalid => unreachable!(),
-        }
-        if piece.unwrap().is_type::<Pawn>() {
-            half_move = true;
-            if to.1 == 7 || to.1 == 0 {
-                self.set(to, Some(Box::new(Queen::new(self.current_player))));
-            }
-        }
-        if half_move {
-            self.half_move_clock = 0;
+    // the outcome of the game once it has ended by checkmate, resignation, time, agreement or one of the draw rules
+    pub fn status(&mut self) -> Option<Outcome> {
+        if self.result.is_none() && !self.checkmate() {
+            self.result = self.draw_reason().map(GameResult::Draw);
         }
-        // if self.player_in_check() {
-        //     println!("Wait you can't put yourself in check, something went wrong");
-        //     println!("last move was {} -> {}", format_coord(from), format_coord(to));
-        //     println!("{self}");
-        // }
-        assert!(
-            !self.player_in_check(),
-            "Wait you can't put yourself in check!"
-        );
-        self.current_player = self.current_player.other();
-        false
+        self.winner()
     }
 
-    fn evaluate(&mut self) -> f32 {
-        if let Some(model) = self.model {
-            model.run_inference(&vec!(self.to_matrix())).unwrap()[0]
-        } else {
-            self.get_piece_scores() as f32
-        }
+    // how the game ended, once status has found that it has
+    pub fn result(&self) -> Option<GameResult> {
+        self.result
     }
 
-    fn to_matrix(&mut self) -> Matrix {
-        let mut data = [[[0.0; 8]; 8]; 13];
-        for &position in self.get_pieces(Player::One) {
-            let piece = self.get(position).unwrap();
-            if piece.is_type::<Queen>() {
-                data[0][position.0 as usize][position.1 as usize] = 1.0;
-            } else if piece.is_type::<King>() {
-                data[2][position.0 as usize][position.1 as usize] = 1.0;
-            } else if piece.is_type::<Rook>() {
-                data[4][position.0 as usize][position.1 as usize] = 1.0;
-            } else if piece.is_type::<Bishop>() {
-                data[6][position.0 as usize][position.1 as usize] = 1.0;
-            } else if piece.is_type::<Knight>() {
-                data[8][position.0 as usize][position.1 as usize] = 1.0;
-            } else if piece.is_type::<Pawn>() {
-                data[10][position.0 as usize][position.1 as usize] = 1.0;
-            }
-        }
-        for &position in self.get_pieces(Player::Two) {
-            let piece = self.get(position).unwrap();
-            if piece.is_type::<Queen>() {
-                data[1][position.0 as usize][position.1 as usize] = 1.0;
-            } else if piece.is_type::<King>() {
-                data[3][position.0 as usize][position.1 as usize] = 1.0;
-            } else if piece.is_type::<Rook>() {
-                data[5][position.0 as usize][position.1 as usize] = 1.0;
-            } else if piece.is_type::<Bishop>() {
-                data[7][position.0 as usize][position.1 as usize] = 1.0;
-            } else if piece.is_type::<Knight>() {
-                data[9][position.0 as usize][position.1 as usize] = 1.0;
-            } else if piece.is_type::<Pawn>() {
-                data[11][position.0 as usize][position.1 as usize] = 1.0;
-            }
-        }
-        if let Some(last_double) = self.last_double {
-            let (x, y) = last_double;
-            data[12][x as usize][y as usize] = 1.0;
-        }
-        if self.has_p1_king_moved {
-            if self.has_p1_left_rook_moved {
-                data[12][7][0] = 1.0;
-            }
-            if self.has_p1_right_rook_moved {
-                data[12][7][7] = 1.0;
-            }
-        }
-        if self.has_p2_king_moved {
-            if self.has_p2_left_rook_moved {
-                data[12][0][0] = 1.0;
-            }
-            if self.has_p2_right_rook_moved {
-                data[12][0][7] = 1.0;
-            }
-        }
-        if self.current_player == Player::One {
-            data[12][7][4] = 1.0;
+    pub fn resign(&mut self) {
+        self.result.get_or_insert(GameResult::Win(self.current_player().other(), Termination::Resignation));
+    }
+
+    // checks a move the way the pieces do, saying why it can't be played
+    pub fn check_move(&mut self, from: (u8, u8), to: (u8, u8)) -> Result<(), MoveError> {
+        if !self.is_current_player(from) {
+            Err(MoveError::NotYourPiece)
+        } else if !self.get(from).unwrap().valid_move(from, to, self).is_valid() {
+            Err(MoveError::Invalid)
+        } else if self.is_current_player(to) {
+            Err(MoveError::OwnPiece)
+        } else if self.try_move_for_check(from, to) {
+            Err(MoveError::IntoCheck)
         } else {
-            data[12][0][4] = 1.0;
+            Ok(())
         }
-        if self.half_move_clock > 0 {
-            let mut half_move_clock = self.half_move_clock;
-            let mut c = 7;
-            while self.half_move_clock > 0 && c >= 0 {
-                data[12][3][c as usize] = (half_move_clock % 2) as f32;
-                half_move_clock = half_move_clock / 2;
-                c -= 1;
-            }
+    }
+
+    fn move_piece_promoting(&mut self, from: (u8, u8), to: (u8, u8), promotion: Option<char>) -> bool {
+        if self.half_move_clock_expired() {
+            return true;
         }
-        if self.full_move_clock > 0 {
-            let mut full_move_clock = self.full_move_clock;
-            let mut c = 7;
-            while self.full_move_clock > 0 && c >= 0 {
-                data[12][4][c as usize] = (full_move_clock % 2) as f32;
-                full_move_clock = full_move_clock / 2;
-                c -= 1;
+        self.make_move(from, to, promotion);
+        false
+    }
+
+    // moves the piece and passes the turn, keeping track of the captured pieces
+    pub(crate) fn make_move(&mut self, from: (u8, u8), to: (u8, u8), promotion: Option<char>) {
+        assert!(self.is_current_player(from), "You must move one of your own pieces!");
+        assert!(!self.is_current_player(to));
+        self.positions.push(self.hash());
+        let promotion = match promotion.and_then(PieceType::promotion) {
+            Some(piece_type) => Some(piece_type),
+            None => self.is_promotion(from, to).then_some(PieceType::Queen),
+        };
+        let mov = Move::new(square(from), square(to), promotion);
+        if let Some(captured) = self.position.captured(mov) {
+            let i = match captured {
+                PieceType::Pawn => 0,
+                PieceType::Rook => 1,
+                PieceType::Bishop => 2,
+                PieceType::Knight => 3,
+                PieceType::Queen => 4,
+                PieceType::King => unreachable!("You can't take a king, something went wrong!"),
+            };
+            match self.current_player() {
+                Player::One => self.p1_taken[i] += 1,
+                Player::Two => self.p2_taken[i] += 1,
             }
         }
-        data
-    }
-
-    pub fn algorithm_move(&mut self) -> bool {
-        let best_move = self.get_best_move();
-        if best_move.is_none() {
-            // assert!(!self.check());
-            // assert!(!self.checkmate());
-            println!(
-                "No possible moves for player {}!",
-                self.current_player.number()
-            );
-            println!("Stalemate!");
-            return true;
-        }
-        let (from, to) = best_move.unwrap();
-        println!(
-            "Player {} moved {}, {} -> {} ",
-            self.current_player.number(),
-            self.get(from).unwrap().name(),
-            format_coord(&from),
-            format_coord(&to)
-        );
-        if self.move_piece(from, to) {
-            println!("{self}");
-            println!("Game over!");
-            println!("Draw, half move clock expired");
-            return true;
+        let player = self.current_player();
+        self.position.make_move(mov);
+        assert!(!self.in_check(player), "Wait you can't put yourself in check!");
+    }
+
+    pub fn hash(&self) -> u64 {
+        self.position.hash()
+    }
+
+    // the bitboard position underneath the game
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    // plays a move if it is legal, promoting to the given piece (or a queen), returns whether the move was made
+    pub fn apply_move(&mut self, from: (u8, u8), to: (u8, u8), promotion: Option<char>) -> bool {
+        if !self.is_current_player(from) || self.half_move_clock_expired() {
+            return false;
         }
-        if self.checkmate() {
-            println!("{self}");
-            println!("Game over!");
-            println!(
-                "Player {} is in checkmate, {} wins!",
-                self.current_player.number(),
-                self.current_player.other()
-            );
-            return true;
+        let promotion_type = match promotion {
+            Some(promotion) => match PieceType::promotion(promotion) {
+                Some(piece_type) if self.is_promotion(from, to) => Some(piece_type),
+                _ => return false,
+            },
+            None => self.is_promotion(from, to).then_some(PieceType::Queen),
+        };
+        if !self.position.legal_moves().contains(&Move::new(square(from), square(to), promotion_type)) {
+            return false;
         }
-        return false;
+        self.play_move(from, to, promotion);
+        true
     }
 
-    pub fn rl_training_move(&mut self) -> bool {
-        self.in_simulation = true;
-        let (mut from, mut to) = self.get_best_move_and_back_propagate();
-        if self.epsilon_greedy {
-            let mut rng = rand::thread_rng();
-            let choice = rng.gen_bool(self.epsilon);
-            if choice {
-                let moves = self.get_possible_moves(self.current_player);
-                (from, to) = *moves.choose(&mut rng).unwrap();
-            }
-            self.update_epsilon();
-        }
-        println!(
-            "Player {} moved {} -> {} ",
-            self.current_player.number(),
-            format_coord(&from),
-            format_coord(&to)
-        );
-        self.in_simulation = false;
-        if self.move_piece(from, to) {
-            println!("{self}");
-            println!("Game over!");
-            println!("Draw, half move clock expired");
+    // makes the move and records it in the game history
+    fn play_move(&mut self, from: (u8, u8), to: (u8, u8), promotion: Option<char>) -> bool {
+        let san = san::to_san(self, from, to, promotion);
+        let state = self.state();
+        if self.move_piece_promoting(from, to, promotion) {
             return true;
         }
-        if self.checkmate() {
-            println!("{self}");
-            println!("Game over!");
-            println!(
-                "Player {} is in checkmate, {} wins!",
-                self.current_player.number(),
-                self.current_player.other()
-            );
-            return true;
+        self.record_move(san, state);
+        false
+    }
+
+    fn record_move(&mut self, san: String, state: GameState) {
+        let suffix = san::check_suffix(self);
+        self.history.push(san + suffix);
+        self.states.push(Arc::new(state));
+        self.undone.clear();
+    }
+
+    fn state(&self) -> GameState {
+        GameState {
+            position: self.position,
+            p1_taken: self.p1_taken,
+            p2_taken: self.p2_taken,
         }
-        return false;
     }
 
-    fn checkmate(&mut self) -> bool {
-        if !self.in_check(self.current_player) {
+    fn restore(&mut self, state: &GameState) {
+        self.position = state.position;
+        self.p1_taken = state.p1_taken;
+        self.p2_taken = state.p2_taken;
+        self.result = None;
+    }
+
+    // takes back the last move, returning its SAN, or None if no moves have been made. the clocks keep what is left
+    // on them, the time spent on a move isn't given back with it
+    pub fn undo(&mut self) -> Option<String> {
+        let state = self.states.pop()?;
+        let san = self.history.pop().unwrap();
+        self.undone.push((san.clone(), Arc::new(self.state())));
+        self.positions.pop();
+        self.restore(&state);
+        Some(san)
+    }
+
+    // plays the last move taken back again, returning its SAN
+    pub fn redo(&mut self) -> Option<String> {
+        let (san, state) = self.undone.pop()?;
+        self.history.push(san.clone());
+        self.states.push(Arc::new(self.state()));
+        self.positions.push(self.hash());
+        self.restore(&state);
+        Some(san)
+    }
+
+    pub fn history(&self) -> &Vec<String> {
+        &self.history
+    }
+
+    pub fn start_fen(&self) -> &str {
+        &self.start_fen
+    }
+
+    pub fn is_promotion(&self, from: (u8, u8), to: (u8, u8)) -> bool {
+        self.position.piece_at(square(from)).is_some_and(|(_, piece_type)| piece_type == PieceType::Pawn) && (to.1 == 0 || to.1 == 7)
+    }
+
+    // the standard algebraic notation for a legal move, including its check or checkmate suffix
+    pub fn san(&self, from: (u8, u8), to: (u8, u8), promotion: Option<char>) -> String {
+        let mut game = self.clone();
+        let san = san::to_san(&mut game, from, to, promotion);
+        game.move_piece_promoting(from, to, promotion);
+        san + san::check_suffix(&mut game)
+    }
+
+    pub fn checkmate(&mut self) -> bool {
+        if !self.in_check(self.current_player()) || !self.stalemate() {
             return false;
         }
-        // for every enemy that can attack king {
-        //     for every square in path from enemy to king {
-        //         for every friendly piece {
-        //             if friendly piece can move to square {
-        //                 return false;
-        //             }
-        //         }
-        //     }
-        // }
-        let king = self.get_king(self.current_player);
-        let pieces = self.get_pieces(self.current_player).clone();
-        let enemy_pieces = self.get_pieces(self.current_player.other()).clone();
-        for enemy_position in enemy_pieces {
-            let enemy = self.get(enemy_position).unwrap();
-            if enemy.valid_move(enemy_position, king, self) != Move::Invalid {
-                //if it puts the king in check
-                for friendly_position in pieces.clone() {
-                    let friendly = self.get(friendly_position).unwrap();
-                    //for every friendly piece, see if it can block the path and get us out of check
-                    for square in
-                        friendly.can_intercept_path(friendly_position, enemy_position, king, self)
-                    {
-                        if !self.try_move_for_check(friendly_position, square, self.current_player)
-                        {
-                            return false;
-                        }
-                    }
-                }
-            }
-        }
-        let (x, y) = king;
-        for (i, j) in [(0, 1), (1, 0), (0, -1), (-1, 0), (1, 1), (1, -1), (-1, 1), (-1, -1)] {
-            let (new_x, new_y) = (x as i8 + i, y as i8 + j);
-            if new_x < 0 || new_x >= 8 || new_y < 0 || new_y >= 8 {
-                continue;
-            }
-            let new_pos = (new_x as u8, new_y as u8);
-            if !self.is_current_player(new_pos) {
-                if !self.try_move_for_check(king, new_pos, self.current_player) {
-                    return false;
-                }
-            }
-        }
-        self.winner = Some(self.current_player.other());
+        self.result = Some(GameResult::Win(self.current_player().other(), Termination::Checkmate));
         true
     }
 
     #[cfg(test)]
     pub(crate) fn set_board(&mut self, board: Board) {
-        self.board = board;
-    }
-
-    #[cfg(test)]
-    pub(crate) fn set_pieces(&mut self, p1_pieces: Vec<(u8, u8)>, p2_pieces: Vec<(u8, u8)>) {
-        self.p1_pieces = p1_pieces;
-        self.p2_pieces = p2_pieces;
+        let position = self.position;
+        self.position = Position::from_board(
+            &board,
+            position.side(),
+            position.castling(),
+            position.en_passant(),
+            position.half_move_clock(),
+            position.full_move_clock(),
+        );
     }
 
     pub fn save_model(&self) {
         self.model.as_ref().unwrap().save_model()
     }
 
-    fn get_piece_scores(&mut self) -> i32 {
-        if self.checkmate() {
-            return if self.is_maximizing() {
-                i32::MIN
-            } else {
-                i32::MAX
-            }
-        }
-        if self.stalemate() || self.half_move_clock_expired() {
-            return 0;
-        }
-        let mut score = 0;
-        for &piece in &self.p1_pieces {
-            score += self.get(piece).unwrap().value();
-        }
-        for &piece in &self.p2_pieces {
-            score -= self.get(piece).unwrap().value();
-        }
-        score
-    }
-
     fn is_current_player(&self, from: (u8, u8)) -> bool {
-        self.get(from).is_some_and(|spot| self.current_player == spot.player())
+        self.is_player(from, self.current_player())
     }
 
     pub(crate) fn check_horiz(&self, from: (u8, u8), to: (u8, u8)) -> bool {
         if from.0 == to.0 {
             for i in min(from.1, to.1)..=max(from.1, to.1) {
-                if self.get((from.0, i)).is_some() && i != from.1 && i != to.1 {
+                if !self.square_is_none((from.0, i)) && i != from.1 && i != to.1 {
                     return false;
                 }
             }
         } else {
             for i in min(from.0, to.0)..=max(from.0, to.0) {
-                if self.get((i, from.1)).is_some() && i != from.0 && i != to.0 {
+                if !self.square_is_none((i, from.1)) && i != from.0 && i != to.0 {
                     return false;
                 }
             }
@@ -762,13 +982,10 @@ impl<'a> Game<'a> {
     pub(crate) fn check_diag(&self, from: (u8, u8), delta: (i8, i8)) -> bool {
         let signs = (delta.0.signum(), delta.1.signum());
         for i in 1..delta.0.abs() as u8 {
-            if self
-                .get((
-                    (from.0 as i8 + signs.0 * i as i8) as u8,
-                    (from.1 as i8 + signs.1 * i as i8) as u8,
-                ))
-                .is_some()
-            {
+            if !self.square_is_none((
+                (from.0 as i8 + signs.0 * i as i8) as u8,
+                (from.1 as i8 + signs.1 * i as i8) as u8,
+            )) {
                 return false;
             }
         }
@@ -776,44 +993,15 @@ impl<'a> Game<'a> {
     }
 
     pub(crate) fn square_is_opponent(&self, to: (u8, u8), player: Player) -> bool {
-        self.get(to).is_some_and(|piece| piece.player() != player)
-    }
-
-    pub(crate) fn take(&mut self, to: (u8, u8), new_piece: Square) {
-        let piece = self.get(to).unwrap();
-        // if piece.is_type::<King>() {
-        //     println!("You can't take a king, something went wrong!");
-        //     println!("{self}");
-        //     println!("Took {:?}", to);
-        //     panic!("You can't take a king, something went wrong!");
-        // }
-        assert!(
-            !piece.is_type::<King>(),
-            "You can't take a king, something went wrong!"
-        );
-        if !self.in_simulation {
-            println!(
-                "Player {} took {}'s {}!",
-                self.current_player.number(),
-                self.current_player.other(),
-                piece.name()
-            );
+        self.position.piece_at(square(to)).is_some_and(|(owner, _)| owner != player)
+    }
+
+    // the time left on a player's clock, to show next to their name
+    fn display_clock(&self, player: Player) -> String {
+        match self.clock(player) {
+            Some(clock @ TimeControl::Clock { .. }) => format!("  {clock}"),
+            _ => String::new(),
         }
-        self.remove_piece(to);
-        self.set(to, new_piece);
-        let i = match piece.name() {
-            "pawn" => 0,
-            "rook" => 1,
-            "bishop" => 2,
-            "knight" => 3,
-            "queen" => 4,
-            _ => unreachable!("Invalid piece name!"),
-        };
-        let taken = match self.current_player {
-            Player::One => &mut self.p1_taken[i],
-            Player::Two => &mut self.p2_taken[i],
-        };
-        *taken += 1;
     }
 
     fn display_taken(&self, player: Player) -> Option<String> {
@@ -846,496 +1034,92 @@ impl<'a> Game<'a> {
         }
     }
 
-    fn remove_piece(&mut self, position: (u8, u8)) {
-        match self.get(position).unwrap().player() {
-            Player::One => self.p1_pieces.retain(|&x| x != position),
-            Player::Two => self.p2_pieces.retain(|&x| x != position),
-        }
-    }
-
-    pub(crate) fn get(&self, (x, y): (u8, u8)) -> Square {
-        // should I pass the position as reference?
-        self.board[y as usize][x as usize].clone()
-    }
-
-    fn set(&mut self, (x, y): (u8, u8), piece: Square) {
-        self.board[y as usize][x as usize] = piece;
-    }
-
-    pub(crate) fn in_check(&mut self, player: Player) -> bool {
-        let king = self.get_king(player);
-        for position in self.get_pieces(player.other()).clone() {
-            let piece = self.get(position).unwrap();
-            if piece.valid_move(position, king, self).is_valid() {
-                return true;
-            }
-        }
-        false
-    }
-
-    fn get_king(&self, player: Player) -> (u8, u8) {
-        match player {
-            Player::One => self.king_one,
-            Player::Two => self.king_two,
-        }
+    pub fn get(&self, position: (u8, u8)) -> Square {
+        self.position
+            .piece_at(square(position))
+            .map(|(player, piece_type)| piece_type.piece(player))
     }
 
-    fn player_in_check(&mut self) -> bool {
-        self.in_check(self.current_player)
-    }
-
-    fn get_move(&mut self) -> ((u8, u8), (u8, u8)) {
-        println!("Enter a move or \"help\" to see more commands");
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        if input.to_ascii_lowercase().trim() == "exit" {
-            std::process::exit(0);
-        } else if input.to_ascii_lowercase().trim() == "resign" {
-            println!(
-                "Player {} resigned, {} wins!",
-                self.current_player.number(),
-                self.current_player.other()
-            );
-            std::process::exit(0);
-        } else if input.to_ascii_lowercase().trim() == "hint" {
-            if self.allow_hints {
-                let best_move = self.get_best_move().unwrap();
-                println!("Hint, your best move is: {} -> {}", format_coord(&best_move.0), format_coord(&best_move.1));
-            } else {
-                println!("Hints are turned off!");
-            }
-            return self.get_move();
-        } else if input.to_ascii_lowercase().trim() == "moves" {
-            for position in self.get_pieces(self.current_player).clone() {
-                let piece = self.get(position).expect("Piece not found! something went wrong");
-                let piece_moves = piece.get_legal_moves(position, self);
-                if !piece_moves.is_empty() {
-                    println!("{} {} -> {}", piece.name(), format_coord(&position), piece_moves.iter().map(format_coord).collect::<Vec<String>>().join(", "));
-                }
-            }
-            return self.get_move();
-        } else if input.to_ascii_lowercase().trim() == "help" {
-            println!("Commands:");
-            println!("  a2      - display all possible moves for the piece at a4");
-            println!("  a2 a4   - move the piece at a2 to a4");
-            println!("  moves   - see all possible moves");
-            println!("  hint    - get a hint for your next move");
-            println!("  resign  - resign the game");
-            println!("  exit    - exit the game");
-            return self.get_move();
-        }
-        let mut input = input.split_whitespace();
-        let from = input.next();
-        let to = input.next();
-        if from.is_none() {
-            print!("Invalid input! ");
-            return self.get_move();
-        } else if to.is_none() {
-            let from = from.unwrap().to_ascii_lowercase();
-            let from = (from.chars().nth(0), from.chars().nth(1));
-            if from.0.is_none() || from.1.is_none() {
-                print!("Invalid input! ");
-                return self.get_move();
-            }
-            let from = (
-                from.0.unwrap() as i8 - 'a' as i8,
-                '8' as i8 - from.1.unwrap() as i8,
-            );
-            if from.0 < 0 || from.0 > 7 || from.1 < 0 || from.1 > 7 {
-                print!("Invalid input! ");
-                return self.get_move();
-            }
-            let from = (from.0 as u8, from.1 as u8);
-            self.see_all_moves(from);
-            return self.get_move();
-        }
-        let from = from.unwrap().to_ascii_lowercase();
-        let to = to.unwrap().to_ascii_lowercase();
-        let from = (from.chars().nth(0), from.chars().nth(1));
-        let to = (to.chars().nth(0), to.chars().nth(1));
-        if from.0.is_none() || from.1.is_none() || to.0.is_none() || to.1.is_none() {
-            print!("Invalid input! ");
-            return self.get_move();
-        }
-        let from = (
-            from.0.unwrap() as i8 - 'a' as i8,
-            '8' as i8 - from.1.unwrap() as i8,
-        );
-        let to = (
-            to.0.unwrap() as i8 - 'a' as i8,
-            '8' as i8 - to.1.unwrap() as i8,
-        );
-        if from.0 < 0
-            || from.1 < 0
-            || from.0 > 7
-            || from.1 > 7
-            || to.0 < 0
-            || to.1 < 0
-            || to.0 > 7
-            || to.1 > 7
-        {
-            print!("Invalid input! ");
-            return self.get_move();
-        }
-        let from = (from.0 as u8, from.1 as u8);
-        let to = (to.0 as u8, to.1 as u8);
-        (from, to)
-    }
-
-    fn promote_piece(&mut self, position: (u8, u8)) {
-        println!("Pawn promotion! Enter a piece to promote to: (q, r, b, k)");
-        let mut input = String::new();
-        let piece: Box<dyn Piece>;
-        loop {
-            io::stdin().read_line(&mut input).unwrap();
-            piece = match input
-                .trim()
-                .chars()
-                .next()
-                .unwrap_or(' ')
-                .to_ascii_lowercase()
-            {
-                'q' => Box::new(Queen::new(self.current_player)),
-                'r' => Box::new(Rook::new(self.current_player)),
-                'b' => Box::new(Bishop::new(self.current_player)),
-                'k' => Box::new(Knight::new(self.current_player)),
-                _ => {
-                    println!("Invalid piece! Enter another.");
-                    continue;
-                }
-            };
-            break;
-        }
-        self.set(position, Some(piece));
+    pub fn in_check(&self, player: Player) -> bool {
+        self.position.in_check(player)
     }
 
-    fn castle(&mut self, king_to: (u8, u8)) {
-        let (rook_from, rook_to) = match king_to.0 {
-            6 => ((7, king_to.1), (5, king_to.1)),
-            2 => ((0, king_to.1), (3, king_to.1)),
-            _ => unreachable!(),
-        };
-        self.set(rook_to, self.get(rook_from));
-        self.set(rook_from, None);
-        self.update_piece(rook_from, rook_to);
+    pub fn get_king(&self, player: Player) -> (u8, u8) {
+        coords(self.position.king(player))
     }
 
+    // the pawn that just moved two squares, which can be taken en passant
     pub fn set_last_double(&mut self, position: Option<(u8, u8)>) {
-        self.last_double = position;
+        let target = position.map(|(x, y)| square((x, if y == 3 { 2 } else { 5 })));
+        self.position.set_en_passant(target);
     }
 
     pub(crate) fn get_last_double(&self) -> Option<(u8, u8)> {
-        self.last_double
+        let (x, y) = coords(self.position.en_passant()?);
+        Some((x, if y == 2 { 3 } else { 4 }))
     }
 
     pub(crate) fn square_is_none(&self, to: (u8, u8)) -> bool {
-        self.get(to).is_none()
+        self.position.piece_at(square(to)).is_none()
     }
 
     pub(crate) fn is_not_player(&self, new_pos: (u8, u8), player: Player) -> bool {
-        if let Some(piece) = self.get(new_pos) {
-            piece.player() != player
-        } else {
-            true
-        }
-    }
-
-    fn set_moved(&mut self, piece: Square, from: (u8, u8), to: (u8, u8)) {
-        let piece = piece.unwrap();
-        if piece.is_type::<King>() {
-            match piece.player() {
-                Player::One => {
-                    self.king_one = to;
-                    self.has_p1_king_moved = true
-                }
-                Player::Two => {
-                    self.king_two = to;
-                    self.has_p2_king_moved = true
-                }
-            }
-        } else if piece.is_type::<Rook>() {
-            match piece.player() {
-                Player::One => {
-                    if from == (0, 0) {
-                        self.has_p1_left_rook_moved = true;
-                    } else if from == (7, 0) {
-                        self.has_p1_right_rook_moved = true;
-                    }
-                }
-                Player::Two => {
-                    if from == (0, 7) {
-                        self.has_p2_left_rook_moved = true;
-                    } else if from == (7, 7) {
-                        self.has_p2_right_rook_moved = true;
-                    }
-                }
-            }
-        }
-        self.update_piece(from, to)
+        !self.is_player(new_pos, player)
     }
 
+    // a side that has lost both its castling rights has moved its king, as far as the rules are concerned
     pub(crate) fn has_king_moved(&self, player: Player) -> bool {
-        match player {
-            Player::One => self.has_p1_king_moved,
-            Player::Two => self.has_p2_king_moved,
-        }
+        self.position.castling() & castling_rights(player, 0b11) == 0
     }
 
     pub(crate) fn has_left_rook_moved(&self, player: Player) -> bool {
-        match player {
-            Player::One => self.has_p1_left_rook_moved,
-            Player::Two => self.has_p2_left_rook_moved,
-        }
+        self.position.castling() & castling_rights(player, 0b10) == 0
     }
 
     pub(crate) fn has_right_rook_moved(&self, player: Player) -> bool {
-        match player {
-            Player::One => self.has_p1_right_rook_moved,
-            Player::Two => self.has_p2_right_rook_moved,
-        }
-    }
-
-    fn see_all_moves(&mut self, from: (u8, u8)) {
-        if let Some(piece) = self.get(from) {
-            let moves = piece.get_legal_moves(from, self);
-            if moves.is_empty() {
-                println!(
-                    "Player {}'s {} has no legal moves!",
-                    piece.player().number(),
-                    piece.name()
-                );
-                return;
-            }
-            println!(
-                "Player {}'s {} can move to:",
-                piece.player().number(),
-                piece.name()
-            );
-            let moves = moves
-                .iter()
-                .map(|(x, y)| format!("{}{}", (x + 'a' as u8) as char, 8 - y))
-                .collect::<Vec<String>>()
-                .join(",");
-            println!("{moves}");
-        } else {
-            println!("There's no piece there!");
-        }
+        self.position.castling() & castling_rights(player, 0b01) == 0
     }
 
     pub(crate) fn is_player(&self, new_pos: (u8, u8), player: Player) -> bool {
-        if let Some(piece) = self.get(new_pos) {
-            piece.player() == player
-        } else {
-            false
-        }
+        self.position.piece_at(square(new_pos)).is_some_and(|(owner, _)| owner == player)
     }
 
-    pub(crate) fn try_move_for_check(
-        &mut self,
-        from: (u8, u8),
-        to: (u8, u8),
-        player: Player,
-    ) -> bool {
-        let friendly = self.get(from);
-        let friendly_piece = friendly.clone().unwrap();
-        let en_passent_capture = if let Some(pawn) = friendly_piece.get_piece::<Pawn>() {
-            pawn.en_passent_capture(from, to, self)
-        } else {
-            None
-        };
-        let is_king = friendly_piece.is_type::<King>();
-        let old = self.get(to);
-        let enemy_pieces = self.get_pieces(player.other()).clone();
-        // assumes that old isn't a friendly piece
-        if old.is_some() {
-            self.remove_piece(to);
-        }
-        if let Some(captured) = en_passent_capture {
-            self.remove_piece(captured);
-            self.set(captured, None);
-        }
-        self.set(to, friendly.clone());
-        self.set(from, None);
-        if is_king {
-            self.set_king(player, to);
-        }
-        let still_in_check = self.in_check(player);
-        self.set(from, friendly);
-        self.set(to, old);
-        if is_king {
-            self.set_king(player, from);
-        }
-        if let Some(captured) = en_passent_capture {
-            self.set(captured, Some(Box::new(Pawn::new(player.other()))));
-        }
-        self.set_player_pieces(player.other(), enemy_pieces);
-        still_in_check
+    // whether moving the piece would leave its king in check
+    pub(crate) fn try_move_for_check(&self, from: (u8, u8), to: (u8, u8)) -> bool {
+        self.position.exposes_king(square(from), square(to))
     }
 
-    pub(crate) fn set_king(&mut self, player: Player, king: (u8, u8)) {
-        match player {
-            Player::One => self.king_one = king,
-            Player::Two => self.king_two = king,
-        }
-    }
-
-    pub fn get_pieces(&self, player: Player) -> &Vec<(u8, u8)> {
-        match player {
-            Player::One => &self.p1_pieces,
-            Player::Two => &self.p2_pieces,
-        }
-    }
-
-    fn update_piece(&mut self, from: (u8, u8), to: (u8, u8)) {
-        match self.current_player {
-            Player::One => {
-                self.p1_pieces.retain(|&x| x != from);
-                self.p1_pieces.push(to);
-                // self.p2_pieces.retain(|x| *x != to);
-            }
-            Player::Two => {
-                self.p2_pieces.retain(|&x| x != from);
-                self.p2_pieces.push(to);
-                // self.p1_pieces.retain(|x| *x != to);
-                // should this be here or in take
-            }
-        }
+    pub fn get_pieces(&self, player: Player) -> Vec<(u8, u8)> {
+        squares(self.position.occupied_by(player)).map(coords).collect()
     }
 
-    pub(crate) fn set_player_pieces(&mut self, player: Player, pieces: Vec<(u8, u8)>) {
-        match player {
-            Player::One => self.p1_pieces = pieces,
-            Player::Two => self.p2_pieces = pieces,
-        }
-    }
-    
     fn stalemate(&mut self) -> bool {
-        self.get_possible_moves(self.current_player).is_empty()
+        !self.position.has_legal_move()
     }
 
-    pub fn get_possible_moves(&mut self, player: Player) -> Vec<((u8, u8), (u8, u8))> {
-        let mut moves = Vec::new();
-        for position in self.get_pieces(player).clone() {
-            let piece = self.get(position).expect("Piece not found!");
-            let piece_moves = piece.get_legal_moves(position, self);
-            moves.extend(piece_moves.iter().map(|&x| (position, x)));
-        }
-        moves
+    // the move between two squares, of the kind it would be in the current position, whether or not it is legal
+    pub fn move_between(&self, from: (u8, u8), to: (u8, u8), promotion: Option<PieceType>) -> piece::Move {
+        Move::new(square(from), square(to), promotion).in_position(&self.position)
     }
 
-    fn tick(&mut self) -> bool {
-        if self.is_last_halfmove() && !self.in_simulation {
-            println!("The halfmove clock is nearly up! Next move must be a capture or pawn move.");
-        } else if self.half_move_clock_expired() && !self.in_simulation {
-            println!("{HALF_MOVE_LIMIT} moves without a capture or pawn move, it's a draw!");
-            return true;
-        }
-        self.half_move_clock += 1;
-        self.full_move_clock += 1;
-        false
-    }
-
-    fn evaluate_moves(&mut self, moves: &Vec<((u8, u8), (u8, u8))>) -> Vec<f32> {
-        // TODO: optimize by not cloning the entire game?
-        // } if self.rl_training {
-        self.model
-            .clone()
-            .unwrap()
-            .run_inference(&moves.iter().map(|&(from, to)| {
-                let mut game = self.clone();
-                game.move_piece(from, to);
-                game.to_matrix()
-            }).collect())
-            .unwrap()
-        // } else if let Some(model) = self.model {
-        //     // check if par_iter is actually faster
-        //     let games = moves
-        //         .iter()
-        //         .map(|&(from, to)| {
-        //             let mut game = self.clone();
-        //             game.move_piece(from, to);
-        //             game
-        //             // self.matrix_from_move(mov)
-        //     });
-
-        //     let fens: Vec<String> = games.clone().map(|mut game| {
-        //         game.to_short_fen()
-        //     }).collect();
-
-        //     let mut cached = Vec::with_capacity(moves.len());
-        //     for (i, fen) in fens.iter().enumerate() {
-        //         if let Some(eval) = self.cache.get(fen) {
-        //             cached.push((i, eval));
-        //         }
-        //     }
-
-        //     let matrices: Vec<[[[f32; 8]; 8]; 13]> = games.clone().map(|mut game| {
-        //         game.to_matrix()
-        //     }).collect();
-
-        //     if cached.len() == 0 {
-        //         let evals = self.model.as_ref().unwrap().run_inference(&matrices).unwrap();
-        //         (0..evals.len()).for_each(|i| {
-        //             self.cache.insert(fens[i].clone(), evals[i]);
-        //         });
-        //         return evals;
-        //     }
-
-        //     let mut j = 0;
-        //     let uncached_games = matrices
-        //         .into_iter()
-        //         .enumerate()
-        //         .filter(|(i, _)| {
-        //             if j < cached.len() && cached[j].0 == *i {
-        //                 j += 1;
-        //                 false
-        //             } else {
-        //                 true
-        //             }
-        //         })
-        //         .map(|(_, game)| game)
-        //         .collect();
-
-        //     let evals = self
-        //         .model
-        //         .run_inference(&uncached_games)
-        //         .unwrap();
-
-        //     j = 0;
-        //     for i in 0..moves.len() {
-        //         if j < cached.len() && cached[j].0 == i {
-        //             j += 1;
-        //             continue;
-        //         }
-        //         self.cache.clone().insert(fens[i].clone(), evals[i - j]);
-        //     }
-
-        //     j = 0;
-        //     games
-        //         .enumerate()
-        //         .map(|(i, _)| {
-        //             if j < cached.len() && cached[j].0 == i {
-        //                 j += 1;
-        //                 cached[j].1
-        //             } else {
-        //                 &evals[i - j]
-        //             }
-        //         }).cloned().collect()
-        // }
-        // // self.model.clone().unwrap().run_inference(games).unwrap()
+    // every legal move for the player, with a move for each piece a pawn can promote to
+    pub fn get_possible_moves(&mut self, player: Player) -> Vec<piece::Move> {
+        let mut position = self.position;
+        position.set_side(player);
+        position.legal_moves().into_iter().map(|mov| mov.in_position(&position)).collect()
     }
 
     #[cfg(test)]
     pub(crate) fn set_player(&mut self, player: Player) {
-        self.current_player = player;
+        self.position.set_side(player);
     }
 
-    pub(crate) fn is_last_halfmove(&self) -> bool {
-        self.half_move_clock == HALF_MOVE_LIMIT - 1
+    pub fn is_last_halfmove(&self) -> bool {
+        self.position.half_move_clock() == HALF_MOVE_HARD_LIMIT - 1
     }
 
     fn half_move_clock_expired(&self) -> bool {
-        self.half_move_clock >= HALF_MOVE_LIMIT
+        self.position.half_move_clock() >= HALF_MOVE_HARD_LIMIT
     }
 
     pub fn to_fen(&mut self) -> String {
@@ -1344,40 +1128,26 @@ impl<'a> Game<'a> {
 
         // fen.push((self.half_move_clock + '0' as u8) as char);
         // fen.push(self.half_move_clock.to_string().chars().next().unwrap());
-        fen.extend(self.half_move_clock.to_string().chars());
+        fen.extend(self.position.half_move_clock().to_string().chars());
         fen.push(' ');
         
         // fen.push((self.full_move_clock + '0' as u8) as char);
         // self.full_move_clock.to_string().chars().for_each(|c| fen.push(c));
-        fen.extend(self.full_move_clock.to_string().chars());
+        fen.extend(self.position.full_move_clock().to_string().chars());
         fen
     }
 
     pub fn to_short_fen(&self) -> String {
         let mut fen = String::with_capacity(87);
-        for row in &self.board {
+        for y in 0..8 {
             let mut num_empty = 0;
-            for square in row {
-                if let Some(piece) = square {
+            for x in 0..8 {
+                if let Some(piece) = self.get((x, y)) {
                     if num_empty > 0 {
                         fen.push((num_empty + '0' as u8) as char);
                     }
                     num_empty = 0;
-                    match (piece.name(), piece.player()) {
-                        ("rook", Player::Two) => fen.push('r'),
-                        ("knight", Player::Two) => fen.push('n'),
-                        ("bishop", Player::Two) => fen.push('b'),
-                        ("king", Player::Two) => fen.push('k'),
-                        ("queen", Player::Two) => fen.push('q'),
-                        ("pawn", Player::Two) => fen.push('p'),
-                        ("rook", Player::One) => fen.push('R'),
-                        ("knight", Player::One) => fen.push('N'),
-                        ("bishop", Player::One) => fen.push('B'),
-                        ("king", Player::One) => fen.push('K'),
-                        ("queen", Player::One) => fen.push('Q'),
-                        ("pawn", Player::One) => fen.push('P'),
-                        (_, _) => unreachable!("invalid piece player combos"),
-                    }
+                    fen.push(fen::piece_to_fen(piece.as_ref()));
                 } else {
                     num_empty += 1
                 }
@@ -1389,29 +1159,16 @@ impl<'a> Game<'a> {
         }
         fen.pop();
         fen.push(' ');
-        fen.push(match self.current_player {
+        fen.push(match self.current_player() {
             Player::One => 'w',
             Player::Two => 'b',
         });
         fen.push(' ');
         
         let mut castleable = false;
-        if self.has_p1_king_moved {
-            if self.has_p1_left_rook_moved {
-                fen.push('K');
-                castleable = true;
-            }
-            if self.has_p1_right_rook_moved {
-                fen.push('Q')
-            }
-        }
-        if self.has_p2_king_moved {
-            if self.has_p2_left_rook_moved {
-                fen.push('k');
-                castleable = true;
-            }
-            if self.has_p2_right_rook_moved {
-                fen.push('q');
+        for (i, right) in ['K', 'Q', 'k', 'q'].into_iter().enumerate() {
+            if self.position.castling() & (1 << i) != 0 {
+                fen.push(right);
                 castleable = true;
             }
         }
@@ -1420,10 +1177,9 @@ impl<'a> Game<'a> {
         }
         fen.push(' ');
 
-        if let Some(last_double) = self.last_double {
-            for c in format_coord(&last_double).bytes() {
-                fen.push(c as char);
-            }
+        // fen records the square passed over by the double move, not the pawn itself
+        if let Some(target) = self.position.en_passant() {
+            fen.push_str(&format_coord(&coords(target)));
         } else {
             fen.push('-')
         }
@@ -1434,27 +1190,55 @@ impl<'a> Game<'a> {
         self.epsilon *= self.epsilon_decay_rate;
     }
 
-    pub fn winner(&mut self) -> Option<Player> {
-        self.winner
+    pub fn winner(&self) -> Option<Outcome> {
+        self.result.map(GameResult::outcome)
     }
 
     pub fn is_maximizing(&self) -> bool {
-        self.current_player.is_maximizing()
+        self.current_player().is_maximizing()
+    }
+
+    pub fn current_player(&self) -> Player {
+        self.position.side()
+    }
+
+    pub fn is_two_player(&self) -> bool {
+        self.two_player
+    }
+
+    pub fn computer_player(&self) -> Option<Player> {
+        self.computer_player
+    }
+
+    pub fn is_rl_training(&self) -> bool {
+        self.rl_training
+    }
+
+    pub fn has_model(&self) -> bool {
+        self.model.is_some()
+    }
+
+    pub fn allows_hints(&self) -> bool {
+        self.allow_hints
+    }
+
+    pub fn search_depth(&self) -> u8 {
+        self.search_depth
     }
 }
 
 impl<'a> Display for Game<'a> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        writeln!(f, "{}", format!("\t\tPlayer 2").red().bold())?;
+        writeln!(f, "{}{}", format!("\t\tPlayer 2").red().bold(), self.display_clock(Player::Two))?;
         if let Some(taken2) = self.display_taken(Player::Two) {
             writeln!(f, "{}", taken2)?;
         }
         writeln!(f, "    a    b    c    d    e    f    g    h")?;
-        self.board.iter().enumerate().for_each(|(i, row)| {
+        (0..8).for_each(|i| {
             writeln!(f, "  -----------------------------------------").unwrap();
             write!(f, "{} ", 8 - i).unwrap();
-            row.iter().enumerate().for_each(|(_, piece)| {
-                match piece {
+            (0..8).for_each(|x| {
+                match self.get((x, i)) {
                     // Some(piece) => write!(f, "|{}", if (i+j)%2==0 {format!(" {piece}  ").on_black()} else {format!(" {piece}  ").on_bright_black()}),
                     // None => write!(f,"|{}", if (i+j)%2==0 {format!("    ").on_black()} else {format!("    ").on_bright_black()}),
                     Some(piece) => write!(f, "|{}", format!(" {piece}  ").on_black()),
@@ -1473,12 +1257,12 @@ impl<'a> Display for Game<'a> {
         if let Some(taken1) = self.display_taken(Player::One) {
             writeln!(f, "{}", taken1)?;
         }
-        writeln!(f, "{}", format!("\t\tPlayer 1").blue().bold())?;
+        writeln!(f, "{}{}", format!("\t\tPlayer 1").blue().bold(), self.display_clock(Player::One))?;
         Ok(())
     }
 }
 
-fn format_coord(coordinate: &(u8, u8)) -> String {
+pub fn format_coord(coordinate: &(u8, u8)) -> String {
     format!(
         "{}{}",
         (coordinate.0 + 'a' as u8) as char,
@@ -1486,6 +1270,23 @@ fn format_coord(coordinate: &(u8, u8)) -> String {
     )
 }
 
+pub fn parse_coord(coordinate: &str) -> Option<(u8, u8)> {
+    let mut chars = coordinate.chars();
+    let (file, rank) = (chars.next()?, chars.next()?);
+    if chars.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+        return None;
+    }
+    Some((file as u8 - b'a', b'8' - rank as u8))
+}
+
+// the castling rights bits for one side, given as kingside then queenside bits
+fn castling_rights(player: Player, rights: u8) -> u8 {
+    match player {
+        Player::One => rights,
+        Player::Two => rights << 2,
+    }
+}
+
 fn setup_board() -> Board {
     let mut board: Board = vec![vec![None; 8]; 8];
     board[0] = vec![
@@ -1515,6 +1316,7 @@ fn setup_board() -> Board {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::time_manager::Bonus;
 
     #[test]
     fn checkmate_no_friendlies() {
@@ -1525,8 +1327,6 @@ mod tests {
 
         let mut game = Game::two_player_game(false);
         game.set_board(board);
-        game.set_pieces(vec![(0, 0)], vec![(1, 0), (0, 1)]);
-        game.set_king(Player::One, (0, 0));
 
         assert!(game.checkmate());
     }
@@ -1541,8 +1341,6 @@ mod tests {
 
         let mut game = Game::two_player_game(false);
         game.set_board(board);
-        game.set_pieces(vec![(0, 0)], vec![(1, 0), (0, 1), (2, 0)]);
-        game.set_king(Player::One, (0, 0));
 
         print!("{game}");
 
@@ -1558,8 +1356,6 @@ mod tests {
 
         let mut game = Game::two_player_game(false);
         game.set_board(board);
-        game.set_pieces(vec![(0, 0)], vec![(1, 0), (0, 1)]);
-        game.set_king(Player::One, (0, 0));
 
         print!("{game}");
 
@@ -1575,8 +1371,6 @@ mod tests {
 
         let mut game = Game::two_player_game(false);
         game.set_board(board);
-        game.set_pieces(vec![(0, 0), (1, 0)], vec![(0, 2)]);
-        game.set_king(Player::One, (0, 0));
 
         print!("{game}");
 
@@ -1593,8 +1387,6 @@ mod tests {
 
         let mut game = Game::two_player_game(false);
         game.set_board(board);
-        game.set_pieces(vec![(0, 0), (1, 0), (1, 1)], vec![(0, 3)]);
-        game.set_king(Player::One, (0, 0));
 
         assert!(game.checkmate());
     }
@@ -1603,10 +1395,10 @@ mod tests {
     fn checking_all_legal_moves_are_valid() {
         let mut game = Game::two_player_game(false);
 
-        for position in game.p1_pieces.clone() {
+        for position in game.get_pieces(Player::One) {
             let piece = game.get(position).unwrap();
             for (x, y) in piece.get_legal_moves(position, &mut game) {
-                assert!(piece.valid_move(position, (x, y), &mut game) != Move::Invalid);
+                assert!(piece.valid_move(position, (x, y), &mut game) != crate::piece::MoveKind::Invalid);
             }
         }
     }
@@ -1621,9 +1413,6 @@ mod tests {
 
         let mut game = Game::two_player_game(false);
         game.set_board(board);
-        game.set_pieces(vec![(5, 7)], vec![(4, 4), (3, 4), (3, 2)]);
-        game.set_king(Player::One, (5, 7));
-        game.set_king(Player::Two, (3, 2));
 
         println!("{game}");
 
@@ -1643,8 +1432,6 @@ mod tests {
 
         let mut game = Game::two_player_game(false);
         game.set_board(board);
-        game.set_pieces(vec![(3, 7), (6, 3)], vec![(4, 2), (4, 1), (5, 2), (4, 0)]);
-        game.set_king(Player::Two, (4, 1));
         game.set_player(Player::Two);
 
         println!("{game}");
@@ -1663,6 +1450,74 @@ mod tests {
         println!("{}", game.to_fen());
     }
 
+    #[test]
+    fn fen_round_trip() {
+        let mut game = Game::two_player_game(false);
+        assert_eq!(game.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "r3k3/8/8/8/8/8/8/4K2R b Kq - 12 40",
+        ] {
+            let mut game = Game::from_fen(fen).unwrap();
+            assert_eq!(game.to_fen(), fen);
+            game.assert_pieces();
+        }
+    }
+
+    #[test]
+    fn fen_after_moves() {
+        let mut game = Game::two_player_game(false);
+        game.make_move((4, 6), (4, 4), None);
+        assert_eq!(game.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        game.make_move((6, 0), (5, 2), None);
+        assert_eq!(game.to_fen(), "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2");
+        game.make_move((7, 6), (7, 4), None);
+        game.make_move((5, 2), (4, 4), None);
+        game.make_move((7, 7), (7, 5), None);
+        assert_eq!(game.to_fen(), "rnbqkb1r/pppppppp/8/8/4n2P/7R/PPPP1PP1/RNBQKBN1 b Qkq - 1 3");
+    }
+
+    #[test]
+    fn from_fen_builds_position() {
+        let mut game = Game::from_fen("Kq6/q7/8/8/8/8/8/7k w - - 0 1").unwrap();
+        assert_eq!(game.get_king(Player::One), (0, 0));
+        assert_eq!(game.get_king(Player::Two), (7, 7));
+        assert_eq!(game.p1_taken, [7, 2, 2, 2, 0]);
+        assert_eq!(game.p2_taken, [8, 2, 2, 2, 1]);
+        assert!(game.checkmate());
+
+        let game = Game::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3").unwrap();
+        assert_eq!(game.get_last_double(), Some((5, 3)));
+    }
+
+    #[test]
+    fn from_fen_rejects_invalid_positions() {
+        let cases = [
+            ("", FenError::MissingField("piece placement")),
+            ("8/8/8/8/8/8/8/8 w - -", FenError::KingCount(Player::One, 0)),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1", FenError::RankCount(7)),
+            ("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::InvalidPiece('9')),
+            ("rnbqkbnr/pppppppp/7/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::RankLength(6)),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1", FenError::InvalidPiece('X')),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1", FenError::ActiveColor("x".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w KQkq - 0 1", FenError::Castling("KQkq".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1", FenError::EnPassant("e3".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1", FenError::HalfMoveClock("x".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0", FenError::FullMoveClock("0".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 1", FenError::TooManyFields),
+            ("k6K/8/8/8/8/8/8/7P w - - 0 1", FenError::PawnOnBackRank("h1".to_string())),
+            ("kk5K/8/8/8/8/8/8/8 w - - 0 1", FenError::KingCount(Player::Two, 2)),
+            ("k6K/8/8/8/8/8/PPPPPPPP/QQQQQQQQ w - - 0 1", FenError::TooManyPieces(Player::One)),
+            ("k6R/8/8/8/8/8/8/7K w - - 0 1", FenError::OpponentInCheck),
+        ];
+        for (fen, err) in cases {
+            assert_eq!(Game::from_fen(fen).err(), Some(err), "{fen}");
+        }
+    }
+
     #[test]
     fn en_passant_into_check() {
         let mut board: Board = vec![vec![None;8];8];
@@ -1678,11 +1533,8 @@ mod tests {
         board[5][2] = Some(Box::new(Rook::new(Player::Two)));
 
         let mut game = Game::self_play(None, Some(4), false, None, None, None);
-        game.set_pieces(vec![(6,3), (2, 3), (7,5)], vec![(6,0),(7,4),(5,1),(0,4), (3,3), (0,3), (2,5)]);
         game.set_last_double(Some((3,3)));
         game.set_board(board);
-        game.set_king(Player::One, (6,3));
-        game.set_king(Player::Two, (6,0));
         game.set_player(Player::Two);
 
         println!("{game}");
@@ -1697,10 +1549,307 @@ mod tests {
     #[test]
     fn pieces_add_up() {
         let mut game = Game::self_play(None, Some(2), false, None, None, None);
-        let mut game_over = false;
-        while !game_over {
-            game_over = game.turn();
+        while game.status().is_none() {
+            let (mov, _) = game.search(game.search_depth()).unwrap();
+            assert!(game.apply_move(mov.from, mov.to, mov.promotion_letter()));
             game.assert_pieces();
         }
     }
+
+    #[test]
+    fn check_move_explains_illegal_moves() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/3r4/R3K3 w Q - 0 1").unwrap();
+        assert_eq!(game.check_move((3, 6), (3, 5)), Err(MoveError::NotYourPiece));
+        assert_eq!(game.check_move((0, 7), (1, 6)), Err(MoveError::Invalid));
+        assert_eq!(game.check_move((4, 7), (4, 6)), Err(MoveError::IntoCheck));
+        assert_eq!(game.check_move((0, 7), (4, 7)), Err(MoveError::OwnPiece));
+        assert_eq!(game.check_move((4, 7), (3, 6)), Ok(()));
+
+        assert_eq!(game.status(), None);
+        game.resign();
+        assert_eq!(game.status(), Some(Outcome::Win(Player::Two)));
+        let mut game = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(game.status(), Some(Outcome::Draw(Draw::Stalemate)));
+    }
+
+    #[test]
+    fn undo_and_redo_restore_state() {
+        let fen = "r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1";
+        let mut game = Game::from_fen(fen).unwrap();
+        let taken = (game.p1_taken, game.p2_taken);
+        assert!(game.apply_move((4, 3), (3, 2), None));
+        assert!(game.apply_move((4, 0), (6, 0), None));
+        assert!(game.apply_move((1, 1), (0, 0), Some('n')));
+        let end = game.to_fen();
+        let end_taken = (game.p1_taken, game.p2_taken);
+        assert_eq!(game.undo(), Some("bxa8=N".to_string()));
+        assert_eq!(game.undo(), Some("O-O".to_string()));
+        assert_eq!(game.undo(), Some("exd6".to_string()));
+        assert_eq!(game.undo(), None);
+        assert_eq!(game.to_fen(), fen);
+        assert_eq!(game.get_last_double(), Some((3, 3)));
+        assert_eq!((game.p1_taken, game.p2_taken), taken);
+        assert!(!game.has_king_moved(Player::Two) && !game.has_left_rook_moved(Player::Two));
+
+        assert_eq!(game.redo(), Some("exd6".to_string()));
+        assert_eq!(game.redo(), Some("O-O".to_string()));
+        assert_eq!(game.redo(), Some("bxa8=N".to_string()));
+        assert_eq!(game.redo(), None);
+        assert_eq!(game.to_fen(), end);
+        assert_eq!((game.p1_taken, game.p2_taken), end_taken);
+        assert_eq!(game.history(), &vec!["exd6", "O-O", "bxa8=N"]);
+
+        game.undo();
+        assert!(game.apply_move((1, 1), (0, 0), Some('q')));
+        assert_eq!(game.redo(), None);
+        assert_eq!(game.history().last().unwrap(), "bxa8=Q");
+    }
+
+
+    #[test]
+    fn repetition_draws() {
+        let mut game = Game::two_player_game(false);
+        let shuffle = [((6, 7), (5, 5)), ((6, 0), (5, 2)), ((5, 5), (6, 7)), ((5, 2), (6, 0))];
+        for (from, to) in shuffle.iter().chain(&shuffle[..3]) {
+            assert!(game.apply_move(*from, *to, None));
+            assert_eq!((game.draw_reason(), game.claimable_draw()), (None, None));
+        }
+        assert_eq!(game.repetitions(), 2);
+        assert!(game.apply_move((5, 2), (6, 0), None));
+        assert_eq!((game.draw_reason(), game.claimable_draw()), (None, Some(Draw::ThreefoldRepetition)));
+        for (from, to) in shuffle.iter().chain(&shuffle) {
+            assert!(game.apply_move(*from, *to, None));
+        }
+        assert_eq!(game.draw_reason(), Some(Draw::FivefoldRepetition));
+        game.undo();
+        assert_eq!(game.repetitions(), 4);
+    }
+
+    #[test]
+    fn dead_position_draws() {
+        let cases = [
+            ("8/8/8/4k3/8/8/8/4K3 w - - 0 1", true),
+            ("8/8/8/4k3/8/8/8/3BK3 w - - 0 1", true),
+            ("8/8/8/4k3/8/8/8/3NK3 b - - 0 1", true),
+            ("2b5/8/8/4k3/8/8/8/3BK3 w - - 0 1", true),
+            ("1b6/8/8/4k3/8/8/8/3BK3 w - - 0 1", false),
+            ("8/8/8/4k3/8/8/8/2NNK3 w - - 0 1", false),
+            ("8/8/8/4k3/8/8/4P3/4K3 w - - 0 1", false),
+            ("8/8/8/4k3/8/8/8/R3K3 w - - 0 1", false),
+        ];
+        for (fen, dead) in cases {
+            let mut game = Game::from_fen(fen).unwrap();
+            assert_eq!(game.insufficient_material(), dead, "{fen}");
+            assert_eq!(game.draw_reason() == Some(Draw::InsufficientMaterial), dead, "{fen}");
+        }
+        let mut game = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(game.draw_reason(), Some(Draw::Stalemate));
+        let mut game = Game::from_fen("7k/8/6K1/8/8/8/8/R7 b - - 100 80").unwrap();
+        assert_eq!((game.draw_reason(), game.claimable_draw()), (None, Some(Draw::FiftyMoveRule)));
+        let mut game = Game::from_fen("7k/8/6K1/8/8/8/8/R7 b - - 150 105").unwrap();
+        assert_eq!(game.draw_reason(), Some(Draw::SeventyFiveMoveRule));
+    }
+
+    #[test]
+    fn ending_the_game() {
+        let mut game = Game::two_player_game(false);
+        assert!(game.apply_move((4, 6), (4, 4), None));
+        game.resign();
+        assert_eq!(game.status(), Some(Outcome::Win(Player::One)));
+        assert_eq!(game.result(), Some(GameResult::Win(Player::One, Termination::Resignation)));
+        game.undo();
+        assert_eq!(game.status(), None);
+        assert_eq!(game.claim_draw(), None);
+        game.agree_draw();
+        assert_eq!(game.status(), Some(Outcome::Draw(Draw::Agreement)));
+
+        let mut game = Game::from_fen("7k/8/6K1/8/8/8/8/R7 b - - 100 80").unwrap();
+        assert_eq!(game.claim_draw(), Some(Draw::FiftyMoveRule));
+        assert_eq!(game.result(), Some(GameResult::Draw(Draw::FiftyMoveRule)));
+
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert!(!game.accepts_draw(Player::One));
+        assert!(game.accepts_draw(Player::Two));
+        // an even position is taken whoever is to move
+        for fen in ["8/8/8/4k3/8/8/8/4K3 w - - 0 1", "8/8/8/4k3/8/8/8/4K3 b - - 0 1"] {
+            let game = Game::from_fen(fen).unwrap();
+            assert!(game.accepts_draw(Player::One) && game.accepts_draw(Player::Two), "{fen}");
+        }
+    }
+
+
+    #[test]
+    fn hash_is_updated_incrementally() {
+        let mut game = Game::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        let start = game.hash();
+        let moves = [((4, 3), (3, 2)), ((4, 0), (6, 0)), ((1, 1), (0, 0)), ((6, 0), (6, 1)), ((0, 0), (5, 0))];
+        for (from, to) in moves {
+            assert!(game.apply_move(from, to, None));
+            assert_eq!(game.hash(), Game::from_fen(&game.to_fen()).unwrap().hash());
+        }
+        for _ in moves {
+            game.undo();
+        }
+        assert_eq!(game.hash(), start);
+
+        // the same position reached by different move orders has the same hash
+        let mut first = Game::two_player_game(false);
+        let mut second = Game::two_player_game(false);
+        for (from, to) in [((6, 7), (5, 5)), ((6, 0), (5, 2)), ((1, 7), (2, 5))] {
+            first.apply_move(from, to, None);
+        }
+        for (from, to) in [((1, 7), (2, 5)), ((6, 0), (5, 2)), ((6, 7), (5, 5))] {
+            second.apply_move(from, to, None);
+        }
+        assert_eq!(first.hash(), second.hash());
+        assert_ne!(first.hash(), Game::two_player_game(false).hash());
+    }
+
+    #[test]
+    fn consults_the_endgame_tables() {
+        let mut game = Game::from_fen("8/8/8/8/8/2k5/8/3Q3K w - - 0 1").unwrap();
+        let won = game.evaluator.evaluate(game.position());
+        assert!(won > Material.evaluate(game.position()));
+        game.set_evaluator(Arc::new(Material));
+        assert_eq!(game.evaluator.evaluate(game.position()), won);
+    }
+
+    #[test]
+    fn allocates_the_cache_on_the_first_search() {
+        let mut game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert!(game.cache.get().is_none());
+        assert!(game.search(1).is_some());
+        assert!(game.cache.get().is_some());
+        let cache = Arc::new(TranspositionTable::new(1));
+        let game = Game::engine(None, None, Some(cache.clone()));
+        assert!(Arc::ptr_eq(game.cache(), &cache));
+    }
+
+    #[test]
+    fn iterative_deepening_stops_within_the_budget() {
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let mut depths = Vec::new();
+        let start = Instant::now();
+        let result = game
+            .iterative_deepening(MAX_SEARCH_DEPTH, Some(Duration::from_millis(200)), |result| depths.push(result.depth))
+            .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(result.best_move.to_string(), "a1a8");
+        assert_eq!(result.pv, vec![result.best_move]);
+        assert_eq!(result.score, Score::Mate(1));
+        assert!(result.nodes > 0 && result.tt_probes >= result.tt_hits);
+        assert_eq!(depths, (1..=depths.len() as u8).collect::<Vec<u8>>());
+        assert!(depths.len() > 1 && depths.len() < MAX_SEARCH_DEPTH as usize);
+    }
+
+    #[test]
+    fn searches_the_first_root_move_in_full() {
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        let mut game = Game::from_fen(fen).unwrap();
+        let moves = game.position.legal_moves();
+        let (exact, _) = game.search_moves(&moves, 3, None, true).unwrap();
+        let game = Game::from_fen(fen).unwrap();
+        let mate = moves.iter().position(|mov| mov.in_position(&game.position).to_string() == "a1a8").unwrap();
+        let mut moves = moves;
+        moves.swap(0, mate);
+        let (bounded, _) = game.search_moves(&moves, 3, None, false).unwrap();
+        assert_eq!(bounded[0], exact[mate]);
+        // the other moves only have to be shown to be worse than the mate
+        for &(mov, score) in &bounded[1..] {
+            let exact_score = exact.iter().find(|&&(other, _)| other == mov).unwrap().1;
+            assert!(score < bounded[0].1 && exact_score <= score);
+        }
+    }
+
+    #[test]
+    fn thinking_uses_the_clock() {
+        let mut game = Game::from_fen(fen::START_FEN).unwrap();
+        game.search_depth = MAX_SEARCH_DEPTH;
+        game.set_time_control(Some(TimeControl::clock(Duration::from_secs(3), Duration::from_millis(100))));
+        let start = Instant::now();
+        assert!(game.think(|_| ()).is_some());
+        assert!(game.punch_clock(start.elapsed()));
+        let remaining = game.clock(Player::One).and_then(|clock| clock.remaining()).unwrap();
+        assert!(remaining < Duration::from_millis(3100) && remaining > Duration::from_secs(2));
+        assert_eq!(game.clock(Player::Two), game.time_control());
+    }
+
+    #[test]
+    fn finds_the_shortest_mate() {
+        // legal's mate, the knight check can only be taken by the pawn, which lets the bishop mate on f7
+        let game = Game::from_fen("r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1").unwrap();
+        let result = game.iterative_deepening(4, None, |_| ()).unwrap();
+        assert_eq!(result.best_move.to_string(), "d5f6");
+        assert_eq!(result.score, Score::Mate(2));
+        assert_eq!(result.score.to_string(), "mate in 2");
+
+        // a smothered mate, sacrificing the queen so that the rook has to block the king in
+        let game = Game::from_fen("r5k1/5Npp/8/8/2Q5/8/PP6/7K w - - 0 1").unwrap();
+        let result = game.iterative_deepening(5, None, |_| ()).unwrap();
+        assert_eq!(result.best_move.to_string(), "f7h6");
+        assert_eq!(result.score, Score::Mate(3));
+
+        // and the player being mated sees it coming
+        let game = Game::from_fen("r5k1/6pp/7N/8/2Q5/8/PP6/7K b - - 1 1").unwrap();
+        let result = game.iterative_deepening(4, None, |_| ()).unwrap();
+        assert_eq!(result.score, Score::Mate(-2));
+    }
+
+    #[test]
+    fn plays_the_quickest_tablebase_win() {
+        let tablebase = Arc::new(crate::syzygy::tests::fixtures());
+        let mut game = Game::from_fen("8/8/8/8/8/2k5/8/3Q3K w - - 0 1").unwrap();
+        game.set_tablebase(Some(tablebase.clone()));
+        let result = game.iterative_deepening(4, None, |_| ()).unwrap();
+        let quickest = tablebase
+            .root_moves(game.position())
+            .unwrap()
+            .into_iter()
+            .filter(|&(_, wdl, _)| wdl == crate::syzygy::Wdl::Win)
+            .map(|(_, _, dtz)| dtz)
+            .min()
+            .unwrap();
+        let (_, wdl, dtz) = tablebase
+            .root_moves(game.position())
+            .unwrap()
+            .into_iter()
+            .find(|&(mov, _, _)| mov == Move::from(result.best_move))
+            .unwrap();
+        assert_eq!((wdl, dtz), (crate::syzygy::Wdl::Win, quickest));
+        assert!(matches!(result.score, Score::Centipawns(centipawns) if centipawns > 10_000));
+    }
+
+    #[test]
+    fn running_out_of_time() {
+        let second = Duration::from_secs(1);
+        let mut game = Game::two_player_game(false);
+        game.set_time_control(Some(TimeControl::periods(second * 60, Bonus::Increment(second), None)));
+        assert!(game.punch_clock(second * 10));
+        assert!(game.apply_move((4, 6), (4, 4), None));
+        assert_eq!(game.clock(Player::One).unwrap().remaining(), Some(second * 51));
+        assert!(game.to_string().contains("0:51"));
+        // taking the move back leaves the time spent on it off the clock
+        game.undo();
+        assert_eq!(game.clock(Player::One).unwrap().remaining(), Some(second * 51));
+        assert!(game.apply_move((4, 6), (4, 4), None));
+        assert!(!game.punch_clock(second * 60));
+        assert_eq!((game.flagged(), game.status()), (Some(Player::Two), Some(Outcome::Win(Player::One))));
+        game.restart();
+        assert_eq!((game.flagged(), game.clock(Player::One).unwrap().remaining()), (None, Some(second * 60)));
+
+        // a lone king can't win on time
+        game.load_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        assert!(!game.punch_clock(second * 60));
+        assert_eq!(game.status(), Some(Outcome::Draw(Draw::TimeoutVsInsufficientMaterial)));
+        // but a knight can when the other king has pieces to be hemmed in by
+        game.load_fen("4k3/4p3/8/8/8/8/8/4K1N1 b - - 0 1").unwrap();
+        game.set_time_control(Some(TimeControl::clock(second, Duration::ZERO)));
+        assert!(!game.punch_clock(second));
+        assert_eq!(game.status(), Some(Outcome::Win(Player::One)));
+        // nor can bishops on one color
+        game.load_fen("4k3/8/8/8/8/4B3/8/2B1K3 b - - 0 1").unwrap();
+        game.set_time_control(Some(TimeControl::clock(second, Duration::ZERO)));
+        assert!(!game.punch_clock(second));
+        assert_eq!(game.status(), Some(Outcome::Draw(Draw::TimeoutVsInsufficientMaterial)));
+    }
 }
@@ -0,0 +1,341 @@
+use std::{io, time::Instant};
+
+use chess::{format_coord, parse_coord, piece::{Move, MoveKind}, position::PieceType, san, Game, MoveError, SearchResult};
+
+// plays one turn of an interactive game, returns whether the game is over
+pub fn turn(game: &mut Game) -> bool {
+    println!("{}", game);
+    println!("It's {}'s turn.", game.current_player());
+
+    if game.status().is_some() {
+        return game_over(game);
+    }
+    if game.is_last_halfmove() {
+        println!("The halfmove clock is nearly up! Next move must be a capture or pawn move.");
+    }
+
+    // a player's clock runs from when the board is shown until they make their move
+    let start = Instant::now();
+    if game.is_rl_training() {
+        if game.has_model() {
+            return rl_training_move(game, start);
+        } else {
+            return algorithm_move(game, start);
+        }
+    }
+    if !game.is_two_player() && game.computer_player() == Some(game.current_player()) {
+        return algorithm_move(game, start);
+    }
+    let in_check = game.in_check(game.current_player());
+    if in_check {
+        println!("You're in check!");
+    }
+    if let Some(reason) = game.claimable_draw() {
+        println!("You can claim a draw by {reason} with \"claim\".");
+    }
+
+    loop {
+        // None means the game ended without a move, by resigning or a draw, or a move was taken back, which starts
+        // the turn over
+        let Some(mov) = get_move(game) else {
+            return game_over(game);
+        };
+        match game.check_move(mov.from, mov.to) {
+            Ok(()) => (),
+            Err(MoveError::IntoCheck) if in_check => {
+                println!("Invalid move while you are in check! go again");
+                continue;
+            }
+            Err(err) => {
+                println!("{err}! go again.");
+                continue;
+            }
+        }
+        if mov.promotion.is_some() && !game.is_promotion(mov.from, mov.to) {
+            println!("Only a pawn reaching the last rank can promote! go again.");
+            continue;
+        }
+        if !game.punch_clock(start.elapsed()) {
+            return game_over(game);
+        }
+        play_move(game, mov);
+        break;
+    }
+    game_over(game)
+}
+
+// announces the result once the game has ended
+fn game_over(game: &mut Game) -> bool {
+    game.status();
+    let Some(result) = game.result() else {
+        return false;
+    };
+    println!("{game}");
+    println!("Game over!");
+    println!("{result}");
+    true
+}
+
+// plays a legal move, telling the players about any capture
+fn play_move(game: &mut Game, mov: Move) {
+    let captured = game.get(mov.to).map(|piece| piece.name().to_string()).or_else(|| {
+        matches!(mov.kind, MoveKind::EnPassant(_)).then(|| "pawn".to_string())
+    });
+    assert!(game.apply_move(mov.from, mov.to, mov.promotion_letter()), "Invalid move!");
+    if let Some(captured) = captured {
+        println!(
+            "Player {} took {}'s {}!",
+            game.current_player().other().number(),
+            game.current_player(),
+            captured
+        );
+    }
+}
+
+// the hint for a player searches as long as the computer would, without taking any time off a clock
+fn get_best_move(game: &mut Game, hint: bool) -> Option<Move> {
+    println!("Thinking...");
+    // the positions searched at each depth over those at the one before, which better move ordering keeps down
+    let mut branching_factor = None;
+    let (mut total_nodes, mut last_nodes) = (0, 0);
+    let report = |result: &SearchResult| {
+        let nodes = result.nodes - total_nodes;
+        if last_nodes > 0 {
+            branching_factor = Some(nodes as f64 / last_nodes as f64);
+        }
+        (total_nodes, last_nodes) = (result.nodes, nodes);
+    };
+    let result = if hint {
+        let budget = game.time_control().map(|time_control| time_control.budget());
+        game.iterative_deepening(game.search_depth(), budget, report)
+    } else {
+        game.think(report)
+    }?;
+    print_search_result(game, &result, branching_factor);
+    Some(result.best_move)
+}
+
+fn print_search_result(game: &Game, result: &SearchResult, branching_factor: Option<f64>) {
+    println!("Evaluated to a depth of {} in {:?}, score {}", result.depth, result.elapsed, result.score);
+    println!("Expected line: {}", san::line_to_san(game, &result.pv));
+    print!(
+        "Searched {} positions ({} per second), {:.0}% found in the transposition table",
+        result.nodes,
+        result.nps(),
+        result.tt_hit_rate() * 100.0
+    );
+    match branching_factor {
+        Some(branching_factor) => println!(", branching factor {branching_factor:.2}"),
+        None => println!(),
+    }
+}
+
+fn algorithm_move(game: &mut Game, start: Instant) -> bool {
+    if game.claimable_draw().is_some() && game.accepts_draw(game.current_player()) {
+        game.claim_draw();
+        println!("Player {} claimed a draw", game.current_player().number());
+        return game_over(game);
+    }
+    let book_move = game.book_move();
+    if book_move.is_some() {
+        println!("Playing from the opening book");
+    }
+    let Some(mov) = book_move.or_else(|| get_best_move(game, false)) else {
+        println!(
+            "No possible moves for player {}!",
+            game.current_player().number()
+        );
+        return true;
+    };
+    if !game.punch_clock(start.elapsed()) {
+        return game_over(game);
+    }
+    play_move(game, mov);
+    println!("Player {} moved {}", game.current_player().other().number(), game.history().last().unwrap());
+    game_over(game)
+}
+
+fn rl_training_move(game: &mut Game, start: Instant) -> bool {
+    let now = std::time::SystemTime::now();
+    let (mov, loss) = game.training_move();
+    let elapsed = now.elapsed().unwrap();
+    println!(
+        "Time to evaluate best move to depth of {}: {:?}",
+        game.search_depth(), elapsed
+    );
+    println!("Loss: {:?}", loss);
+    if !game.punch_clock(start.elapsed()) {
+        return game_over(game);
+    }
+    play_move(game, mov);
+    println!("Player {} moved {}", game.current_player().other().number(), game.history().last().unwrap());
+    game_over(game)
+}
+
+fn get_move(game: &mut Game) -> Option<Move> {
+    println!("Enter a move or \"help\" to see more commands");
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    if input.to_ascii_lowercase().trim() == "exit" {
+        std::process::exit(0);
+    } else if input.to_ascii_lowercase().trim() == "resign" {
+        game.resign();
+        return None;
+    } else if input.to_ascii_lowercase().trim() == "draw" {
+        if offer_draw(game) {
+            game.agree_draw();
+            return None;
+        }
+        println!("Player {} declined the draw.", game.current_player().other().number());
+        return get_move(game);
+    } else if input.to_ascii_lowercase().trim() == "claim" {
+        if game.claim_draw().is_some() {
+            return None;
+        }
+        println!("There's no draw to claim, it takes fifty moves without a capture or pawn move or the same position three times!");
+        return get_move(game);
+    } else if input.to_ascii_lowercase().trim() == "hint" {
+        if game.allows_hints() {
+            let mov = get_best_move(game, true).unwrap();
+            println!("Hint, your best move is: {}", game.san(mov.from, mov.to, mov.promotion_letter()));
+        } else {
+            println!("Hints are turned off!");
+        }
+        return get_move(game);
+    } else if input.to_ascii_lowercase().trim() == "moves" {
+        for position in game.get_pieces(game.current_player()) {
+            let piece = game.get(position).expect("Piece not found! something went wrong");
+            let piece_moves = piece.get_legal_moves(position, game);
+            if !piece_moves.is_empty() {
+                let sans = piece_moves.iter().map(|&to| game.san(position, to, None)).collect::<Vec<String>>();
+                println!("{} {} -> {}", piece.name(), format_coord(&position), sans.join(", "));
+            }
+        }
+        return get_move(game);
+    } else if ["undo", "redo"].contains(&input.to_ascii_lowercase().trim()) {
+        let redo = input.to_ascii_lowercase().trim() == "redo";
+        match take_back(game, redo) {
+            Some(moves) => {
+                println!("{} {}", if redo { "Replayed" } else { "Took back" }, moves.join(", "));
+                return None;
+            }
+            None => println!("No moves to {}!", if redo { "redo" } else { "undo" }),
+        }
+        return get_move(game);
+    } else if input.to_ascii_lowercase().trim() == "help" {
+        println!("Commands:");
+        println!("  a2      - display all possible moves for the piece at a2");
+        println!("  a2 a4   - move the piece at a2 to a4");
+        println!("  e7 e8 n - promote a pawn to a knight, or q, r or b, a queen if left out");
+        println!("  Nf3     - make a move in standard algebraic notation, like e4, exd5, O-O or e8=Q");
+        println!("  moves   - see all possible moves");
+        println!("  hint    - get a hint for your next move");
+        println!("  undo    - take back your last move");
+        println!("  redo    - play a move you took back again");
+        println!("  draw    - offer your opponent a draw");
+        println!("  claim   - claim a draw by the fifty-move rule or threefold repetition");
+        println!("  resign  - resign the game");
+        println!("  exit    - exit the game");
+        return get_move(game);
+    }
+    match input.split_whitespace().collect::<Vec<&str>>()[..] {
+        // a square with a piece on it can't be a pawn push, so show that piece's moves
+        [square] if parse_coord(&square.to_ascii_lowercase()).is_some_and(|from| game.get(from).is_some()) => {
+            see_all_moves(game, parse_coord(&square.to_ascii_lowercase()).unwrap());
+            get_move(game)
+        }
+        [mov] => match san::from_san(game, mov) {
+            Ok(mov) => Some(mov),
+            Err(err) => {
+                print!("{err}! ");
+                get_move(game)
+            }
+        },
+        [from, to] => match (parse_coord(&from.to_ascii_lowercase()), parse_coord(&to.to_ascii_lowercase())) {
+            (Some(from), Some(to)) => Some(game.move_between(from, to, None)),
+            _ => {
+                print!("Invalid input! ");
+                get_move(game)
+            }
+        },
+        [from, to, promotion] => {
+            let promotion = promotion.chars().next().filter(|_| promotion.len() == 1).and_then(PieceType::promotion);
+            match (parse_coord(&from.to_ascii_lowercase()), parse_coord(&to.to_ascii_lowercase()), promotion) {
+                (Some(from), Some(to), Some(promotion)) => Some(game.move_between(from, to, Some(promotion))),
+                (Some(_), Some(_), None) => {
+                    print!("Invalid piece to promote to, enter q, r, b or n! ");
+                    get_move(game)
+                }
+                _ => {
+                    print!("Invalid input! ");
+                    get_move(game)
+                }
+            }
+        }
+        _ => {
+            print!("Invalid input! ");
+            get_move(game)
+        }
+    }
+}
+
+// asks the opponent whether they take a draw, the computer deciding by its evaluation of the position
+fn offer_draw(game: &mut Game) -> bool {
+    let opponent = game.current_player().other();
+    if game.computer_player() == Some(opponent) {
+        println!("Thinking...");
+        return game.accepts_draw(opponent);
+    }
+    println!("Player {} offers a draw, player {} do you accept? (y/n)", game.current_player().number(), opponent.number());
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    ["y", "yes"].contains(&input.to_ascii_lowercase().trim())
+}
+
+// undo and redo work on whole turns, which against the computer are its reply and your move
+fn take_back(game: &mut Game, redo: bool) -> Option<Vec<String>> {
+    let plies = if game.is_two_player() { 1 } else { 2 };
+    let step = |game: &mut Game, redo: bool| if redo { game.redo() } else { game.undo() };
+    let mut moves = Vec::with_capacity(plies);
+    while moves.len() < plies {
+        match step(game, redo) {
+            Some(mov) => moves.push(mov),
+            None => break,
+        }
+    }
+    if moves.len() < plies {
+        // put back the half of a turn that could be taken back
+        for _ in &moves {
+            step(game, !redo);
+        }
+        return None;
+    }
+    Some(moves)
+}
+
+fn see_all_moves(game: &mut Game, from: (u8, u8)) {
+    if let Some(piece) = game.get(from) {
+        let moves = piece.get_legal_moves(from, game);
+        if moves.is_empty() {
+            println!(
+                "Player {}'s {} has no legal moves!",
+                piece.player().number(),
+                piece.name()
+            );
+            return;
+        }
+        println!(
+            "Player {}'s {} can move to:",
+            piece.player().number(),
+            piece.name()
+        );
+        let moves = moves
+            .iter()
+            .map(format_coord)
+            .collect::<Vec<String>>()
+            .join(",");
+        println!("{moves}");
+    } else {
+        println!("There's no piece there!");
+    }
+}
@@ -2,7 +2,7 @@ use std::{any::Any, fmt::{Display, Formatter}};
 
 use colored::Colorize;
 
-use crate::{game::Game, rook::Rook, piece::{Construct, Move, Piece, DynClone}, player::Player};
+use crate::{game::Game, rook::Rook, piece::{Construct, MoveKind, Piece, DynClone}, player::Player};
 
 #[derive(Clone, Debug)]
 pub struct King {
@@ -19,7 +19,7 @@ impl Piece for King {
             }
             let new_pos = (new_pos.0 as u8, new_pos.1 as u8);
             if game.is_not_player(new_pos, self.player) {
-                if !game.try_move_for_check(position, new_pos, self.player) {
+                if !game.try_move_for_check(position, new_pos) {
                     moves.push(new_pos);
                 }
             }
@@ -37,16 +37,16 @@ impl Piece for King {
     }
 
     //doesn't  handle friendly fire or moving into check
-    fn valid_move(&self, from: (u8,u8), to: (u8,u8), game: &mut Game) -> Move {
+    fn valid_move(&self, from: (u8,u8), to: (u8,u8), game: &mut Game) -> MoveKind {
         let (x, y) = (to.0 as i8 - from.0 as i8, to.1 as i8 - from.1 as i8);
         if x.abs() < 2 && y.abs() < 2 {
-            Move::Normal
+            MoveKind::Normal
         } else if to.0 == 2 && to.1 == from.1 && self.can_castle_left(from, game) {
-            Move::Castle
+            MoveKind::Castle
         } else if to.0 == 6 && to.1 == from.1 && self.can_castle_right(from, game) {
-            Move::Castle
+            MoveKind::Castle
         } else {
-            Move::Invalid
+            MoveKind::Invalid
         }
     }
 
@@ -78,11 +78,12 @@ impl King {
         }
         // if game.square_is_none((3,y)) && game.square_is_none((2,y)) && game.square_is_none((1,y)) {
         if [3,2,1].iter().all(|x| game.square_is_none((*x,y))) {
-            if [3,2,1].iter().all(|x| !game.try_move_for_check((4,y), (*x,y), self.player)) {
+            // the king passes over d and c, the rook can pass over an attacked b square
+            if [3,2].iter().all(|x| !game.try_move_for_check((4,y), (*x,y))) {
                 if let Some(rook) = game.get((0,y)) {
                     if rook.is_type::<Rook>() {
                         if !game.has_left_rook_moved(self.player) {
-                            return !game.try_move_for_check((4,y), (2,y), self.player);
+                            return !game.try_move_for_check((4,y), (2,y));
                         }
                     }
                 }
@@ -101,11 +102,11 @@ impl King {
         }
         // if game.square_is_none((5,y)) && game.square_is_none((6,y)) {
         if [5,6].iter().all(|&x| game.square_is_none((x,y))) {
-            if [5,6].iter().all(|&x| !game.try_move_for_check((4,y), (x,y), self.player)) {
+            if [5,6].iter().all(|&x| !game.try_move_for_check((4,y), (x,y))) {
                 if let Some(rook) = game.get((7,y)) {
                     if rook.is_type::<Rook>() {
                         if !game.has_right_rook_moved(self.player) {
-                            return !game.try_move_for_check((4,y), (6,y), self.player);
+                            return !game.try_move_for_check((4,y), (6,y));
                         }
                     }
                 }
@@ -157,7 +158,6 @@ mod tests {
         board[7][7] = Some(Box::new(Rook::new(Player::One)));
 
         let mut game = Game::two_player_game(false);
-        game.set_pieces(vec![(0,7),(4,7),(7,7)], vec![(0,0),(4,0),(7,0)]);
         game.set_board(board);
 
         print!("{game}");
@@ -187,7 +187,6 @@ mod tests {
         board[7][6] = Some(Box::new(Rook::new(Player::One)));
 
         let mut game = Game::two_player_game(false);
-        game.set_pieces(vec![(2,7),(4,7),(6,7)], vec![(0,0),(4,0),(7,0)]);
         game.set_board(board);
 
         print!("{game}");
@@ -212,7 +211,6 @@ mod tests {
         board[7][7] = Some(Box::new(Rook::new(Player::One)));
 
         let mut game = Game::two_player_game(false);
-        game.set_pieces(vec![(0,7),(4,7),(7,7)], vec![(3,5)]);
         game.set_board(board);
 
         print!("{game}");
@@ -235,7 +233,6 @@ mod tests {
         board[3][1] = Some(Box::new(Bishop::new(Player::One)));
 
         let mut game = Game::two_player_game(false);
-        game.set_pieces(vec![(1,3)], vec![(4,0),(5,1),(5,0),(4,1)]);
         game.set_board(board);
         game.set_player(Player::Two);
 
@@ -248,4 +245,16 @@ mod tests {
 
         assert_eq!(king.get_legal_moves((4,0), &mut game).len(), 1);
     }
+
+    #[test]
+    fn castle_past_attacked_rook_square() {
+        let mut game = Game::from_fen("1r2k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        let king = game.get((4,7)).unwrap();
+        let king = king.get_piece::<King>().unwrap();
+
+        assert!(king.can_castle_left((4,7), &mut game));
+
+        let mut game = Game::from_fen("2r1k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        assert!(!king.can_castle_left((4,7), &mut game));
+    }
 }
\ No newline at end of file
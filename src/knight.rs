@@ -2,7 +2,7 @@ use std::{any::Any, fmt::{Display, Formatter}};
 
 use colored::Colorize;
 
-use crate::{game::Game, piece::{Construct, Move, Piece, DynClone}, player::Player};
+use crate::{game::Game, piece::{Construct, MoveKind, Piece, DynClone}, player::Player};
 
 #[derive(Clone, Debug)]
 pub struct Knight {
@@ -19,17 +19,17 @@ impl Piece for Knight {
                 continue;
             }
             let new_pos = (new_pos.0 as u8, new_pos.1 as u8);
-            if game.is_not_player(new_pos, self.player)  && !game.try_move_for_check(position, new_pos, self.player){
+            if game.is_not_player(new_pos, self.player)  && !game.try_move_for_check(position, new_pos){
                 moves.push(new_pos);
             }
         }
         moves
     }
 
-    fn valid_move(&self, from: (u8,u8), to: (u8,u8), _: &mut Game) -> Move {
+    fn valid_move(&self, from: (u8,u8), to: (u8,u8), _: &mut Game) -> MoveKind {
         let (x, y) = (to.0 as i8 - from.0 as i8, to.1 as i8 - from.1 as i8);
         let valid = (x.abs() == 2 && y.abs() == 1) || (x.abs() == 1 && y.abs() == 2);
-        if valid {Move::Normal} else {Move::Invalid}
+        if valid {MoveKind::Normal} else {MoveKind::Invalid}
     }
 
     fn player(&self) -> Player {
@@ -0,0 +1,43 @@
+extern crate tensorflow;
+pub mod game;
+pub mod book;
+pub mod bitboard;
+pub mod position;
+pub mod fen;
+pub mod piece;
+pub mod king;
+pub mod queen;
+pub mod rook;
+pub mod bishop;
+pub mod knight;
+pub mod pawn;
+pub mod player;
+pub mod outcome;
+pub mod model;
+pub mod uci;
+pub mod san;
+pub mod pgn;
+pub mod perft;
+pub mod tt;
+pub mod eval;
+pub mod evaluator;
+pub mod endgame;
+pub mod syzygy;
+pub mod time_manager;
+mod search;
+mod zobrist;
+
+pub use book::Book;
+pub use endgame::{EndgameEvaluator, EndgameResult, EndgameTables};
+pub use eval::{EvalError, EvalParams};
+pub use evaluator::{Blend, Evaluator, EvaluatorKind, Material};
+pub use fen::{FenError, START_FEN};
+pub use game::{format_coord, parse_coord, Game, MoveError};
+pub use model::Model;
+pub use outcome::{Draw, GameResult, Outcome, Termination};
+pub use player::Player;
+pub use san::SanError;
+pub use search::{Score, SearchConfig, SearchResult};
+pub use syzygy::{Tablebase, Wdl};
+pub use time_manager::{Bonus, TimeControl};
+pub use tt::TranspositionTable;
@@ -1,59 +1,149 @@
-extern crate tensorflow;
-mod game;
-mod piece;
-mod king;
-mod queen;
-mod rook;
-mod bishop;
-mod knight;
-mod pawn;
-mod player;
-mod model;
 mod args;
+mod interactive;
 
-
-use std::{collections::HashMap, sync::{Mutex, Arc}};
+use std::{sync::Arc, time::Duration};
 use args::ChessArgs;
+use chess::{
+    game::{DEFAULT_EPSILON_DECAY, MAX_SEARCH_DEPTH},
+    pgn, perft, uci, Bonus, Book, Tablebase, EvalParams, EvaluatorKind, Game, Model, Outcome, Player, TimeControl, TranspositionTable,
+};
 use clap::Parser;
-use game::Game;
-use model::Model;
-use player::Player;
-use game::DEFAULT_EPSILON_DECAY;
 
 fn main() {
     let args = ChessArgs::parse();
     
     match args.game_type {
         args::GameType::TwoPlayer(args) => {
-            two_player_game(args.allow_hints);
+            let clock = clock(args.time, args.increment, args.delay, args.moves_per_period);
+            two_player_game(args.allow_hints, clock, args.fen, args.pgn_out);
         },
         args::GameType::SinglePlayer(args) => {
-            single_player_game(args.black, args.heuristic, args.search_depth, args.model_dir);
+            let time_control = time_control(args.movetime, None, None)
+                .or(clock(args.time, args.increment, args.delay, args.moves_per_period));
+            let eval = eval_kind(args.heuristic, args.eval);
+            let book = open_book(args.book).map(Arc::new);
+            let tablebase = open_tablebase(args.syzygy_path).map(Arc::new);
+            single_player_game(args.black, eval, eval_params(args.eval_file), book, args.book_depth, tablebase, args.search_depth, time_control, args.model_dir, args.fen, args.pgn_out);
         },
         args::GameType::SelfPlay(args) => {
-            self_play_games(args.heuristic, args.search_depth, args.num_games, args.model_dir, args.epsilon_greedy, args.epsilon_decay);
+            let time_control = time_control(args.movetime, args.time, args.increment);
+            let eval = eval_kind(args.heuristic, args.eval);
+            let book = open_book(args.book).map(Arc::new);
+            let tablebase = open_tablebase(args.syzygy_path).map(Arc::new);
+            self_play_games(eval, eval_params(args.eval_file), book, args.book_depth, tablebase, args.search_depth, time_control, args.num_games, args.model_dir, args.epsilon_greedy, args.epsilon_decay, args.pgn_out);
+        },
+        args::GameType::Uci(args) => {
+            let eval = eval_kind(args.heuristic, args.eval);
+            uci::uci_loop(eval, eval_params(args.eval_file), open_book(args.book), args.book_depth, open_tablebase(args.syzygy_path), args.search_depth, args.model_dir);
+        },
+        args::GameType::Replay(args) => {
+            replay_game(&args.path, args.game);
+        },
+        args::GameType::Perft(args) => {
+            run_perft(args.depth, args.fen, args.divide);
+        },
+        args::GameType::Book(args) => match args.command {
+            args::BookCommand::Build(args) => build_book(&args.pgn, &args.output, args.depth),
         }
     }
 }
 
-fn two_player_game(allow_hints: bool) {
+fn two_player_game(allow_hints: bool, clock: Option<TimeControl>, fen: Option<String>, pgn_out: Option<String>) {
     let mut game = Game::two_player_game(allow_hints);
-    let mut play_again = true;
-    while play_again {
-        launch_game(&mut game);
-        play_again = user_play_again();
-    }
+    game.set_time_control(clock);
+    load_fen(&mut game, fen);
+    games_loop(&mut game, pgn_out.as_deref(), "Two-player game", "Player 1", "Player 2");
 }
 
-fn single_player_game(black: bool, heuristic: bool, search_depth: Option<u8>, model_dir: Option<String>) {
+#[allow(clippy::too_many_arguments)]
+fn single_player_game(black: bool, eval: EvaluatorKind, eval_params: EvalParams, book: Option<Arc<Book>>, book_depth: u32, tablebase: Option<Arc<Tablebase>>, search_depth: Option<u8>, time_control: Option<TimeControl>, model_dir: Option<String>, fen: Option<String>, pgn_out: Option<String>) {
     let computer_player = if black {Some(Player::One)} else {Some(Player::Two)};
-    let model = if heuristic { None } else { Some(Model::new(model_dir)) };
-    let mut game = Game::single_player_game(computer_player, model.as_ref(), search_depth);
-    games_loop(&mut game);
+    let model = eval.uses_model().then(|| Model::new(model_dir));
+    let mut game = Game::single_player_game(computer_player, model.as_ref(), timed_depth(search_depth, time_control));
+    game.set_evaluator(eval.evaluator(model.as_ref(), &Arc::new(eval_params)));
+    game.set_book(book, book_depth);
+    game.set_tablebase(tablebase);
+    game.set_time_control(time_control);
+    load_fen(&mut game, fen);
+    let (white, black) = if black { ("Computer", "Player") } else { ("Player", "Computer") };
+    games_loop(&mut game, pgn_out.as_deref(), "Single-player game", white, black);
+}
+
+// a per-move budget takes precedence, the two can't be given together anyway
+fn time_control(movetime: Option<u64>, time: Option<u64>, increment: Option<u64>) -> Option<TimeControl> {
+    if let Some(movetime) = movetime {
+        return Some(TimeControl::MoveTime(Duration::from_millis(movetime)));
+    }
+    time.map(|time| TimeControl::clock(Duration::from_secs(time), Duration::from_secs(increment.unwrap_or(0))))
+}
+
+// the same clock for both players in games against people
+fn clock(time: Option<u64>, increment: Option<u64>, delay: Option<u64>, moves_per_period: Option<u32>) -> Option<TimeControl> {
+    let bonus = match delay {
+        Some(delay) => Bonus::Delay(Duration::from_secs(delay)),
+        None => Bonus::Increment(Duration::from_secs(increment.unwrap_or(0))),
+    };
+    time.map(|time| TimeControl::periods(Duration::from_secs(time), bonus, moves_per_period))
+}
+
+// a search limited by time goes as deep as it can unless a depth was given too
+fn timed_depth(search_depth: Option<u8>, time_control: Option<TimeControl>) -> Option<u8> {
+    search_depth.or(time_control.map(|_| MAX_SEARCH_DEPTH))
+}
+
+// the model unless another evaluation is picked
+fn eval_kind(heuristic: bool, eval: Option<EvaluatorKind>) -> EvaluatorKind {
+    eval.unwrap_or(if heuristic { EvaluatorKind::Heuristic } else { EvaluatorKind::Model })
+}
+
+// the hand-crafted heuristic's terms, from a file if one is given
+fn eval_params(eval_file: Option<String>) -> EvalParams {
+    let Some(path) = eval_file else {
+        return EvalParams::default();
+    };
+    EvalParams::from_file(&path).unwrap_or_else(|err| {
+        eprintln!("Invalid evaluation file: {err}");
+        std::process::exit(1);
+    })
+}
+
+fn open_book(path: Option<String>) -> Option<Book> {
+    let path = path?;
+    match Book::open(&path) {
+        Ok(book) => Some(book),
+        Err(err) => {
+            eprintln!("Failed to read book {path}: {err}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn open_tablebase(path: Option<String>) -> Option<Tablebase> {
+    let path = path?;
+    match Tablebase::open(&path) {
+        Ok(tablebase) => Some(tablebase),
+        Err(err) => {
+            eprintln!("Failed to read tablebases in {path}: {err}");
+            std::process::exit(1);
+        }
+    }
 }
 
-fn self_play_games(heuristic: bool, search_depth: Option<u8>, num_games: u16, model_dir: Option<String>, epsilon_greedy: bool, epsilon_decay: Option<f64>) {
-    let model = if heuristic { None } else { Some(Model::new(model_dir)) };
+fn load_fen(game: &mut Game, fen: Option<String>) {
+    if let Some(fen) = fen {
+        if let Err(err) = game.load_fen(&fen) {
+            eprintln!("Invalid FEN: {err}");
+            std::process::exit(1);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn self_play_games(eval: EvaluatorKind, eval_params: EvalParams, book: Option<Arc<Book>>, book_depth: u32, tablebase: Option<Arc<Tablebase>>, search_depth: Option<u8>, time_control: Option<TimeControl>, num_games: u16, model_dir: Option<String>, epsilon_greedy: bool, epsilon_decay: Option<f64>, pgn_out: Option<String>) {
+    let model = eval.uses_model().then(|| Model::new(model_dir));
+    let eval_params = Arc::new(eval_params);
+    // the model only learns from searches that score positions with it alone, on the scale it is trained on
+    let training_model = model.as_ref().filter(|_| eval == EvaluatorKind::Model);
     let mut white_wins = 0;
     let mut black_wins = 0;
     let mut draws = 0;
@@ -62,30 +152,35 @@ fn self_play_games(heuristic: bool, search_depth: Option<u8>, num_games: u16, mo
     } else {
         (None, None)
     };
-    let cache = Arc::new(Mutex::new(HashMap::new()));
+    let cache = Arc::new(TranspositionTable::default());
     let start = std::time::Instant::now();
     let mut times = Vec::with_capacity(num_games as usize);
     for i in 1..num_games+1 {
         if num_games > 1 {
             println!("Playing game {}/{}", i, num_games);
         }
-        let mut game = Game::self_play(model.as_ref(), search_depth, epsilon_greedy, epsilon, epsilon_decay, Some(cache.clone()));
+        let mut game = Game::self_play(training_model, timed_depth(search_depth, time_control), epsilon_greedy, epsilon, epsilon_decay, Some(cache.clone()));
+        game.set_evaluator(eval.evaluator(model.as_ref(), &eval_params));
+        game.set_book(book.clone(), book_depth);
+        game.set_tablebase(tablebase.clone());
+        game.set_time_control(time_control);
         let now = std::time::Instant::now();
         launch_game(&mut game);
         let elapsed = now.elapsed();
         times.push(elapsed);
         println!("Time to play game {}: {:?}", i, elapsed);
+        save_pgn(&game, pgn_out.as_deref(), "Self-play game", i as u32, "Computer", "Computer");
         // if let Some(decay) = epsilon_decay {
         //     epsilon = epsilon.map(|e| e*decay);
         //     println!("Epsilon: {}", epsilon.unwrap());
         // }
         epsilon = epsilon.map(|e| e*epsilon_decay.unwrap());
         match game.winner() {
-            Some(Player::One) => white_wins += 1,
-            Some(Player::Two) => black_wins += 1,
-            None => draws += 1
+            Some(Outcome::Win(Player::One)) => white_wins += 1,
+            Some(Outcome::Win(Player::Two)) => black_wins += 1,
+            _ => draws += 1
         }
-        if !heuristic {
+        if training_model.is_some() {
             game.save_model();
         }
     }
@@ -99,12 +194,110 @@ fn self_play_games(heuristic: bool, search_depth: Option<u8>, num_games: u16, mo
     }
 }
 
-fn games_loop(game: &mut Game) {
+fn games_loop(game: &mut Game, pgn_out: Option<&str>, event: &str, white: &str, black: &str) {
+    let mut round = 1;
     let mut play_again = true;
     while play_again {
         launch_game(game);
+        save_pgn(game, pgn_out, event, round, white, black);
         play_again = user_play_again();
+        if play_again {
+            game.restart();
+            round += 1;
+        }
+    }
+}
+
+fn save_pgn(game: &Game, pgn_out: Option<&str>, event: &str, round: u32, white: &str, black: &str) {
+    if let Some(path) = pgn_out {
+        if let Err(err) = pgn::append_pgn(path, &pgn::write_pgn(game, event, round, white, black)) {
+            eprintln!("Failed to write PGN to {path}: {err}");
+        }
+    }
+}
+
+fn replay_game(path: &str, index: usize) {
+    let games = match std::fs::read_to_string(path) {
+        Ok(text) => pgn::parse_pgn(&text).unwrap_or_else(|err| {
+            eprintln!("Failed to parse {path}: {err}");
+            std::process::exit(1);
+        }),
+        Err(err) => {
+            eprintln!("Failed to read {path}: {err}");
+            std::process::exit(1);
+        }
+    };
+    let Some(pgn) = index.checked_sub(1).and_then(|i| games.get(i)) else {
+        eprintln!("{path} contains {} games", games.len());
+        std::process::exit(1);
+    };
+    for (tag, value) in &pgn.tags {
+        println!("{tag}: {value}");
     }
+    // check the whole game before stepping through it
+    if let Err(err) = pgn.replay() {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+    let mut game = pgn.start_position().unwrap();
+    println!("{game}");
+    for (i, mov) in pgn.moves.iter().enumerate() {
+        println!("Press enter for the next move, or q to quit");
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input).unwrap();
+        if input.trim().eq_ignore_ascii_case("q") {
+            return;
+        }
+        pgn::play_san(&mut game, i + 1, &mov.san).unwrap();
+        println!("{game}");
+        println!("Ply {}: {}", i + 1, mov.san);
+        for comment in &mov.comments {
+            println!("{{{comment}}}");
+        }
+    }
+    println!("Result: {}", pgn.result.as_deref().unwrap_or("*"));
+}
+
+fn build_book(paths: &[String], output: &str, depth: u32) {
+    let mut games = Vec::new();
+    for path in paths {
+        let parsed = std::fs::read_to_string(path).map_err(|err| err.to_string()).and_then(|text| pgn::parse_pgn(&text).map_err(|err| err.to_string()));
+        match parsed {
+            Ok(parsed) => games.extend(parsed),
+            Err(err) => {
+                eprintln!("Failed to read {path}: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+    let book = Book::from_games(&games, depth as usize * 2).unwrap_or_else(|err| {
+        eprintln!("Failed to build book: {err}");
+        std::process::exit(1);
+    });
+    if let Err(err) = book.save(output) {
+        eprintln!("Failed to write {output}: {err}");
+        std::process::exit(1);
+    }
+    println!("Wrote {} moves from {} games to {output}", book.len(), games.len());
+}
+
+fn run_perft(depth: u8, fen: Option<String>, divide: bool) {
+    let mut game = Game::two_player_game(false);
+    load_fen(&mut game, fen);
+    let start = std::time::Instant::now();
+    let nodes = if divide {
+        let moves = perft::divide(&game, depth);
+        for (mov, count) in &moves {
+            println!("{mov}: {count}");
+        }
+        println!();
+        moves.iter().map(|(_, count)| count).sum()
+    } else {
+        perft::perft(&game, depth)
+    };
+    let elapsed = start.elapsed();
+    println!("Nodes searched: {nodes}");
+    println!("Time: {:?} ({:.0} nodes per second)", elapsed, nodes as f64 / elapsed.as_secs_f64());
 }
 
 fn launch_game(game: &mut Game) {
@@ -113,7 +306,7 @@ fn launch_game(game: &mut Game) {
         // print!("\x1b[120S\x1b[1;1H");
         // print!("\x1B[2J\x1B[1;1H");
         // std::process::Command::new(if cfg!(target_os = "windows") {"cls"} else {"clear"}).status().unwrap();
-        game_over = game.turn();
+        game_over = interactive::turn(game);
     }
 }
 
@@ -1,4 +1,5 @@
-use std::{sync::Arc, error::Error, time::Duration};
+use std::{sync::Arc, error::Error};
+use crate::{evaluator::Evaluator, position::Position};
 use tensorflow::{Graph, SavedModelBundle, SessionOptions, SessionRunArgs, Session, Tensor, Operation};
 
 #[derive(Clone)]
@@ -55,7 +56,6 @@ impl Model {
 
     pub fn run_inference(&self, input_data: &Vec<[[[f32; 8]; 8]; 13]>) -> Result<Vec<f32>, Box<dyn Error>> {
         let len = input_data.len() as u64;
-        let now = std::time::SystemTime::now();
 
         let data = input_data.clone().into_iter().flatten().flatten().flatten().collect::<Vec<f32>>();
         let input_tensor: Tensor<f32> = Tensor::new(&[len,13,8,8]).with_values(&data)?;
@@ -71,17 +71,15 @@ impl Model {
     
         let prediction = args.fetch(out)?;
         // println!("data : {:?}", input_tensor);
-        let elapsed = now.elapsed().unwrap_or(Duration::from_secs(0));
-        println!("time to inference {len} inputs: {:?}, {}ms/inf", elapsed, elapsed.as_millis() / len as u128);
         // println!("Prediction: {:?}", prediction);
         
         Ok(prediction.to_vec())
         // Err("this method shouldn't have been called".to_string())
     }
 
-    pub fn back_propagate(&self, input_data: &Vec<[[[f32; 8]; 8]; 13]>, amplified_scores: &Vec<f32>) {
+    // trains the model towards the given scores, returning the loss
+    pub fn back_propagate(&self, input_data: &Vec<[[[f32; 8]; 8]; 13]>, amplified_scores: &Vec<f32>) -> f32 {
         let len = input_data.len() as u64;
-        let now = std::time::SystemTime::now();
 
         let data = input_data.clone().into_iter().flatten().flatten().flatten().collect::<Vec<f32>>();
         let input_tensor: Tensor<f32> = Tensor::new(&[len,13,8,8]).with_values(&data).expect("Can't create tensor from input data");
@@ -98,11 +96,7 @@ impl Model {
         .run(&mut args)
         .expect("Error occurred during calculations");
         
-        let loss: f32 = args.fetch(out).unwrap()[0];
-
-        let elapsed = now.elapsed().unwrap();
-        println!("time to train on {len} inputs: {:?}, {}ms/input", elapsed, elapsed.as_millis() / len as u128);
-        println!("Loss: {:?}", loss);
+        args.fetch(out).unwrap()[0]
     }
 
     pub fn save_model(&self) {
@@ -135,6 +129,25 @@ impl Model {
     // }
 }
 
+// the model is trained on stockfish evaluations divided by 10, and runs much faster on many positions at once
+impl Evaluator for Model {
+    fn evaluate(&self, position: &Position) -> f32 {
+        self.run_inference(&vec![position.to_matrix()]).unwrap()[0]
+    }
+
+    fn evaluate_batch(&self, positions: &[Position]) -> Vec<f32> {
+        self.run_inference(&positions.iter().map(Position::to_matrix).collect()).unwrap()
+    }
+
+    fn pawn_scale(&self) -> f32 {
+        10.0
+    }
+
+    fn prefers_batches(&self) -> bool {
+        true
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
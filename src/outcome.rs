@@ -0,0 +1,81 @@
+use std::fmt::{Display, Formatter, Error};
+
+use crate::player::Player;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Outcome {
+    Win(Player),
+    Draw(Draw),
+}
+
+// how a game ended, the outcome along with what ended it
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GameResult {
+    Win(Player, Termination),
+    Draw(Draw),
+}
+
+// the ways a player can win
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Termination {
+    Checkmate,
+    Resignation,
+    Timeout,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Draw {
+    Stalemate,
+    // claimed by a player once there have been fifty moves without a capture or pawn move
+    FiftyMoveRule,
+    // drawn without a claim after seventy-five
+    SeventyFiveMoveRule,
+    // claimed by a player once the position has occurred three times
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    InsufficientMaterial,
+    // a player ran out of time, but the other couldn't have checkmated them
+    TimeoutVsInsufficientMaterial,
+    Agreement,
+}
+
+impl GameResult {
+    pub fn outcome(self) -> Outcome {
+        match self {
+            GameResult::Win(winner, _) => Outcome::Win(winner),
+            GameResult::Draw(reason) => Outcome::Draw(reason),
+        }
+    }
+}
+
+impl Display for Draw {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            Draw::Stalemate => write!(f, "stalemate"),
+            Draw::FiftyMoveRule => write!(f, "fifty moves without a capture or pawn move"),
+            Draw::SeventyFiveMoveRule => write!(f, "seventy-five moves without a capture or pawn move"),
+            Draw::ThreefoldRepetition => write!(f, "threefold repetition"),
+            Draw::FivefoldRepetition => write!(f, "fivefold repetition"),
+            Draw::InsufficientMaterial => write!(f, "insufficient material"),
+            Draw::TimeoutVsInsufficientMaterial => write!(f, "timeout against insufficient material"),
+            Draw::Agreement => write!(f, "agreement"),
+        }
+    }
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            GameResult::Win(winner, Termination::Checkmate) => {
+                write!(f, "Player {} is in checkmate, {} wins!", winner.other().number(), winner)
+            }
+            GameResult::Win(winner, Termination::Resignation) => {
+                write!(f, "Player {} resigned, {} wins!", winner.other().number(), winner)
+            }
+            GameResult::Win(winner, Termination::Timeout) => {
+                write!(f, "Player {} ran out of time, {} wins!", winner.other().number(), winner)
+            }
+            GameResult::Draw(reason) => write!(f, "Draw by {reason}!"),
+        }
+    }
+}
@@ -2,7 +2,7 @@ use std::{any::Any, fmt::{Display, Formatter}};
 
 use colored::Colorize;
 
-use crate::{game::Game, piece::{Construct, Move, Piece, DynClone}, player::Player};
+use crate::{game::Game, piece::{Construct, MoveKind, Piece, DynClone}, player::Player};
 
 #[derive(Clone, Debug)]
 pub struct Pawn {
@@ -23,12 +23,12 @@ impl Piece for Pawn {
         }
         let new_pos = (position.0, new_y as u8);
         if game.square_is_none(new_pos) {
-            if !game.try_move_for_check(position, new_pos, self.player) {
+            if !game.try_move_for_check(position, new_pos) {
                 moves.push(new_pos);
             }
             if position.1 == (end - 5*sign) as u8 {
                 let new_pos = (new_pos.0, (new_y + sign) as u8);
-                if game.square_is_none(new_pos) && !game.try_move_for_check(position, new_pos, self.player){
+                if game.square_is_none(new_pos) && !game.try_move_for_check(position, new_pos){
                     moves.push(new_pos);
                 }
             }
@@ -38,11 +38,11 @@ impl Piece for Pawn {
                 continue;
             }
             let new_pos = (x as u8, y as u8);
-            if game.is_player(new_pos, self.player.other()) && !game.try_move_for_check(position, new_pos, self.player) {
+            if game.is_player(new_pos, self.player.other()) && !game.try_move_for_check(position, new_pos) {
                 moves.push(new_pos);
             } else if y == end - sign {
                 if let Some(last_double) = game.get_last_double() {
-                    if last_double == (x as u8, (y - sign) as u8) && game.square_is_none(new_pos) && !game.try_move_for_check(position, new_pos, self.player) {
+                    if last_double == (x as u8, (y - sign) as u8) && game.square_is_none(new_pos) && !game.try_move_for_check(position, new_pos) {
                         moves.push(new_pos);
                     }
                 }
@@ -51,7 +51,7 @@ impl Piece for Pawn {
         moves
     }
 
-    fn valid_move(&self, from: (u8,u8), to: (u8,u8), game: &mut Game) -> Move {
+    fn valid_move(&self, from: (u8,u8), to: (u8,u8), game: &mut Game) -> MoveKind {
         let (x, y) = (to.0 as i8 - from.0 as i8, to.1 as i8 - from.1 as i8);
         let (sign, end) = match self.player {
             Player::One => (-1, 2),
@@ -60,21 +60,21 @@ impl Piece for Pawn {
         let single = (x,y) == (0, sign*1) && game.square_is_none(to);
         let double = from.1==(end as i8-sign*4) as u8 && (x,y) == (0, sign*2) && game.check_horiz(from, (from.0, (from.1 as i8 + sign*3) as u8));
         if double {
-            return Move::Double(to);
+            return MoveKind::Double(to);
         }
         let diag = (x.abs() == 1) && (y == sign*1);
         if diag {
             if game.square_is_opponent(to, self.player) {
-                return Move::Normal;
+                return MoveKind::Normal;
             } else if let Some(last_move) = game.get_last_double() {
                 if to.0==last_move.0 && to.1==(end as i8) as u8 && game.square_is_opponent((to.0,(end as i8 - sign) as u8), self.player) {
-                    return Move::EnPassant((to.0,(end as i8 - sign) as u8));
+                    return MoveKind::EnPassant((to.0,(end as i8 - sign) as u8));
                 }
             } else {
-                return Move::Invalid;
+                return MoveKind::Invalid;
             }
         }
-        if single {Move::Normal} else {Move::Invalid}
+        if single {MoveKind::Normal} else {MoveKind::Invalid}
     }
 
     fn player(&self) -> Player {
@@ -0,0 +1,96 @@
+use crate::{game::Game, position::Position};
+
+// counts the leaf nodes of the legal move tree, every promotion piece counting as its own move
+pub fn perft(game: &Game, depth: u8) -> u64 {
+    let mut position = *game.position();
+    count(&mut position, depth)
+}
+
+// the perft count below each legal move, for finding which move a wrong total comes from
+pub fn divide(game: &Game, depth: u8) -> Vec<(String, u64)> {
+    let mut position = *game.position();
+    position
+        .legal_moves()
+        .into_iter()
+        .map(|mov| {
+            let undo = position.make_move(mov);
+            let nodes = count(&mut position, depth.max(1) - 1);
+            position.unmake_move(mov, undo);
+            (mov.to_string(), nodes)
+        })
+        .collect()
+}
+
+fn count(position: &mut Position, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = position.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves
+        .into_iter()
+        .map(|mov| {
+            let undo = position.make_move(mov);
+            let nodes = count(position, depth - 1);
+            position.unmake_move(mov, undo);
+            nodes
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::START_FEN;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    fn assert_perft(fen: &str, counts: &[u64]) {
+        let game = Game::from_fen(fen).unwrap();
+        for (depth, &count) in counts.iter().enumerate() {
+            assert_eq!(perft(&game, depth as u8 + 1), count, "{fen} at depth {}", depth + 1);
+        }
+    }
+
+    #[test]
+    fn start_position() {
+        assert_perft(START_FEN, &[20, 400, 8902]);
+    }
+
+    #[test]
+    fn kiwipete() {
+        assert_perft(KIWIPETE, &[48, 2039, 97862]);
+    }
+
+    #[test]
+    fn rook_endgame() {
+        assert_perft("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238]);
+    }
+
+    #[test]
+    fn promotions_and_castling() {
+        assert_perft("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467]);
+        assert_perft("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1", &[6, 264, 9467]);
+    }
+
+    #[test]
+    fn discovered_checks() {
+        assert_perft("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379]);
+    }
+
+    #[test]
+    fn middlegame() {
+        assert_perft("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[46, 2079, 89890]);
+    }
+
+    #[test]
+    fn divide_adds_up() {
+        let game = Game::from_fen(KIWIPETE).unwrap();
+        let moves = divide(&game, 2);
+        assert_eq!(moves.len(), 48);
+        assert_eq!(moves.iter().map(|(_, count)| count).sum::<u64>(), 2039);
+        assert!(moves.contains(&("e1g1".to_string(), 43)));
+    }
+}
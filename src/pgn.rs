@@ -0,0 +1,473 @@
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+    fs::OpenOptions,
+    io::{self, Write},
+    iter::Peekable,
+    str::Chars,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    fen::{FenError, START_FEN},
+    game::Game,
+    outcome::Outcome,
+    player::Player,
+    san::{self, SanError},
+};
+
+const MAX_LINE_LENGTH: usize = 80;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub comments: Vec<String>,
+    pub moves: Vec<PgnMove>,
+    pub result: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PgnMove {
+    pub san: String,
+    pub nags: Vec<u8>,
+    pub comments: Vec<String>,
+    // alternatives to this move, each starting from the position before it
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PgnError {
+    Syntax(usize, String),
+    Fen(FenError),
+    IllegalMove(usize, SanError),
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            PgnError::Syntax(line, message) => write!(f, "syntax error on line {line}: {message}"),
+            PgnError::Fen(err) => write!(f, "invalid FEN tag: {err}"),
+            PgnError::IllegalMove(ply, err) => write!(f, "illegal move at ply {ply}: {err}"),
+        }
+    }
+}
+
+impl Error for PgnError {}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    StartVariation,
+    EndVariation,
+    Result(String),
+    San(String),
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+
+    pub fn start_position<'a>(&self) -> Result<Game<'a>, PgnError> {
+        match self.tag("FEN") {
+            Some(fen) => Game::from_fen(fen).map_err(PgnError::Fen),
+            None => Ok(Game::two_player_game(false)),
+        }
+    }
+
+    // plays the main line through the game rules, returning the final position
+    pub fn replay<'a>(&self) -> Result<Game<'a>, PgnError> {
+        let mut game = self.start_position()?;
+        for (i, mov) in self.moves.iter().enumerate() {
+            play_san(&mut game, i + 1, &mov.san)?;
+        }
+        Ok(game)
+    }
+}
+
+pub fn play_san(game: &mut Game, ply: usize, san: &str) -> Result<(), PgnError> {
+    let mov = san::from_san(game, san).map_err(|err| PgnError::IllegalMove(ply, err))?;
+    if !game.apply_move(mov.from, mov.to, mov.promotion_letter()) {
+        return Err(PgnError::IllegalMove(ply, SanError::Illegal(san.to_string())));
+    }
+    Ok(())
+}
+
+pub fn parse_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    // the main line is at the bottom of the stack, open variations above it
+    let mut lines: Vec<Vec<PgnMove>> = vec![Vec::new()];
+    let mut in_movetext = false;
+    for (line, token) in tokenize(text)? {
+        match token {
+            Token::Tag(name, value) => {
+                if in_movetext {
+                    finish_game(&mut games, &mut game, &mut lines, line)?;
+                    in_movetext = false;
+                }
+                game.tags.push((name, value));
+            }
+            Token::San(san) => {
+                in_movetext = true;
+                lines.last_mut().unwrap().push(PgnMove { san, ..Default::default() });
+            }
+            Token::Nag(nag) => {
+                let mov = lines.last_mut().unwrap().last_mut();
+                mov.ok_or(PgnError::Syntax(line, "annotation before any move".to_string()))?.nags.push(nag);
+            }
+            Token::Comment(comment) => {
+                let in_variation = lines.len() > 1;
+                match lines.last_mut().unwrap().last_mut() {
+                    Some(mov) => mov.comments.push(comment),
+                    None if !in_variation => game.comments.push(comment),
+                    // comments opening a variation have no move to belong to
+                    None => (),
+                }
+            }
+            Token::StartVariation => {
+                if lines.last().unwrap().is_empty() {
+                    return Err(PgnError::Syntax(line, "variation before any move".to_string()));
+                }
+                in_movetext = true;
+                lines.push(Vec::new());
+            }
+            Token::EndVariation => {
+                if lines.len() == 1 {
+                    return Err(PgnError::Syntax(line, "unmatched ')'".to_string()));
+                }
+                let variation = lines.pop().unwrap();
+                lines.last_mut().unwrap().last_mut().unwrap().variations.push(variation);
+            }
+            Token::Result(result) => {
+                game.result = Some(result);
+                finish_game(&mut games, &mut game, &mut lines, line)?;
+                in_movetext = false;
+            }
+        }
+    }
+    if !game.tags.is_empty() || !lines[0].is_empty() {
+        finish_game(&mut games, &mut game, &mut lines, text.lines().count())?;
+    }
+    Ok(games)
+}
+
+fn finish_game(games: &mut Vec<PgnGame>, game: &mut PgnGame, lines: &mut Vec<Vec<PgnMove>>, line: usize) -> Result<(), PgnError> {
+    if lines.len() > 1 {
+        return Err(PgnError::Syntax(line, "unterminated variation".to_string()));
+    }
+    game.moves = lines.pop().unwrap();
+    lines.push(Vec::new());
+    games.push(std::mem::take(game));
+    Ok(())
+}
+
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, PgnError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    let mut line_start = true;
+    while let Some(c) = chars.next() {
+        let token_line = line;
+        match c {
+            '\n' => {
+                line += 1;
+                line_start = true;
+                continue;
+            }
+            // lines starting with % are escaped from pgn processing
+            '%' if line_start => {
+                take_until(&mut chars, '\n');
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '[' => {
+                let tag = take_until(&mut chars, ']').ok_or(PgnError::Syntax(line, "unterminated tag".to_string()))?;
+                tokens.push((token_line, parse_tag(&tag).ok_or(PgnError::Syntax(line, format!("invalid tag [{tag}]")))?));
+            }
+            '{' => {
+                let comment = take_until(&mut chars, '}').ok_or(PgnError::Syntax(line, "unterminated comment".to_string()))?;
+                line += comment.matches('\n').count();
+                tokens.push((token_line, Token::Comment(comment.split_whitespace().collect::<Vec<&str>>().join(" "))));
+            }
+            ';' => {
+                let comment = take_until(&mut chars, '\n').unwrap_or_default();
+                tokens.push((token_line, Token::Comment(comment.trim().to_string())));
+                line += 1;
+                line_start = true;
+                continue;
+            }
+            '(' => tokens.push((token_line, Token::StartVariation)),
+            ')' => tokens.push((token_line, Token::EndVariation)),
+            '$' => {
+                let nag = take_symbol(&mut chars, String::new());
+                tokens.push((token_line, Token::Nag(nag.parse().map_err(|_| PgnError::Syntax(line, format!("invalid annotation ${nag}")))?)));
+            }
+            _ => {
+                let symbol = take_symbol(&mut chars, c.to_string());
+                tokens.extend(parse_symbol(&symbol).into_iter().map(|token| (token_line, token)));
+            }
+        }
+        line_start = false;
+    }
+    Ok(tokens)
+}
+
+// consumes characters up to the delimiter, returning None if it is never found
+fn take_until(chars: &mut Peekable<Chars>, delimiter: char) -> Option<String> {
+    let mut taken = String::new();
+    let mut in_string = false;
+    for c in chars.by_ref() {
+        if c == delimiter && !in_string {
+            return Some(taken);
+        }
+        // tag values may contain escaped quotes and closing brackets
+        if delimiter == ']' && c == '"' && !taken.ends_with('\\') {
+            in_string = !in_string;
+        }
+        taken.push(c);
+    }
+    None
+}
+
+fn take_symbol(chars: &mut Peekable<Chars>, mut symbol: String) -> String {
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() || "[]{}();$".contains(c) {
+            break;
+        }
+        symbol.push(c);
+        chars.next();
+    }
+    symbol
+}
+
+fn parse_tag(tag: &str) -> Option<Token> {
+    let (name, value) = tag.trim().split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some(Token::Tag(name.to_string(), value.replace("\\\"", "\"").replace("\\\\", "\\")))
+}
+
+fn parse_symbol(symbol: &str) -> Vec<Token> {
+    if ["1-0", "0-1", "1/2-1/2", "*"].contains(&symbol) {
+        return vec![Token::Result(symbol.to_string())];
+    }
+    // move numbers may be written directly against the move, as in 12.e4 or 12...e5
+    let digits = symbol.trim_start_matches(|c: char| c.is_ascii_digit());
+    let symbol = if digits.len() < symbol.len() && digits.starts_with('.') {
+        digits.trim_start_matches('.')
+    } else {
+        symbol
+    };
+    if symbol.is_empty() {
+        return Vec::new();
+    }
+    let san = symbol.trim_end_matches(['!', '?']);
+    let mut tokens = vec![Token::San(san.to_string())];
+    let nag = match &symbol[san.len()..] {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    };
+    tokens.extend(nag.map(Token::Nag));
+    tokens
+}
+
+// writes a finished game with the seven tag roster and its movetext
+pub fn write_pgn(game: &Game, event: &str, round: u32, white: &str, black: &str) -> String {
+    let result = result_token(game.winner());
+    let mut pgn = String::new();
+    for (tag, value) in [
+        ("Event", event.to_string()),
+        ("Site", "?".to_string()),
+        ("Date", pgn_date(SystemTime::now())),
+        ("Round", round.to_string()),
+        ("White", white.to_string()),
+        ("Black", black.to_string()),
+        ("Result", result.to_string()),
+    ] {
+        pgn.push_str(&format_tag(tag, &value));
+    }
+    if game.start_fen() != START_FEN {
+        pgn.push_str(&format_tag("SetUp", "1"));
+        pgn.push_str(&format_tag("FEN", game.start_fen()));
+    }
+    pgn.push('\n');
+    pgn.push_str(&movetext(game.start_fen(), game.history(), result));
+    pgn.push('\n');
+    pgn
+}
+
+pub fn append_pgn(path: &str, pgn: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{pgn}")
+}
+
+pub fn result_token(outcome: Option<Outcome>) -> &'static str {
+    match outcome {
+        Some(Outcome::Win(Player::One)) => "1-0",
+        Some(Outcome::Win(Player::Two)) => "0-1",
+        Some(Outcome::Draw(_)) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+fn format_tag(tag: &str, value: &str) -> String {
+    format!("[{tag} \"{}\"]\n", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn movetext(start_fen: &str, history: &[String], result: &str) -> String {
+    let fields = start_fen.split_whitespace().collect::<Vec<&str>>();
+    let mut white_to_move = fields.get(1) != Some(&"b");
+    let mut move_number = fields.get(5).and_then(|number| number.parse().ok()).unwrap_or(1u32);
+    let mut tokens = Vec::with_capacity(history.len() * 3 / 2 + 1);
+    for (i, san) in history.iter().enumerate() {
+        if white_to_move {
+            tokens.push(format!("{move_number}."));
+        } else if i == 0 {
+            tokens.push(format!("{move_number}..."));
+        }
+        tokens.push(san.clone());
+        if !white_to_move {
+            move_number += 1;
+        }
+        white_to_move = !white_to_move;
+    }
+    tokens.push(result.to_string());
+
+    let mut text = String::new();
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length > 0 && line_length + token.len() + 1 > MAX_LINE_LENGTH {
+            text.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            text.push(' ');
+            line_length += 1;
+        }
+        line_length += token.len();
+        text.push_str(&token);
+    }
+    text
+}
+
+fn pgn_date(time: SystemTime) -> String {
+    let days = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs() / 86400).unwrap_or(0) as i64;
+    // converts days since the epoch to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{year:04}.{month:02}.{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::outcome::Draw;
+
+    #[test]
+    fn movetext_numbering() {
+        let history = ["e4", "e5", "Nf3", "Nc6", "Bb5"].map(String::from);
+        assert_eq!(movetext(START_FEN, &history, "*"), "1. e4 e5 2. Nf3 Nc6 3. Bb5 *");
+        let history = ["Kd7", "Ra7+", "Kc6"].map(String::from);
+        assert_eq!(movetext("4k3/8/8/8/8/8/8/R3K3 b - - 3 40", &history, "1/2-1/2"), "40... Kd7 41. Ra7+ Kc6 1/2-1/2");
+    }
+
+    #[test]
+    fn movetext_wraps_lines() {
+        let history = vec!["Nf3".to_string(); 60];
+        let text = movetext(START_FEN, &history, "1/2-1/2");
+        assert!(text.lines().count() > 1);
+        assert!(text.lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+        assert!(text.ends_with("30. Nf3 Nf3 1/2-1/2"));
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(pgn_date(UNIX_EPOCH), "1970.01.01");
+        assert_eq!(pgn_date(UNIX_EPOCH + Duration::from_secs(951_782_400)), "2000.02.29");
+        assert_eq!(pgn_date(UNIX_EPOCH + Duration::from_secs(1_776_470_400)), "2026.04.18");
+    }
+
+    #[test]
+    fn parses_tags_and_movetext() {
+        let text = r#"[Event "Casual \"blitz\" game"]
+[Site "?"]
+[Result "1-0"]
+
+% an escaped line
+{Opening comment} 1. e4 e5 2.Nf3 $1 Nc6 {knight out} 3. Bb5!? (3. Bc4 Bc5 (3... Nf6) 4. c3) 3... a6 ; rest of line
+4. Ba4 1-0
+
+[Event "Second"]
+1. d4 d5 *
+"#;
+        let games = parse_pgn(text).unwrap();
+        assert_eq!(games.len(), 2);
+        let game = &games[0];
+        assert_eq!(game.tag("Event"), Some("Casual \"blitz\" game"));
+        assert_eq!(game.result.as_deref(), Some("1-0"));
+        assert_eq!(game.comments, vec!["Opening comment"]);
+        let sans = game.moves.iter().map(|mov| mov.san.as_str()).collect::<Vec<&str>>();
+        assert_eq!(sans, vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4"]);
+        assert_eq!(game.moves[2].nags, vec![1]);
+        assert_eq!(game.moves[3].comments, vec!["knight out"]);
+        assert_eq!(game.moves[4].nags, vec![5]);
+        let variation = &game.moves[4].variations[0];
+        assert_eq!(variation.iter().map(|mov| mov.san.as_str()).collect::<Vec<&str>>(), vec!["Bc4", "Bc5", "c3"]);
+        assert_eq!(variation[1].variations[0][0].san, "Nf6");
+        assert_eq!(game.moves[5].comments, vec!["rest of line"]);
+        assert_eq!(games[1].tag("Event"), Some("Second"));
+        assert_eq!(games[1].moves.len(), 2);
+        assert_eq!(games[1].result.as_deref(), Some("*"));
+    }
+
+    #[test]
+    fn rejects_malformed_pgn() {
+        assert!(matches!(parse_pgn("1. e4 {unterminated"), Err(PgnError::Syntax(1, _))));
+        assert!(matches!(parse_pgn("1. e4\n(1. d4"), Err(PgnError::Syntax(2, _))));
+        assert!(matches!(parse_pgn("1. e4 )"), Err(PgnError::Syntax(1, _))));
+        assert!(matches!(parse_pgn("[Event]"), Err(PgnError::Syntax(1, _))));
+    }
+
+    #[test]
+    fn replays_games() {
+        let games = parse_pgn("1. f3 e5 2. g4 Qh4# 0-1").unwrap();
+        let mut game = games[0].replay().unwrap();
+        assert!(game.checkmate());
+        assert_eq!(game.history(), &vec!["f3", "e5", "g4", "Qh4#"]);
+
+        let games = parse_pgn("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 w Q - 0 1\"]\n1. O-O-O Kf7 2. Kb1 *").unwrap();
+        let mut game = games[0].replay().unwrap();
+        assert_eq!(game.to_fen(), "8/5k2/8/8/8/8/8/1K1R4 b - - 3 2");
+
+        let games = parse_pgn("1. e4 e5 2. Ke3 *").unwrap();
+        assert!(matches!(games[0].replay(), Err(PgnError::IllegalMove(3, SanError::Illegal(_)))));
+    }
+
+    #[test]
+    fn writes_finished_game() {
+        let mut game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert!(game.apply_move((0, 7), (0, 0), None));
+        assert!(game.checkmate());
+        let pgn = write_pgn(&game, "Test", 1, "Player 1", "Player 2");
+        assert!(pgn.contains("[Result \"1-0\"]\n"));
+        assert!(pgn.contains("[FEN \"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\"]\n"));
+        assert!(pgn.ends_with("\n\n1. Ra8# 1-0\n"));
+        assert_eq!(result_token(Some(Outcome::Draw(Draw::Stalemate))), "1/2-1/2");
+        assert_eq!(result_token(None), "*");
+    }
+}
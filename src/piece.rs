@@ -1,13 +1,14 @@
 use std::{
     any::{Any, TypeId},
-    fmt::{Display, Debug},
+    fmt::{Display, Debug, Formatter},
 };
 
-use crate::game::Game;
+use crate::game::{format_coord, Game};
 use crate::player::Player;
+use crate::position::PieceType;
 
 pub trait Piece: Display + DynClone + Send + Sync + Debug {
-    fn valid_move(&self, from: (u8, u8), to: (u8, u8), game: &mut Game) -> Move;
+    fn valid_move(&self, from: (u8, u8), to: (u8, u8), game: &mut Game) -> MoveKind;
     fn get_legal_moves(&self, position: (u8, u8), game: &mut Game) -> Vec<(u8, u8)>;
     fn player(&self) -> Player;
     fn can_intercept_path(
@@ -74,8 +75,10 @@ impl dyn Piece {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Move {
+// what a piece moving from one square to another does, a double pawn push carries the square the pawn moved to
+// and en passant carries the square of the pawn it takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveKind {
     Normal,
     Double((u8, u8)),
     Castle,
@@ -83,8 +86,39 @@ pub enum Move {
     Invalid
 }
 
-impl Move {
+impl MoveKind {
     pub fn is_valid(self) -> bool {
-        self != Move::Invalid
+        self != MoveKind::Invalid
+    }
+}
+
+// a move from one square to another, with the piece a pawn promotes to when it reaches the last rank
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub from: (u8, u8),
+    pub to: (u8, u8),
+    pub promotion: Option<PieceType>,
+    pub kind: MoveKind,
+}
+
+impl Move {
+    pub fn new(from: (u8, u8), to: (u8, u8), promotion: Option<PieceType>, kind: MoveKind) -> Self {
+        Self { from, to, promotion, kind }
+    }
+
+    // the promotion as the letter apply_move takes
+    pub fn promotion_letter(&self) -> Option<char> {
+        self.promotion.map(PieceType::letter)
+    }
+}
+
+// long algebraic notation, as uci uses
+impl Display for Move {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}{}", format_coord(&self.from), format_coord(&self.to))?;
+        if let Some(promotion) = self.promotion {
+            write!(f, "{}", promotion.letter())?;
+        }
+        Ok(())
     }
 }
\ No newline at end of file
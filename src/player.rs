@@ -14,16 +14,24 @@ impl Player {
         }
     }
 
-    pub(crate) fn other(&self) -> Player {
+    pub fn other(&self) -> Player {
         match self {
             Player::One => Player::Two,
             Player::Two => Player::One,
         }
     }
 
-    pub(crate) fn is_maximizing(&self) -> bool {
+    pub fn is_maximizing(&self) -> bool {
         matches!(self, Player::One)
     }
+
+    // for indexing arrays kept for each player
+    pub(crate) fn index(&self) -> usize {
+        match self {
+            Player::One => 0,
+            Player::Two => 1,
+        }
+    }
 }
 
 impl Display for Player {
@@ -0,0 +1,703 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{
+    bishop::Bishop,
+    bitboard::{
+        bishop_attacks, bit, coords, rook_attacks, square, squares, Bitboard, KING_ATTACKS, KNIGHT_ATTACKS, LIGHT_SQUARES,
+        PAWN_ATTACKS,
+    },
+    game::{format_coord, Board, Matrix},
+    king::King,
+    knight::Knight,
+    pawn::Pawn,
+    piece::{self, Construct, MoveKind, Piece},
+    player::Player,
+    queen::Queen,
+    rook::Rook,
+    zobrist,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PieceType {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+const PROMOTIONS: [PieceType; 4] = [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight];
+
+impl PieceType {
+    pub fn of(piece: &dyn Piece) -> Self {
+        match piece.name() {
+            "pawn" => PieceType::Pawn,
+            "knight" => PieceType::Knight,
+            "bishop" => PieceType::Bishop,
+            "rook" => PieceType::Rook,
+            "queen" => PieceType::Queen,
+            "king" => PieceType::King,
+            _ => unreachable!("Invalid piece name!"),
+        }
+    }
+
+    // the piece a pawn can promote to, from its letter
+    pub fn promotion(c: char) -> Option<Self> {
+        match c.to_ascii_lowercase() {
+            'q' => Some(PieceType::Queen),
+            'r' => Some(PieceType::Rook),
+            'b' => Some(PieceType::Bishop),
+            'n' => Some(PieceType::Knight),
+            _ => None,
+        }
+    }
+
+    pub fn letter(self) -> char {
+        match self {
+            PieceType::Pawn => 'p',
+            PieceType::Knight => 'n',
+            PieceType::Bishop => 'b',
+            PieceType::Rook => 'r',
+            PieceType::Queen => 'q',
+            PieceType::King => 'k',
+        }
+    }
+
+    pub fn value(self) -> i32 {
+        match self {
+            PieceType::Pawn => 1,
+            PieceType::Knight => 3,
+            PieceType::Bishop => 3,
+            PieceType::Rook => 5,
+            PieceType::Queen => 9,
+            PieceType::King => 100,
+        }
+    }
+
+    pub fn piece(self, player: Player) -> Box<dyn Piece> {
+        match self {
+            PieceType::Pawn => Box::new(Pawn::new(player)),
+            PieceType::Knight => Box::new(Knight::new(player)),
+            PieceType::Bishop => Box::new(Bishop::new(player)),
+            PieceType::Rook => Box::new(Rook::new(player)),
+            PieceType::Queen => Box::new(Queen::new(player)),
+            PieceType::King => Box::new(King::new(player)),
+        }
+    }
+}
+
+// a move between two squares, castling is the king moving two squares
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Move {
+    pub from: u8,
+    pub to: u8,
+    pub promotion: Option<PieceType>,
+}
+
+impl Move {
+    pub fn new(from: u8, to: u8, promotion: Option<PieceType>) -> Self {
+        Self { from, to, promotion }
+    }
+
+    // the move in board coordinates, with the kind of move it is in the position it is made from
+    pub fn in_position(self, position: &Position) -> piece::Move {
+        let kind = match position.piece_at(self.from) {
+            Some((_, PieceType::Pawn)) if self.from.abs_diff(self.to) == 16 => MoveKind::Double(coords(self.to)),
+            Some((_, PieceType::Pawn)) if Some(self.to) == position.en_passant => MoveKind::EnPassant(coords(en_passant_capture(self))),
+            Some((_, PieceType::King)) if self.from.abs_diff(self.to) == 2 => MoveKind::Castle,
+            _ => MoveKind::Normal,
+        };
+        piece::Move::new(coords(self.from), coords(self.to), self.promotion, kind)
+    }
+}
+
+impl From<piece::Move> for Move {
+    fn from(mov: piece::Move) -> Self {
+        Move::new(square(mov.from), square(mov.to), mov.promotion)
+    }
+}
+
+// long algebraic notation, as uci uses
+impl Display for Move {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}{}", format_coord(&coords(self.from)), format_coord(&coords(self.to)))?;
+        if let Some(promotion) = self.promotion {
+            write!(f, "{}", promotion.letter())?;
+        }
+        Ok(())
+    }
+}
+
+// what a move overwrites, so that it can be unmade
+#[derive(Debug, Clone, Copy)]
+pub struct Undo {
+    captured: Option<PieceType>,
+    castling: u8,
+    en_passant: Option<u8>,
+    half_move_clock: u8,
+    hash: u64,
+}
+
+// a position as bitboards for each player's piece types, with a piece on every square for looking pieces up.
+// it is small enough to copy, and moves are made and unmade in place
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Position {
+    pieces: [[Bitboard; 6]; 2],
+    occupied: [Bitboard; 2],
+    squares: [Option<(Player, PieceType)>; 64],
+    side: Player,
+    // white kingside, white queenside, black kingside and black queenside as bits
+    castling: u8,
+    // the square passed over by a pawn that just moved two squares
+    en_passant: Option<u8>,
+    half_move_clock: u8,
+    full_move_clock: u32,
+    hash: u64,
+}
+
+impl Position {
+    pub(crate) fn from_board(
+        board: &Board,
+        side: Player,
+        castling: u8,
+        en_passant: Option<u8>,
+        half_move_clock: u8,
+        full_move_clock: u32,
+    ) -> Self {
+        let mut position = Self {
+            pieces: [[0; 6]; 2],
+            occupied: [0; 2],
+            squares: [None; 64],
+            side,
+            castling,
+            en_passant,
+            half_move_clock,
+            full_move_clock,
+            hash: 0,
+        };
+        for (y, row) in board.iter().enumerate() {
+            for (x, square) in row.iter().enumerate() {
+                if let Some(piece) = square {
+                    position.put((y * 8 + x) as u8, piece.player(), PieceType::of(piece.as_ref()));
+                }
+            }
+        }
+        position.hash = position.compute_hash();
+        position
+    }
+
+    // a position with just the given pieces, without castling rights or an en passant square
+    pub(crate) fn from_pieces(pieces: &[(u8, Player, PieceType)], side: Player) -> Self {
+        let mut position = Self {
+            pieces: [[0; 6]; 2],
+            occupied: [0; 2],
+            squares: [None; 64],
+            side,
+            castling: 0,
+            en_passant: None,
+            half_move_clock: 0,
+            full_move_clock: 1,
+            hash: 0,
+        };
+        for &(square, player, piece_type) in pieces {
+            position.put(square, player, piece_type);
+        }
+        position.hash = position.compute_hash();
+        position
+    }
+
+    fn compute_hash(&self) -> u64 {
+        let mut hash = zobrist::castling(self.castling) ^ zobrist::en_passant(self.en_passant);
+        if self.side == Player::Two {
+            hash ^= zobrist::side();
+        }
+        for (square, piece) in self.squares.iter().enumerate() {
+            if let Some((player, piece_type)) = piece {
+                hash ^= zobrist::piece(*player, *piece_type, square as u8);
+            }
+        }
+        hash
+    }
+
+    pub fn side(&self) -> Player {
+        self.side
+    }
+
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    pub fn castling(&self) -> u8 {
+        self.castling
+    }
+
+    pub fn en_passant(&self) -> Option<u8> {
+        self.en_passant
+    }
+
+    pub fn half_move_clock(&self) -> u8 {
+        self.half_move_clock
+    }
+
+    pub fn full_move_clock(&self) -> u32 {
+        self.full_move_clock
+    }
+
+    pub fn piece_at(&self, square: u8) -> Option<(Player, PieceType)> {
+        self.squares[square as usize]
+    }
+
+    pub fn pieces(&self, player: Player, piece_type: PieceType) -> Bitboard {
+        self.pieces[player.index()][piece_type as usize]
+    }
+
+    pub fn occupied_by(&self, player: Player) -> Bitboard {
+        self.occupied[player.index()]
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.occupied[0] | self.occupied[1]
+    }
+
+    pub fn king(&self, player: Player) -> u8 {
+        self.pieces(player, PieceType::King).trailing_zeros() as u8
+    }
+
+    pub(crate) fn set_side(&mut self, side: Player) {
+        if self.side != side {
+            self.hash ^= zobrist::side();
+        }
+        self.side = side;
+    }
+
+    pub(crate) fn set_en_passant(&mut self, en_passant: Option<u8>) {
+        self.hash ^= zobrist::en_passant(self.en_passant) ^ zobrist::en_passant(en_passant);
+        self.en_passant = en_passant;
+    }
+
+    fn put(&mut self, square: u8, player: Player, piece_type: PieceType) {
+        self.pieces[player.index()][piece_type as usize] |= bit(square);
+        self.occupied[player.index()] |= bit(square);
+        self.squares[square as usize] = Some((player, piece_type));
+        self.hash ^= zobrist::piece(player, piece_type, square);
+    }
+
+    fn remove(&mut self, square: u8) -> Option<(Player, PieceType)> {
+        let (player, piece_type) = self.squares[square as usize]?;
+        self.pieces[player.index()][piece_type as usize] &= !bit(square);
+        self.occupied[player.index()] &= !bit(square);
+        self.squares[square as usize] = None;
+        self.hash ^= zobrist::piece(player, piece_type, square);
+        Some((player, piece_type))
+    }
+
+    pub fn is_attacked(&self, square: u8, by: Player) -> bool {
+        let occupied = self.occupied();
+        let queens = self.pieces(by, PieceType::Queen);
+        PAWN_ATTACKS[by.other().index()][square as usize] & self.pieces(by, PieceType::Pawn) != 0
+            || KNIGHT_ATTACKS[square as usize] & self.pieces(by, PieceType::Knight) != 0
+            || KING_ATTACKS[square as usize] & self.pieces(by, PieceType::King) != 0
+            || bishop_attacks(square, occupied) & (self.pieces(by, PieceType::Bishop) | queens) != 0
+            || rook_attacks(square, occupied) & (self.pieces(by, PieceType::Rook) | queens) != 0
+    }
+
+    pub fn in_check(&self, player: Player) -> bool {
+        self.pieces(player, PieceType::King) != 0 && self.is_attacked(self.king(player), player.other())
+    }
+
+    pub fn legal_moves(&mut self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(64);
+        self.pseudo_legal_moves(&mut moves);
+        self.retain_legal(&mut moves);
+        moves
+    }
+
+    // the legal captures and promotions, the moves that change the material on the board
+    pub fn tactical_moves(&mut self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(64);
+        self.pseudo_legal_moves(&mut moves);
+        moves.retain(|&mov| self.is_tactical(mov));
+        self.retain_legal(&mut moves);
+        moves
+    }
+
+    fn retain_legal(&mut self, moves: &mut Vec<Move>) {
+        let player = self.side;
+        moves.retain(|&mov| {
+            let undo = self.make_move(mov);
+            let legal = !self.in_check(player);
+            self.unmake_move(mov, undo);
+            legal
+        });
+    }
+
+    // the piece a move takes, which for en passant isn't on the square it moves to
+    pub fn captured(&self, mov: Move) -> Option<PieceType> {
+        if let Some((_, piece_type)) = self.piece_at(mov.to) {
+            return Some(piece_type);
+        }
+        let is_pawn = self.piece_at(mov.from).is_some_and(|(_, piece_type)| piece_type == PieceType::Pawn);
+        (is_pawn && Some(mov.to) == self.en_passant).then_some(PieceType::Pawn)
+    }
+
+    pub fn is_tactical(&self, mov: Move) -> bool {
+        mov.promotion.is_some() || self.captured(mov).is_some()
+    }
+
+    pub fn has_legal_move(&mut self) -> bool {
+        let mut moves = Vec::with_capacity(64);
+        self.pseudo_legal_moves(&mut moves);
+        let player = self.side;
+        moves.into_iter().any(|mov| {
+            let undo = self.make_move(mov);
+            let legal = !self.in_check(player);
+            self.unmake_move(mov, undo);
+            legal
+        })
+    }
+
+    // moves that follow the piece rules but may leave the king in check
+    fn pseudo_legal_moves(&self, moves: &mut Vec<Move>) {
+        let us = self.side;
+        let own = self.occupied_by(us);
+        let enemy = self.occupied_by(us.other());
+        let occupied = own | enemy;
+        let (forward, start_rank, last_rank): (i8, u8, u8) = match us {
+            Player::One => (-8, 6, 0),
+            Player::Two => (8, 1, 7),
+        };
+
+        let en_passant = self.en_passant.map_or(0, bit);
+        for from in squares(self.pieces(us, PieceType::Pawn)) {
+            let one = (from as i8 + forward) as u8;
+            if occupied & bit(one) == 0 {
+                push_pawn_move(moves, from, one, last_rank);
+                let two = (one as i8 + forward) as u8;
+                if from / 8 == start_rank && occupied & bit(two) == 0 {
+                    moves.push(Move::new(from, two, None));
+                }
+            }
+            for to in squares(PAWN_ATTACKS[us.index()][from as usize] & (enemy | en_passant)) {
+                push_pawn_move(moves, from, to, last_rank);
+            }
+        }
+        for from in squares(self.pieces(us, PieceType::Knight)) {
+            push_moves(moves, from, KNIGHT_ATTACKS[from as usize] & !own);
+        }
+        for from in squares(self.pieces(us, PieceType::Bishop) | self.pieces(us, PieceType::Queen)) {
+            push_moves(moves, from, bishop_attacks(from, occupied) & !own);
+        }
+        for from in squares(self.pieces(us, PieceType::Rook) | self.pieces(us, PieceType::Queen)) {
+            push_moves(moves, from, rook_attacks(from, occupied) & !own);
+        }
+        for from in squares(self.pieces(us, PieceType::King)) {
+            push_moves(moves, from, KING_ATTACKS[from as usize] & !own);
+        }
+        self.castling_moves(moves, occupied);
+    }
+
+    // the king can't castle out of, through or into check, the squares between it and the rook must be empty
+    fn castling_moves(&self, moves: &mut Vec<Move>, occupied: Bitboard) {
+        let us = self.side;
+        let (king, kingside, queenside) = match us {
+            Player::One => (60, 0b0001, 0b0010),
+            Player::Two => (4, 0b0100, 0b1000),
+        };
+        if self.squares[king as usize] != Some((us, PieceType::King)) || self.is_attacked(king, us.other()) {
+            return;
+        }
+        let rook = |square: u8| self.squares[square as usize] == Some((us, PieceType::Rook));
+        let safe = |squares: &[u8]| squares.iter().all(|&square| !self.is_attacked(square, us.other()));
+        if self.castling & kingside != 0
+            && rook(king + 3)
+            && occupied & (bit(king + 1) | bit(king + 2)) == 0
+            && safe(&[king + 1, king + 2])
+        {
+            moves.push(Move::new(king, king + 2, None));
+        }
+        if self.castling & queenside != 0
+            && rook(king - 4)
+            && occupied & (bit(king - 1) | bit(king - 2) | bit(king - 3)) == 0
+            && safe(&[king - 1, king - 2])
+        {
+            moves.push(Move::new(king, king - 2, None));
+        }
+    }
+
+    pub fn make_move(&mut self, mov: Move) -> Undo {
+        let undo = Undo {
+            captured: None,
+            castling: self.castling,
+            en_passant: self.en_passant,
+            half_move_clock: self.half_move_clock,
+            hash: self.hash,
+        };
+        let (player, piece_type) = self.remove(mov.from).expect("No piece to move!");
+        let mut captured = self.remove(mov.to);
+        if piece_type == PieceType::Pawn && Some(mov.to) == self.en_passant {
+            captured = self.remove(en_passant_capture(mov));
+        }
+        self.put(mov.to, player, mov.promotion.unwrap_or(piece_type));
+        if piece_type == PieceType::King && mov.from.abs_diff(mov.to) == 2 {
+            let (rook_from, rook_to) = castling_rook(mov);
+            self.remove(rook_from);
+            self.put(rook_to, player, PieceType::Rook);
+        }
+
+        self.hash ^= zobrist::castling(self.castling) ^ zobrist::en_passant(self.en_passant);
+        self.castling &= !(castling_mask(mov.from) | castling_mask(mov.to));
+        self.en_passant = if piece_type == PieceType::Pawn && mov.from.abs_diff(mov.to) == 16 {
+            Some((mov.from + mov.to) / 2)
+        } else {
+            None
+        };
+        self.hash ^= zobrist::castling(self.castling) ^ zobrist::en_passant(self.en_passant);
+
+        if piece_type == PieceType::Pawn || captured.is_some() {
+            self.half_move_clock = 0;
+        } else {
+            self.half_move_clock += 1;
+        }
+        if player == Player::Two {
+            self.full_move_clock += 1;
+        }
+        self.side = player.other();
+        self.hash ^= zobrist::side();
+        Undo { captured: captured.map(|(_, piece_type)| piece_type), ..undo }
+    }
+
+    pub fn unmake_move(&mut self, mov: Move, undo: Undo) {
+        let player = self.side.other();
+        let (_, moved) = self.remove(mov.to).expect("No piece to move back!");
+        let piece_type = if mov.promotion.is_some() { PieceType::Pawn } else { moved };
+        self.put(mov.from, player, piece_type);
+        if let Some(captured) = undo.captured {
+            let square = if piece_type == PieceType::Pawn && Some(mov.to) == undo.en_passant {
+                en_passant_capture(mov)
+            } else {
+                mov.to
+            };
+            self.put(square, player.other(), captured);
+        }
+        if piece_type == PieceType::King && mov.from.abs_diff(mov.to) == 2 {
+            let (rook_from, rook_to) = castling_rook(mov);
+            self.remove(rook_to);
+            self.put(rook_from, player, PieceType::Rook);
+        }
+        if player == Player::Two {
+            self.full_move_clock -= 1;
+        }
+        self.side = player;
+        self.castling = undo.castling;
+        self.en_passant = undo.en_passant;
+        self.half_move_clock = undo.half_move_clock;
+        self.hash = undo.hash;
+    }
+
+    // passes the turn to the other player without moving, for null-move pruning
+    pub fn make_null_move(&mut self) -> Undo {
+        let undo = Undo {
+            captured: None,
+            castling: self.castling,
+            en_passant: self.en_passant,
+            half_move_clock: self.half_move_clock,
+            hash: self.hash,
+        };
+        self.set_en_passant(None);
+        self.half_move_clock += 1;
+        self.side = self.side.other();
+        self.hash ^= zobrist::side();
+        undo
+    }
+
+    pub fn unmake_null_move(&mut self, undo: Undo) {
+        self.side = self.side.other();
+        self.en_passant = undo.en_passant;
+        self.half_move_clock = undo.half_move_clock;
+        self.hash = undo.hash;
+    }
+
+    // whether the player has anything besides pawns and their king, without which passing could be better than any move
+    pub fn has_non_pawn_material(&self, player: Player) -> bool {
+        self.occupied_by(player) & !(self.pieces(player, PieceType::Pawn) | self.pieces(player, PieceType::King)) != 0
+    }
+
+    // whether moving a piece leaves its king attacked, whether or not the move is otherwise valid
+    pub(crate) fn exposes_king(&self, from: u8, to: u8) -> bool {
+        let mut position = *self;
+        let (player, piece_type) = position.remove(from).expect("No piece to move!");
+        position.remove(to);
+        if piece_type == PieceType::Pawn && Some(to) == self.en_passant && from % 8 != to % 8 {
+            position.remove(en_passant_capture(Move::new(from, to, None)));
+        }
+        position.put(to, player, piece_type);
+        position.in_check(player)
+    }
+
+    // neither side can checkmate with a lone king, a single minor piece, or bishops all on one color
+    pub fn insufficient_material(&self) -> bool {
+        let (one, two) = (Player::One, Player::Two);
+        let heavy = [PieceType::Pawn, PieceType::Rook, PieceType::Queen]
+            .iter()
+            .fold(0, |heavy, &piece_type| heavy | self.pieces(one, piece_type) | self.pieces(two, piece_type));
+        if heavy != 0 {
+            return false;
+        }
+        let knights = self.pieces(one, PieceType::Knight) | self.pieces(two, PieceType::Knight);
+        let bishops = self.pieces(one, PieceType::Bishop) | self.pieces(two, PieceType::Bishop);
+        let bishops_on_one_color = knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0);
+        (knights | bishops).count_ones() <= 1 || bishops_on_one_color
+    }
+
+    // whether any series of moves could let the player checkmate, which a lone minor piece can only do with the other
+    // king's own pieces hemming it in, and bishops can't do on a single color
+    pub fn has_mating_material(&self, player: Player) -> bool {
+        if [PieceType::Pawn, PieceType::Rook, PieceType::Queen].iter().any(|&piece_type| self.pieces(player, piece_type) != 0) {
+            return true;
+        }
+        let knights = self.pieces(player, PieceType::Knight);
+        let bishops = self.pieces(player, PieceType::Bishop);
+        let lone_king = self.occupied_by(player.other()).count_ones() == 1;
+        match (knights | bishops).count_ones() {
+            0 => false,
+            1 => !lone_king,
+            _ => !lone_king || knights != 0 || (bishops & LIGHT_SQUARES != 0 && bishops & !LIGHT_SQUARES != 0),
+        }
+    }
+
+    // player one's piece values minus player two's
+    pub fn material(&self) -> i32 {
+        [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King]
+            .iter()
+            .map(|&piece_type| {
+                let count = self.pieces(Player::One, piece_type).count_ones() as i32
+                    - self.pieces(Player::Two, piece_type).count_ones() as i32;
+                count * piece_type.value()
+            })
+            .sum()
+    }
+
+    // the evaluation model's input, a plane for each player's piece types and one for the rest of the state
+    pub fn to_matrix(&self) -> Matrix {
+        let mut data = [[[0.0; 8]; 8]; 13];
+        for (square, piece) in self.squares.iter().enumerate() {
+            if let Some((player, piece_type)) = piece {
+                let plane = match piece_type {
+                    PieceType::Queen => 0,
+                    PieceType::King => 2,
+                    PieceType::Rook => 4,
+                    PieceType::Bishop => 6,
+                    PieceType::Knight => 8,
+                    PieceType::Pawn => 10,
+                };
+                data[plane + player.index()][square % 8][square / 8] = 1.0;
+            }
+        }
+        if let Some(target) = self.en_passant {
+            // the pawn that moved two squares, beyond the square it passed over
+            let (x, y) = coords(target);
+            let y = if y == 2 { 3 } else { 4 };
+            data[12][x as usize][y] = 1.0;
+        }
+        if self.castling & 0b0011 == 0 {
+            data[12][7][0] = 1.0;
+            data[12][7][7] = 1.0;
+        }
+        if self.castling & 0b1100 == 0 {
+            data[12][0][0] = 1.0;
+            data[12][0][7] = 1.0;
+        }
+        if self.side == Player::One {
+            data[12][7][4] = 1.0;
+        } else {
+            data[12][0][4] = 1.0;
+        }
+        for c in 0..8 {
+            data[12][3][7 - c] = ((self.half_move_clock >> c) & 1) as f32;
+            data[12][4][7 - c] = ((self.full_move_clock >> c) & 1) as f32;
+        }
+        data
+    }
+}
+
+fn push_moves(moves: &mut Vec<Move>, from: u8, targets: Bitboard) {
+    moves.extend(squares(targets).map(|to| Move::new(from, to, None)));
+}
+
+fn push_pawn_move(moves: &mut Vec<Move>, from: u8, to: u8, last_rank: u8) {
+    if to / 8 == last_rank {
+        moves.extend(PROMOTIONS.iter().map(|&promotion| Move::new(from, to, Some(promotion))));
+    } else {
+        moves.push(Move::new(from, to, None));
+    }
+}
+
+// the pawn taken en passant is beside the capturing pawn, on the square the capture moves past
+fn en_passant_capture(mov: Move) -> u8 {
+    mov.from / 8 * 8 + mov.to % 8
+}
+
+fn castling_rook(mov: Move) -> (u8, u8) {
+    if mov.to > mov.from {
+        (mov.from + 3, mov.from + 1)
+    } else {
+        (mov.from - 4, mov.from - 1)
+    }
+}
+
+// the castling rights lost when a piece moves from or to a square
+fn castling_mask(square: u8) -> u8 {
+    match square {
+        60 => 0b0011,
+        63 => 0b0001,
+        56 => 0b0010,
+        4 => 0b1100,
+        7 => 0b0100,
+        0 => 0b1000,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Move, MoveKind};
+    use crate::game::Game;
+
+    #[test]
+    fn unmake_restores_the_position() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut position = *Game::from_fen(fen).unwrap().position();
+        let start = position;
+        for mov in position.legal_moves() {
+            let undo = position.make_move(mov);
+            for reply in position.legal_moves() {
+                let before = position;
+                let undo = position.make_move(reply);
+                position.unmake_move(reply, undo);
+                assert_eq!(position, before);
+            }
+            position.unmake_move(mov, undo);
+            assert_eq!(position, start);
+        }
+
+        // passing clears the en passant square and keeps the hash in step
+        let mut position = *Game::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap().position();
+        let start = position;
+        let undo = position.make_null_move();
+        assert_eq!(position.en_passant(), None);
+        assert_eq!(position.hash(), position.compute_hash());
+        position.unmake_null_move(undo);
+        assert_eq!(position, start);
+    }
+
+    #[test]
+    fn moves_know_their_kind() {
+        let position = *Game::from_fen("r3k3/8/8/3pP3/8/8/1P6/4K2R w Kq d6 0 1").unwrap().position();
+        let kind = |from, to| Move::new(from, to, None).in_position(&position).kind;
+        assert_eq!(kind(49, 33), MoveKind::Double((1, 4)));
+        assert_eq!(kind(28, 19), MoveKind::EnPassant((3, 3)));
+        assert_eq!(kind(60, 62), MoveKind::Castle);
+        assert_eq!(kind(63, 55), MoveKind::Normal);
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+};
+
+use crate::{
+    game::{format_coord, parse_coord, Game},
+    king::King,
+    pawn::Pawn,
+    piece::Move,
+    player::Player,
+    position::PieceType,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SanError {
+    Invalid(String),
+    Illegal(String),
+    Ambiguous(String),
+}
+
+impl Display for SanError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            SanError::Invalid(san) => write!(f, "'{san}' is not a valid move"),
+            SanError::Illegal(san) => write!(f, "'{san}' is not a legal move"),
+            SanError::Ambiguous(san) => write!(f, "'{san}' could be more than one move"),
+        }
+    }
+}
+
+impl Error for SanError {}
+
+// encodes a legal move in standard algebraic notation, without the check or checkmate suffix
+pub fn to_san(game: &mut Game, from: (u8, u8), to: (u8, u8), promotion: Option<char>) -> String {
+    let piece = game.get(from).expect("No piece to move!");
+    if piece.is_type::<King>() && (from.0 as i8 - to.0 as i8).abs() == 2 {
+        return if to.0 == 6 { "O-O".to_string() } else { "O-O-O".to_string() };
+    }
+    let capture = game.get(to).is_some() || (piece.is_type::<Pawn>() && from.0 != to.0);
+    let mut san = String::new();
+    if piece.is_type::<Pawn>() {
+        if capture {
+            san.push(format_coord(&from).chars().next().unwrap());
+        }
+    } else {
+        san.push(piece_letter(piece.name()));
+        san.push_str(&disambiguation(game, from, to));
+    }
+    if capture {
+        san.push('x');
+    }
+    san.push_str(&format_coord(&to));
+    if game.is_promotion(from, to) {
+        san.push('=');
+        san.push(promotion.unwrap_or('q').to_ascii_uppercase());
+    }
+    san
+}
+
+// the suffix for the position after a move, '#' for checkmate and '+' for check
+pub fn check_suffix(game: &mut Game) -> &'static str {
+    let player = game.current_player();
+    if !game.in_check(player) {
+        ""
+    } else if game.get_possible_moves(player).is_empty() {
+        "#"
+    } else {
+        "+"
+    }
+}
+
+// a line of moves from the game's position in standard algebraic notation with move numbers, like "12... Nf6 13. e5"
+pub fn line_to_san(game: &Game, line: &[Move]) -> String {
+    let mut game = game.clone();
+    let mut san = Vec::new();
+    for (i, mov) in line.iter().enumerate() {
+        let number = game.position().full_move_clock();
+        if game.current_player() == Player::One {
+            san.push(format!("{number}."));
+        } else if i == 0 {
+            san.push(format!("{number}..."));
+        }
+        if !game.apply_move(mov.from, mov.to, mov.promotion_letter()) {
+            break;
+        }
+        san.push(game.history().last().unwrap().clone());
+    }
+    san.join(" ")
+}
+
+// decodes a move in standard algebraic notation
+pub fn from_san(game: &mut Game, san: &str) -> Result<Move, SanError> {
+    let invalid = || SanError::Invalid(san.to_string());
+    let trimmed = san.trim_end_matches(['+', '#', '!', '?']);
+    let player = game.current_player();
+    let castle = match trimmed {
+        "O-O" | "0-0" => Some(6),
+        "O-O-O" | "0-0-0" => Some(2),
+        _ => None,
+    };
+    if let Some(x) = castle {
+        let king = game.get_king(player);
+        let to = (x, king.1);
+        let is_castle = game.get(king).is_some_and(|piece| piece.get_legal_moves(king, game).contains(&to));
+        return if king.0 == 4 && is_castle { Ok(game.move_between(king, to, None)) } else { Err(SanError::Illegal(san.to_string())) };
+    }
+
+    let mut chars = trimmed.chars().collect::<Vec<char>>();
+    let mut promotion = None;
+    if let Some(&last) = chars.last() {
+        if "QRBNqrbn".contains(last) && chars.len() > 2 && chars[chars.len() - 2] != 'x' {
+            promotion = PieceType::promotion(last);
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+    }
+    if chars.len() < 2 {
+        return Err(invalid());
+    }
+    let to = parse_coord(&chars[chars.len() - 2..].iter().collect::<String>()).ok_or_else(invalid)?;
+    let mut prefix = &chars[..chars.len() - 2];
+    let name = match prefix.first() {
+        Some('N') => "knight",
+        Some('B') => "bishop",
+        Some('R') => "rook",
+        Some('Q') => "queen",
+        Some('K') => "king",
+        _ => "pawn",
+    };
+    if name != "pawn" {
+        prefix = &prefix[1..];
+    }
+    let capture = prefix.last() == Some(&'x');
+    if capture {
+        prefix = &prefix[..prefix.len() - 1];
+    }
+    let (mut file, mut rank) = (None, None);
+    for &c in prefix {
+        match c {
+            'a'..='h' if file.is_none() && rank.is_none() => file = Some(c as u8 - b'a'),
+            '1'..='8' if rank.is_none() => rank = Some(b'8' - c as u8),
+            _ => return Err(invalid()),
+        }
+    }
+    // a pawn captures onto the next file, never its own
+    if name == "pawn" && (capture != file.is_some() || rank.is_some() || file == Some(to.0)) {
+        return Err(invalid());
+    }
+
+    let mut candidates = Vec::new();
+    for from in game.get_pieces(player) {
+        let piece = game.get(from).unwrap();
+        if piece.name() != name || file.is_some_and(|x| x != from.0) || rank.is_some_and(|y| y != from.1) {
+            continue;
+        }
+        if name == "pawn" && !capture && from.0 != to.0 {
+            continue;
+        }
+        if piece.get_legal_moves(from, game).contains(&to) {
+            candidates.push(from);
+        }
+    }
+    match candidates[..] {
+        [] => Err(SanError::Illegal(san.to_string())),
+        [from] => {
+            if game.is_promotion(from, to) != promotion.is_some() {
+                return Err(SanError::Illegal(san.to_string()));
+            }
+            Ok(game.move_between(from, to, promotion))
+        }
+        _ => Err(SanError::Ambiguous(san.to_string())),
+    }
+}
+
+pub(crate) fn piece_letter(name: &str) -> char {
+    match name {
+        "knight" => 'N',
+        "bishop" => 'B',
+        "rook" => 'R',
+        "queen" => 'Q',
+        "king" => 'K',
+        _ => 'P',
+    }
+}
+
+// the file, rank or square of the moving piece needed to tell it apart from identical pieces
+fn disambiguation(game: &mut Game, from: (u8, u8), to: (u8, u8)) -> String {
+    let piece = game.get(from).unwrap();
+    let rivals = game
+        .get_possible_moves(piece.player())
+        .into_iter()
+        .filter(|mov| mov.to == to && mov.from != from && game.get(mov.from).unwrap().name() == piece.name())
+        .map(|mov| mov.from)
+        .collect::<Vec<(u8, u8)>>();
+    let square = format_coord(&from);
+    if rivals.is_empty() {
+        String::new()
+    } else if rivals.iter().all(|rival| rival.0 != from.0) {
+        square[0..1].to_string()
+    } else if rivals.iter().all(|rival| rival.1 != from.1) {
+        square[1..2].to_string()
+    } else {
+        square
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn san(fen: &str, from: &str, to: &str, promotion: Option<char>) -> String {
+        let mut game = Game::from_fen(fen).unwrap();
+        let (from, to) = (crate::game::parse_coord(from).unwrap(), crate::game::parse_coord(to).unwrap());
+        let mut san = to_san(&mut game, from, to, promotion);
+        assert!(game.apply_move(from, to, promotion));
+        san.push_str(check_suffix(&mut game));
+        san
+    }
+
+    #[test]
+    fn encodes_moves() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(san(start, "e2", "e4", None), "e4");
+        assert_eq!(san(start, "g1", "f3", None), "Nf3");
+        assert_eq!(san("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", "e4", "d5", None), "exd5");
+        assert_eq!(san("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", "e5", "f6", None), "exf6");
+        assert_eq!(san("r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1", "e1", "g1", None), "O-O");
+        assert_eq!(san("r3k3/8/8/8/8/8/8/3K4 b q - 0 1", "e8", "c8", None), "O-O-O+");
+        assert_eq!(san("3qk3/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7", "d8", Some('n')), "exd8=N");
+        assert_eq!(san("4k3/4P3/8/8/8/8/8/4K3 b - - 0 1", "e8", "e7", None), "Kxe7");
+    }
+
+    #[test]
+    fn encodes_lines() {
+        let game = Game::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+        let mut replay = game.clone();
+        let line = ["e7e5", "g1f3", "b8c6"].map(|uci| {
+            let mov = replay.get_possible_moves(replay.current_player()).into_iter().find(|mov| mov.to_string() == uci).unwrap();
+            replay.apply_move(mov.from, mov.to, None);
+            mov
+        });
+        assert_eq!(line_to_san(&game, &line), "1... e5 2. Nf3 Nc6");
+        assert_eq!(line_to_san(&game, &[]), "");
+    }
+
+    fn decode(fen: &str, san: &str) -> Result<String, SanError> {
+        let mut game = Game::from_fen(fen).unwrap();
+        from_san(&mut game, san).map(|mov| mov.to_string())
+    }
+
+    #[test]
+    fn decodes_moves() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(decode(start, "e4"), Ok("e2e4".to_string()));
+        assert_eq!(decode(start, "Nf3"), Ok("g1f3".to_string()));
+        assert_eq!(decode(start, "Ngf3!?"), Ok("g1f3".to_string()));
+        assert_eq!(decode("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", "exd5"), Ok("e4d5".to_string()));
+        assert_eq!(decode("r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1", "O-O"), Ok("e1g1".to_string()));
+        assert_eq!(decode("r3k3/8/8/8/8/8/8/3K4 b q - 0 1", "0-0-0+"), Ok("e8c8".to_string()));
+        assert_eq!(decode("3qk3/4P3/8/8/8/8/8/4K3 w - - 0 1", "exd8=N"), Ok("e7d8n".to_string()));
+        assert_eq!(decode("3qk3/4P3/8/8/8/8/8/4K3 w - - 0 1", "exd8Q"), Ok("e7d8q".to_string()));
+        assert_eq!(decode("6k1/5ppp/8/8/8/8/8/R2RK3 w - - 0 1", "Rac1"), Ok("a1c1".to_string()));
+        assert_eq!(decode("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "R5a3"), Ok("a5a3".to_string()));
+        assert_eq!(decode("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "Qa1b2"), Ok("a1b2".to_string()));
+    }
+
+    #[test]
+    fn rejects_bad_moves() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(decode(start, "e5"), Err(SanError::Illegal("e5".to_string())));
+        assert_eq!(decode(start, "O-O"), Err(SanError::Illegal("O-O".to_string())));
+        assert_eq!(decode(start, "Zf3"), Err(SanError::Invalid("Zf3".to_string())));
+        assert_eq!(decode(start, "e"), Err(SanError::Invalid("e".to_string())));
+        assert_eq!(decode("6k1/5ppp/8/8/8/8/8/R2RK3 w - - 0 1", "Rc1"), Err(SanError::Ambiguous("Rc1".to_string())));
+        assert_eq!(decode("3qk3/4P3/8/8/8/8/8/4K3 w - - 0 1", "exd8"), Err(SanError::Illegal("exd8".to_string())));
+        assert_eq!(decode("4k3/8/8/8/4P3/8/8/4K3 w - - 0 1", "exe5"), Err(SanError::Invalid("exe5".to_string())));
+    }
+
+    #[test]
+    fn disambiguates_and_marks_mate() {
+        assert_eq!(san("6k1/5ppp/8/8/8/8/8/R2RK3 w - - 0 1", "a1", "c1", None), "Rac1");
+        assert_eq!(san("6k1/5ppp/8/8/8/8/8/R2RK3 w - - 0 1", "d1", "d8", None), "Rd8#");
+        assert_eq!(san("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a5", "a3", None), "R5a3");
+        assert_eq!(san("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "a1", "b2", None), "Qa1b2");
+        assert_eq!(san("3r2k1/5ppp/8/8/8/8/5PPP/r2R2K1 b - - 0 1", "a1", "d1", None), "Raxd1#");
+        assert_eq!(san("k7/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7", "e8", Some('n')), "e8=N");
+        assert_eq!(san("7k/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7", "e8", Some('r')), "e8=R+");
+    }
+}
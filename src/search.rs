@@ -0,0 +1,794 @@
+use std::{
+    cmp::min,
+    fmt::{Display, Error, Formatter},
+    ops::AddAssign,
+    sync::{Mutex, atomic::{AtomicBool, Ordering}},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    game::{HALF_MOVE_LIMIT, MAX_SEARCH_DEPTH},
+    evaluator::Evaluator,
+    piece,
+    position::{Move, PieceType, Position, Undo},
+    syzygy::{Tablebase, Wdl},
+    tt::{Bound, Entry, TranspositionTable},
+};
+
+// how many pawns a capture may gain beyond the piece it takes and still be searched, for positional swings
+const DELTA_MARGIN: i32 = 2;
+// move ordering puts the best move from the transposition table first, then captures, promotions, killers and the rest by history
+const TT_MOVE_SCORE: i32 = 1 << 30;
+const CAPTURE_SCORE: i32 = 1 << 29;
+const PROMOTION_SCORE: i32 = 1 << 28;
+const KILLER_SCORE: i32 = 1 << 27;
+// how many plies less a null move is searched to than the moves it stands in for
+const NULL_MOVE_REDUCTION: u8 = 2;
+// late move reductions only start after this many moves have been searched at full depth, and only this deep
+const LATE_MOVES: usize = 3;
+const LATE_MOVE_MIN_DEPTH: u8 = 3;
+
+// the selective search techniques to use, each of which can be turned off to measure what it gains in engine matches
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchConfig {
+    // order captures, promotions, killers and then the rest by history, rather than only the transposition table move first
+    pub move_ordering: bool,
+    // search the moves after the first with a zero window, only searching again with the full window if one is better
+    pub principal_variation: bool,
+    // let the opponent move twice, and if they still can't get back under beta the position isn't worth searching
+    pub null_move: bool,
+    // search quiet moves late in the ordering less deeply, unless they turn out better than expected
+    pub late_move_reductions: bool,
+    // search a ply deeper after a check, so that checks can't push a threat past the search horizon
+    pub check_extensions: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            move_ordering: true,
+            principal_variation: true,
+            null_move: true,
+            late_move_reductions: true,
+            check_extensions: true,
+        }
+    }
+}
+
+// the score for checkmating in the current position, less a point for every ply the mate is away, so that a
+// shorter mate always scores better. it is far beyond anything any evaluation can give
+const MATE: f32 = 1_000_000.0;
+// scores at least this close to MATE are mates, which no search can be deep enough to blur
+const MATE_THRESHOLD: f32 = MATE - 1000.0;
+
+// a win from the tablebases, in pawns, which is below any checkmate but above anything the evaluation gives
+const TABLEBASE_WIN: i32 = 1000;
+
+// the number of plies until checkmate that a score stands for, if it is a mate score
+pub(crate) fn plies_to_mate(score: f32) -> Option<u32> {
+    (score.abs() >= MATE_THRESHOLD).then(|| (MATE - score.abs()) as u32)
+}
+
+// a tablebase result for the player to move, with wins closer to the next capture or pawn move, or to the root, first
+pub(crate) fn tablebase_score(wdl: Wdl, plies: u32, pawn_scale: f32) -> f32 {
+    let win = TABLEBASE_WIN as f32 * pawn_scale - plies as f32;
+    match wdl {
+        Wdl::Win => win,
+        Wdl::Loss => -win,
+        // the fifty-move rule makes these draws
+        _ => 0.0,
+    }
+}
+
+// mate scores count the plies from the root of the search, but the cache may be read from other depths,
+// so it stores them counting from the position they were found in
+fn score_to_cache(score: f32, ply: usize) -> f32 {
+    if score >= MATE_THRESHOLD {
+        score + ply as f32
+    } else if score <= -MATE_THRESHOLD {
+        score - ply as f32
+    } else {
+        score
+    }
+}
+
+fn score_from_cache(score: f32, ply: usize) -> f32 {
+    if score >= MATE_THRESHOLD {
+        score - ply as f32
+    } else if score <= -MATE_THRESHOLD {
+        score + ply as f32
+    } else {
+        score
+    }
+}
+
+// a search score from the point of view of the player to move
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Score {
+    Centipawns(i32),
+    // mate in the given number of moves, negative when the player to move is the one being mated
+    Mate(i32),
+}
+
+impl Display for Score {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            Score::Centipawns(centipawns) => write!(f, "{:+.2}", centipawns as f32 / 100.0),
+            Score::Mate(moves) if moves > 0 => write!(f, "mate in {moves}"),
+            Score::Mate(moves) => write!(f, "mated in {}", -moves),
+        }
+    }
+}
+
+// what the deepest completed iteration of a search found
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub best_move: piece::Move,
+    pub score: Score,
+    // the line of play the search expects, starting with the best move
+    pub pv: Vec<piece::Move>,
+    pub depth: u8,
+    // positions searched over every depth so far
+    pub nodes: u64,
+    pub elapsed: Duration,
+    pub tt_probes: u64,
+    pub tt_hits: u64,
+}
+
+impl SearchResult {
+    pub fn nps(&self) -> u64 {
+        (self.nodes as f64 / self.elapsed.as_secs_f64().max(0.001)) as u64
+    }
+
+    // the fraction of transposition table lookups that found the position
+    pub fn tt_hit_rate(&self) -> f64 {
+        if self.tt_probes == 0 {
+            0.0
+        } else {
+            self.tt_hits as f64 / self.tt_probes as f64
+        }
+    }
+}
+
+// counts of the work a search did
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct SearchStats {
+    pub(crate) nodes: u64,
+    pub(crate) tt_probes: u64,
+    pub(crate) tt_hits: u64,
+}
+
+impl AddAssign for SearchStats {
+    fn add_assign(&mut self, other: Self) {
+        self.nodes += other.nodes;
+        self.tt_probes += other.tt_probes;
+        self.tt_hits += other.tt_hits;
+    }
+}
+
+// the quiet moves that have caused cutoffs in a search, to try first in the positions searched after
+#[derive(Clone)]
+pub(crate) struct Cutoffs {
+    // the quiet moves that last caused a cutoff at each ply, which often cut off in the positions beside it too
+    killers: Vec<[Option<Move>; 2]>,
+    // how much each quiet move from one square to another has caused cutoffs, weighted towards deeper searches
+    history: Box<[[i32; 64]; 64]>,
+}
+
+impl Default for Cutoffs {
+    fn default() -> Self {
+        Self { killers: Vec::new(), history: Box::new([[0; 64]; 64]) }
+    }
+}
+
+impl Cutoffs {
+    // halves the history, so that the cutoffs of the next, deeper search soon count for more
+    fn age(&mut self) {
+        self.history.iter_mut().flatten().for_each(|count| *count /= 2);
+    }
+}
+
+// the cutoffs of a game's searches, kept across root moves and depths rather than learned again by every search, with
+// one for each of the searches running at once
+#[derive(Default)]
+pub(crate) struct CutoffTables(Mutex<Vec<Cutoffs>>);
+
+impl Clone for CutoffTables {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.0.lock().unwrap().clone()))
+    }
+}
+
+impl CutoffTables {
+    // cutoffs that no other search is using, new ones if they all are
+    pub(crate) fn take(&self) -> Cutoffs {
+        self.0.lock().unwrap().pop().unwrap_or_default()
+    }
+
+    pub(crate) fn put_back(&self, cutoffs: Cutoffs) {
+        self.0.lock().unwrap().push(cutoffs);
+    }
+
+    // called between the depths of iterative deepening
+    pub(crate) fn age(&self) {
+        self.0.lock().unwrap().iter_mut().for_each(Cutoffs::age);
+    }
+}
+
+// minimax with alpha-beta pruning over a position, making and unmaking moves in place
+pub(crate) struct Searcher<'a, E: Evaluator + ?Sized> {
+    evaluator: &'a E,
+    cache: &'a TranspositionTable,
+    tablebase: Option<&'a Tablebase>,
+    stop: &'a AtomicBool,
+    deadline: Option<Instant>,
+    // set once the search is stopped or runs out of time, after which its scores mean nothing
+    stopped: bool,
+    nodes: u64,
+    tt_probes: u64,
+    tt_hits: u64,
+    config: SearchConfig,
+    cutoffs: Cutoffs,
+    // the hash of the position before every move of the game and of the line being searched, for spotting repetitions
+    positions: Vec<u64>,
+    // how many moves deep the search is
+    ply: usize,
+    // set while searching the reply to a null move, so that the opponent doesn't pass straight back
+    after_null_move: bool,
+}
+
+impl<'a, E: Evaluator + ?Sized> Searcher<'a, E> {
+    pub(crate) fn new(
+        evaluator: &'a E,
+        cache: &'a TranspositionTable,
+        tablebase: Option<&'a Tablebase>,
+        stop: &'a AtomicBool,
+        deadline: Option<Instant>,
+        positions: Vec<u64>,
+        config: SearchConfig,
+    ) -> Self {
+        Self {
+            evaluator,
+            cache,
+            tablebase,
+            stop,
+            deadline,
+            stopped: false,
+            nodes: 0,
+            tt_probes: 0,
+            tt_hits: 0,
+            config,
+            cutoffs: Cutoffs::default(),
+            positions,
+            ply: 0,
+            after_null_move: false,
+        }
+    }
+
+    // searches with the cutoffs of earlier searches rather than from nothing
+    pub(crate) fn with_cutoffs(self, cutoffs: Cutoffs) -> Self {
+        Self { cutoffs, ..self }
+    }
+
+    pub(crate) fn into_cutoffs(self) -> Cutoffs {
+        self.cutoffs
+    }
+
+    // the positions searched, counting the ones in the quiescence search, and how often the transposition table had them
+    pub(crate) fn stats(&self) -> SearchStats {
+        SearchStats { nodes: self.nodes, tt_probes: self.tt_probes, tt_hits: self.tt_hits }
+    }
+
+    pub(crate) fn make_move(&mut self, position: &mut Position, mov: Move) -> Undo {
+        self.positions.push(position.hash());
+        self.ply += 1;
+        position.make_move(mov)
+    }
+
+    pub(crate) fn unmake_move(&mut self, position: &mut Position, mov: Move, undo: Undo) {
+        position.unmake_move(mov, undo);
+        self.ply -= 1;
+        self.positions.pop();
+    }
+
+    fn is_killer(&self, mov: Move) -> bool {
+        self.cutoffs.killers.get(self.ply).is_some_and(|killers| killers.contains(&Some(mov)))
+    }
+
+    // sorts the moves so that the ones most likely to be best, and so to cut off the rest, are searched first
+    fn order_moves(&self, position: &Position, moves: &mut [Move], tt_move: Option<Move>) {
+        moves.sort_by_cached_key(|&mov| -self.move_score(position, mov, tt_move));
+    }
+
+    fn move_score(&self, position: &Position, mov: Move, tt_move: Option<Move>) -> i32 {
+        if tt_move == Some(mov) {
+            return TT_MOVE_SCORE;
+        }
+        if !self.config.move_ordering {
+            return 0;
+        }
+        if position.is_tactical(mov) {
+            return tactical_score(position, mov);
+        }
+        match self.cutoffs.killers.get(self.ply) {
+            Some([first, _]) if *first == Some(mov) => KILLER_SCORE + 1,
+            Some([_, second]) if *second == Some(mov) => KILLER_SCORE,
+            _ => self.cutoffs.history[mov.from as usize][mov.to as usize].min(KILLER_SCORE - 1),
+        }
+    }
+
+    // remembers a quiet move that caused a cutoff, as a killer for this ply and in the history table
+    fn record_cutoff(&mut self, position: &Position, mov: Move, depth: u8) {
+        if position.is_tactical(mov) {
+            return;
+        }
+        let Cutoffs { killers, history } = &mut self.cutoffs;
+        if killers.len() <= self.ply {
+            killers.resize(self.ply + 1, [None; 2]);
+        }
+        let killers = &mut killers[self.ply];
+        if killers[0] != Some(mov) {
+            killers[1] = killers[0];
+            killers[0] = Some(mov);
+        }
+        let history = &mut history[mov.from as usize][mov.to as usize];
+        *history = history.saturating_add(depth as i32 * depth as i32);
+    }
+
+    // looking at the clock every node would slow the search down, so it is only checked every so often
+    fn is_stopped(&mut self) -> bool {
+        if !self.stopped {
+            let timed_out = self.nodes.is_multiple_of(1024) && self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            self.stopped = timed_out || self.stop.load(Ordering::Relaxed);
+        }
+        self.stopped
+    }
+
+    pub(crate) fn was_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    pub(crate) fn minimax(&mut self, position: &mut Position, depth: u8, maximizing: bool, mut alpha: f32, mut beta: f32) -> f32 {
+        // the algorithm assumes a good heuristic or evaluation model to approximate game state evaluations
+        self.nodes += 1;
+        let after_null_move = std::mem::take(&mut self.after_null_move);
+        if self.is_stopped() || self.draw(position) {
+            return 0.0;
+        }
+        let ply = self.ply;
+        let entry = self.cache.probe(position.hash()).map(|entry| Entry { score: score_from_cache(entry.score, ply), ..entry });
+        self.tt_probes += 1;
+        self.tt_hits += entry.is_some() as u64;
+        if let Some(entry) = entry.filter(|entry| entry.depth >= depth) {
+            match entry.bound {
+                Bound::Exact => return entry.score,
+                Bound::Lower => alpha = f32::max(alpha, entry.score),
+                Bound::Upper => beta = f32::min(beta, entry.score),
+            }
+            if alpha >= beta {
+                return entry.score;
+            }
+        }
+        if let Some(score) = self.tablebase_score(position) {
+            return score;
+        }
+        if depth <= 1 && self.evaluator.prefers_batches() {
+            return self.last_level_minimax(position, maximizing, alpha, beta);
+        }
+        if depth == 0 {
+            return self.quiescence(position, maximizing, alpha, beta);
+        }
+        let (original_alpha, original_beta) = (alpha, beta);
+        let in_check = position.in_check(position.side());
+        // passing is almost never better than moving, so if the opponent can't get back under beta even after a free
+        // move the position would be cut off anyway. that isn't true in check, or in zugzwang, which is common when a
+        // player only has pawns left
+        if self.config.null_move
+            && !after_null_move
+            && depth > NULL_MOVE_REDUCTION
+            && !in_check
+            && position.has_non_pawn_material(position.side())
+        {
+            if let Some(score) = self.null_move(position, depth, maximizing, alpha, beta) {
+                return score;
+            }
+        }
+        let mut moves = position.legal_moves();
+        if moves.is_empty() {
+            return self.evaluate(position);
+        }
+        self.order_moves(position, &mut moves, entry.and_then(|entry| entry.best_move));
+        let mut best_move = None;
+        let mut best_score = if maximizing { f32::MIN } else { f32::MAX };
+        for (i, mov) in moves.into_iter().enumerate() {
+            let late = self.config.late_move_reductions
+                && i >= LATE_MOVES
+                && depth >= LATE_MOVE_MIN_DEPTH
+                && !in_check
+                && !position.is_tactical(mov)
+                && !self.is_killer(mov);
+            let undo = self.make_move(position, mov);
+            let gives_check = position.in_check(position.side());
+            let extension = (self.config.check_extensions && gives_check && self.ply < MAX_SEARCH_DEPTH as usize) as u8;
+            let reduction = (late && !gives_check) as u8;
+            let score = self.search_move(position, i, depth - 1 + extension, reduction, maximizing, alpha, beta);
+            self.unmake_move(position, mov, undo);
+            let better = if maximizing { score > best_score } else { score < best_score };
+            if better || best_move.is_none() {
+                best_score = score;
+                best_move = Some(mov);
+            }
+            if maximizing {
+                alpha = f32::max(alpha, best_score);
+            } else {
+                beta = f32::min(beta, best_score);
+            }
+            if alpha >= beta {
+                self.record_cutoff(position, mov, depth);
+                break;
+            }
+        }
+        if !self.is_stopped() {
+            // a score outside the window only bounds the true value
+            let bound = if best_score <= original_alpha {
+                Bound::Upper
+            } else if best_score >= original_beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            self.cache.store(position.hash(), Entry { score: score_to_cache(best_score, self.ply), depth, bound, best_move });
+        }
+        best_score
+    }
+
+    // searches a move that has been made, the first with the full window and later ones with a zero window and
+    // possibly reduced, searching them again in full if they turn out better than the best move so far
+    #[allow(clippy::too_many_arguments)]
+    fn search_move(
+        &mut self,
+        position: &mut Position,
+        index: usize,
+        depth: u8,
+        reduction: u8,
+        maximizing: bool,
+        alpha: f32,
+        beta: f32,
+    ) -> f32 {
+        if index == 0 || (reduction == 0 && !self.config.principal_variation) {
+            return self.minimax(position, depth, !maximizing, alpha, beta);
+        }
+        let (window_alpha, window_beta) = match (self.config.principal_variation, maximizing) {
+            (false, _) => (alpha, beta),
+            (true, true) => (alpha, alpha.next_up()),
+            (true, false) => (beta.next_down(), beta),
+        };
+        let score = self.minimax(position, depth - reduction, !maximizing, window_alpha, window_beta);
+        let better = if maximizing { score > alpha } else { score < beta };
+        // a zero window search that fails high at full depth has already shown the move is too good to be allowed
+        let within = if maximizing { score < beta } else { score > alpha };
+        if better && (reduction > 0 || within) {
+            self.minimax(position, depth, !maximizing, alpha, beta)
+        } else {
+            score
+        }
+    }
+
+    // the score to cut off with if the position is still past beta after passing the move to the opponent
+    fn null_move(&mut self, position: &mut Position, depth: u8, maximizing: bool, alpha: f32, beta: f32) -> Option<f32> {
+        let (window_alpha, window_beta) = if maximizing { (beta.next_down(), beta) } else { (alpha, alpha.next_up()) };
+        self.positions.push(position.hash());
+        self.ply += 1;
+        let undo = position.make_null_move();
+        self.after_null_move = true;
+        let score = self.minimax(position, depth - 1 - NULL_MOVE_REDUCTION, !maximizing, window_alpha, window_beta);
+        self.after_null_move = false;
+        position.unmake_null_move(undo);
+        self.ply -= 1;
+        self.positions.pop();
+        // a mate found after passing isn't a real one, so the cutoff is only by the bound
+        let cutoff = if maximizing { score >= beta } else { score <= alpha };
+        (cutoff && !self.is_stopped()).then_some(if maximizing { beta } else { alpha })
+    }
+
+    fn last_level_minimax(&mut self, position: &mut Position, maximizing: bool, alpha: f32, beta: f32) -> f32 {
+        let moves = position.legal_moves();
+        if moves.is_empty() {
+            return self.evaluate(position);
+        };
+        let mut move_evals = self.evaluate_moves(position, &moves);
+        // the evaluation can't see that a capture is about to be taken back, so captures are followed until the position is quiet,
+        // and it doesn't know the rules, so moves that leave the opponent without a move are scored by them
+        for (i, &mov) in moves.iter().enumerate() {
+            let tactical = position.is_tactical(mov);
+            let undo = self.make_move(position, mov);
+            if tactical {
+                move_evals[i] = self.quiescence(position, !maximizing, alpha, beta);
+            } else if !position.has_legal_move() {
+                move_evals[i] = self.evaluate(position);
+            }
+            self.unmake_move(position, mov, undo);
+        }
+        if beta <= alpha {
+            move_evals[0]
+        } else if maximizing {
+            move_evals.into_iter().fold(f32::MIN, f32::max)
+        } else {
+            move_evals.into_iter().fold(f32::MAX, f32::min)
+        }
+    }
+
+    // searches captures and promotions until the position is quiet, so that a position isn't evaluated in the
+    // middle of an exchange. the side to move can stand pat on the evaluation rather than make a bad capture
+    fn quiescence(&mut self, position: &mut Position, maximizing: bool, mut alpha: f32, mut beta: f32) -> f32 {
+        self.nodes += 1;
+        if self.is_stopped() {
+            return 0.0;
+        }
+        // in check every move that gets out of it has to be searched, and there is no standing pat
+        let in_check = position.in_check(position.side());
+        let mut moves = if in_check { position.legal_moves() } else { position.tactical_moves() };
+        // underpromotions rarely matter, so they are left to the main search
+        if !in_check {
+            moves.retain(|mov| mov.promotion.is_none_or(|promotion| promotion == PieceType::Queen));
+        }
+        if in_check && moves.is_empty() {
+            return self.evaluate(position);
+        }
+        moves.sort_by_cached_key(|&mov| -tactical_score(position, mov));
+        let stand_pat = (!in_check).then(|| self.static_evaluation(position));
+        let mut best_score = if maximizing { f32::MIN } else { f32::MAX };
+        if let Some(stand_pat) = stand_pat {
+            best_score = stand_pat;
+            if maximizing {
+                alpha = f32::max(alpha, stand_pat);
+            } else {
+                beta = f32::min(beta, stand_pat);
+            }
+            if alpha >= beta {
+                return stand_pat;
+            }
+        }
+        for mov in moves {
+            // delta pruning, skip captures that can't get back to the window even if the piece is won for free
+            if let Some(stand_pat) = stand_pat {
+                let gain = self.pawns(material_gain(position, mov) + DELTA_MARGIN);
+                if (maximizing && stand_pat + gain <= alpha) || (!maximizing && stand_pat - gain >= beta) {
+                    continue;
+                }
+            }
+            let undo = self.make_move(position, mov);
+            let score = self.quiescence(position, !maximizing, alpha, beta);
+            self.unmake_move(position, mov, undo);
+            if maximizing {
+                best_score = f32::max(best_score, score);
+                alpha = f32::max(alpha, best_score);
+            } else {
+                best_score = f32::min(best_score, score);
+                beta = f32::min(beta, best_score);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        best_score
+    }
+
+    // a number of pawns on the scale of the evaluation
+    fn pawns(&self, pawns: i32) -> f32 {
+        pawns as f32 * self.evaluator.pawn_scale()
+    }
+
+    // evaluates the positions after each move in one batch
+    pub(crate) fn evaluate_moves(&self, position: &Position, moves: &[Move]) -> Vec<f32> {
+        let positions = moves
+            .iter()
+            .map(|&mov| {
+                let mut next = *position;
+                next.make_move(mov);
+                next
+            })
+            .collect::<Vec<Position>>();
+        self.evaluator.evaluate_batch(&positions)
+    }
+
+    // checkmate scores by how many plies away it is, and stalemate is a draw
+    pub(crate) fn evaluate(&self, position: &mut Position) -> f32 {
+        if position.has_legal_move() {
+            self.static_evaluation(position)
+        } else if !position.in_check(position.side()) {
+            0.0
+        } else if position.side().is_maximizing() {
+            self.ply as f32 - MATE
+        } else {
+            MATE - self.ply as f32
+        }
+    }
+
+    // the tablebases' result, probed only just after a capture or pawn move, since the wdl tables don't know how long
+    // the fifty-move count has been running
+    fn tablebase_score(&self, position: &Position) -> Option<f32> {
+        if self.ply == 0 || position.half_move_clock() != 0 {
+            return None;
+        }
+        let wdl = self.tablebase?.probe_wdl(position)?;
+        let score = tablebase_score(wdl, self.ply as u32, self.evaluator.pawn_scale());
+        Some(if position.side().is_maximizing() { score } else { -score })
+    }
+
+    // the evaluation without looking for checkmate or stalemate
+    fn static_evaluation(&self, position: &Position) -> f32 {
+        self.evaluator.evaluate(position)
+    }
+
+    // the search treats a single repetition as a draw so that it doesn't go in circles
+    fn draw(&self, position: &Position) -> bool {
+        position.half_move_clock() >= HALF_MOVE_LIMIT || self.repetitions(position) >= 2 || position.insufficient_material()
+    }
+
+    fn repetitions(&self, position: &Position) -> usize {
+        let reversible = min(position.half_move_clock() as usize, self.positions.len());
+        let earlier = &self.positions[self.positions.len() - reversible..];
+        1 + earlier.iter().filter(|&&earlier| earlier == position.hash()).count()
+    }
+}
+
+// orders captures by the most valuable victim first, taken by the least valuable attacker, and then promotions
+fn tactical_score(position: &Position, mov: Move) -> i32 {
+    if let Some(captured) = position.captured(mov) {
+        let (_, attacker) = position.piece_at(mov.from).unwrap();
+        return CAPTURE_SCORE + captured.value() * 100 - attacker.value();
+    }
+    match mov.promotion {
+        Some(promotion) => PROMOTION_SCORE + promotion.value(),
+        None => 0,
+    }
+}
+
+// the material a move wins in pawns, the piece it takes plus what a pawn promotes to
+fn material_gain(position: &Position, mov: Move) -> i32 {
+    let captured = position.captured(mov).map_or(0, PieceType::value);
+    let promoted = mov.promotion.map_or(0, |promotion| promotion.value() - PieceType::Pawn.value());
+    captured + promoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{evaluator::Material, game::Game};
+
+    #[test]
+    fn cache_records_bound_types() {
+        let game = Game::two_player_game(false);
+        let cache = TranspositionTable::default();
+        let stop = AtomicBool::new(false);
+        let minimax = |alpha, beta| {
+            let mut position = *game.position();
+            Searcher::new(&Material, &cache, None, &stop, None, Vec::new(), SearchConfig::default()).minimax(&mut position, 2, true, alpha, beta)
+        };
+        let exact = minimax(f32::MIN, f32::MAX);
+        assert_eq!(cache.probe(game.hash()).unwrap().bound, Bound::Exact);
+
+        cache.clear();
+        assert!(minimax(exact + 1.0, exact + 2.0) <= exact + 1.0);
+        assert_eq!(cache.probe(game.hash()).unwrap().bound, Bound::Upper);
+
+        cache.clear();
+        assert!(minimax(exact - 2.0, exact - 1.0) >= exact - 1.0);
+        let entry = cache.probe(game.hash()).unwrap();
+        assert_eq!(entry.bound, Bound::Lower);
+        assert!(entry.best_move.is_some());
+    }
+
+    #[test]
+    fn quiescence_sees_recaptures() {
+        // the pawn on d5 is defended, so taking it with the queen loses the queen
+        let mut game = Game::from_fen("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let (mov, _) = game.search(1).unwrap();
+        assert_ne!(mov.to_string(), "d1d5");
+
+        // taking the free knight lets black take the queen, but white takes back and is a knight up
+        let mut game = Game::from_fen("3q3k/5ppp/n7/8/8/3B4/5PPP/2KQ4 w - - 0 1").unwrap();
+        let (mov, score) = game.search(2).unwrap();
+        assert_eq!(mov.to_string(), "d3a6");
+        assert!(score > 0.0);
+    }
+
+    #[test]
+    fn quiescence_follows_promotions() {
+        // the pawn on b2 queens unless the rook keeps guarding b1, which a one ply search can't see on its own
+        let mut game = Game::from_fen("6k1/8/8/8/8/8/1p3PPP/R5K1 w - - 0 1").unwrap();
+        let (mov, score) = game.search(1).unwrap();
+        assert_eq!(mov.from, (0, 7));
+        assert_eq!(score, 7.0);
+    }
+
+    #[test]
+    fn search_finds_underpromotions() {
+        // promoting to a knight forks the king and queen, where a new queen would just be taken
+        let mut game = Game::from_fen("8/3q1P1k/8/8/8/8/P7/K7 w - - 0 1").unwrap();
+        let (mov, score) = game.search(2).unwrap();
+        assert_eq!(mov.to_string(), "f7f8n");
+        assert_eq!(score, 4.0);
+    }
+
+    #[test]
+    fn ordering_searches_fewer_positions() {
+        let game = Game::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let stop = AtomicBool::new(false);
+        let nodes = |ordering| {
+            let cache = TranspositionTable::default();
+            let config = SearchConfig { move_ordering: ordering, ..SearchConfig::default() };
+            let mut searcher = Searcher::new(&Material, &cache, None, &stop, None, Vec::new(), config);
+            for depth in 1..=3 {
+                let mut position = *game.position();
+                searcher.minimax(&mut position, depth, true, f32::MIN, f32::MAX);
+            }
+            searcher.stats().nodes
+        };
+        let (ordered, unordered) = (nodes(true), nodes(false));
+        assert!(ordered < unordered, "{ordered} positions searched with ordering, {unordered} without");
+    }
+
+    #[test]
+    fn keeps_the_cutoffs_between_searches() {
+        let game = Game::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let cache = TranspositionTable::default();
+        let stop = AtomicBool::new(false);
+        let tables = CutoffTables::default();
+        let mut position = *game.position();
+        let mut searcher = Searcher::new(&Material, &cache, None, &stop, None, Vec::new(), SearchConfig::default())
+            .with_cutoffs(tables.take());
+        for depth in 1..=4 {
+            searcher.minimax(&mut position, depth, true, f32::MIN, f32::MAX);
+        }
+        tables.put_back(searcher.into_cutoffs());
+
+        let cutoffs = tables.take();
+        // a search running beside the first starts from nothing
+        assert!(tables.take().killers.is_empty());
+        assert!(!cutoffs.killers.is_empty());
+        let history = cutoffs.history.clone();
+        assert!(history.iter().flatten().any(|&count| count > 1));
+        tables.put_back(cutoffs);
+        tables.age();
+        let aged = tables.take().history;
+        assert!(history.iter().flatten().zip(aged.iter().flatten()).all(|(&count, &aged)| aged == count / 2));
+    }
+
+    #[test]
+    fn selective_search_finds_the_same_tactics() {
+        // the knight forks the king and queen, winning the queen once the king has moved
+        let game = Game::from_fen("2q3k1/8/8/3N4/8/8/P7/K7 w - - 0 1").unwrap();
+        let stop = AtomicBool::new(false);
+        let plain = SearchConfig {
+            principal_variation: false,
+            null_move: false,
+            late_move_reductions: false,
+            check_extensions: false,
+            ..SearchConfig::default()
+        };
+        let configs = [
+            plain,
+            SearchConfig { principal_variation: true, ..plain },
+            SearchConfig { null_move: true, ..plain },
+            SearchConfig { late_move_reductions: true, ..plain },
+            SearchConfig { check_extensions: true, ..plain },
+            SearchConfig::default(),
+        ];
+        let mut nodes = Vec::new();
+        for config in configs {
+            let cache = TranspositionTable::default();
+            let mut searcher = Searcher::new(&Material, &cache, None, &stop, None, Vec::new(), config);
+            let mut position = *game.position();
+            let mut score = 0.0;
+            for depth in 1..=4 {
+                score = searcher.minimax(&mut position, depth, true, f32::MIN, f32::MAX);
+            }
+            assert_eq!(position, *game.position());
+            assert_eq!(score, 4.0, "{config:?}");
+            nodes.push(searcher.stats().nodes);
+        }
+        assert!(nodes[5] < nodes[0], "{} positions searched with every technique, {} without", nodes[5], nodes[0]);
+    }
+}
@@ -0,0 +1,1251 @@
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::{
+    bitboard::squares,
+    player::Player,
+    position::{Move, PieceType, Position},
+};
+
+// reads syzygy endgame tablebases, which hold whether every position with a few pieces is won, drawn or lost (wdl)
+// and how many plies it takes to get to a capture or pawn move while keeping that result (dtz).
+// tables square from a1 to h8 and give pieces the codes 1 to 6 from pawn to king, plus 8 for black
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+const WDL_SUFFIX: &str = "rtbw";
+const DTZ_SUFFIX: &str = "rtbz";
+const MAX_PIECES: usize = 7;
+const PIECE_LETTERS: [char; 6] = ['K', 'Q', 'R', 'B', 'N', 'P'];
+const PIECE_TYPES: [PieceType; 6] = [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King];
+
+// the dtz of a capture or pawn move to a position of each wdl, from loss to win
+const WDL_TO_DTZ: [i32; 5] = [-1, -101, 0, 101, 1];
+// which of a dtz table's four maps each wdl uses, and the flag saying it counts plies rather than moves
+const WDL_TO_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+const PA_FLAGS: [u8; 5] = [8, 0, 0, 0, 4];
+
+// what a position is worth to the player to move. cursed wins and blessed losses are wins and losses that the
+// fifty-move rule turns into draws
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Self {
+        match value {
+            ..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+}
+
+// the tables found in one or more directories, each read the first time it is probed
+pub struct Tablebase {
+    wdl: HashMap<String, LazyTable>,
+    dtz: HashMap<String, LazyTable>,
+    max_pieces: usize,
+}
+
+struct LazyTable {
+    path: PathBuf,
+    // None once reading the table has failed, so that it isn't tried again
+    table: OnceLock<Option<Table>>,
+}
+
+impl Tablebase {
+    // the directories are separated like the PATH environment variable
+    pub fn open(paths: &str) -> io::Result<Self> {
+        let mut tablebase = Self { wdl: HashMap::new(), dtz: HashMap::new(), max_pieces: 0 };
+        for dir in env::split_paths(paths) {
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                let (Some(name), Some(suffix)) = (path.file_stem().and_then(|name| name.to_str()), path.extension()) else {
+                    continue;
+                };
+                if !is_table_name(name) {
+                    continue;
+                }
+                let tables = if suffix == WDL_SUFFIX {
+                    tablebase.max_pieces = tablebase.max_pieces.max(name.len() - 1);
+                    &mut tablebase.wdl
+                } else if suffix == DTZ_SUFFIX {
+                    &mut tablebase.dtz
+                } else {
+                    continue;
+                };
+                tables.insert(name.to_string(), LazyTable { path, table: OnceLock::new() });
+            }
+        }
+        if tablebase.wdl.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no syzygy tables in {paths}")));
+        }
+        Ok(tablebase)
+    }
+
+    // how many pieces, kings included, the largest tables have
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    // whether the position has few enough pieces for the tables. positions where castling is still allowed aren't in them
+    pub fn covers(&self, position: &Position) -> bool {
+        position.castling() == 0 && (position.occupied().count_ones() as usize) <= self.max_pieces
+    }
+
+    // None if the position isn't covered or a table it needs is missing
+    pub fn probe_wdl(&self, position: &Position) -> Option<Wdl> {
+        if !self.covers(position) {
+            return None;
+        }
+        self.wdl(&mut { *position }).map(Wdl::from_value)
+    }
+
+    // the plies to a capture or pawn move that keeps the result, positive for the player to move winning and over 100
+    // for cursed wins. checkmates and captures that win count as 1
+    pub fn probe_dtz(&self, position: &Position) -> Option<i32> {
+        if !self.covers(position) {
+            return None;
+        }
+        self.dtz(&mut { *position })
+    }
+
+    // each legal move with what it leads to for the player making it and how many plies until the fifty-move count
+    // is reset, which is how winning moves should be picked. a win that the fifty-move rule would stop, going by the
+    // position's half-move clock, is a cursed win. None unless every move can be probed
+    pub fn root_moves(&self, position: &Position) -> Option<Vec<(Move, Wdl, u32)>> {
+        if !self.covers(position) {
+            return None;
+        }
+        let mut position = *position;
+        let clock = position.half_move_clock() as i32;
+        position
+            .legal_moves()
+            .into_iter()
+            .map(|mov| {
+                let mut next = position;
+                next.make_move(mov);
+                let dtz = if next.in_check(next.side()) && !next.has_legal_move() {
+                    1
+                } else if next.half_move_clock() == 0 {
+                    WDL_TO_DTZ[(2 - self.wdl(&mut next)?) as usize]
+                } else {
+                    match self.dtz(&mut next)? {
+                        dtz if dtz > 0 => -dtz - 1,
+                        dtz if dtz < 0 => -dtz + 1,
+                        _ => 0,
+                    }
+                };
+                let wdl = match dtz {
+                    dtz if dtz > 0 && dtz + clock <= 100 => Wdl::Win,
+                    dtz if dtz > 0 => Wdl::CursedWin,
+                    dtz if dtz < 0 && clock - dtz <= 100 => Wdl::Loss,
+                    dtz if dtz < 0 => Wdl::BlessedLoss,
+                    _ => Wdl::Draw,
+                };
+                Some((mov, wdl, dtz.unsigned_abs()))
+            })
+            .collect()
+    }
+
+    fn table<'a>(tables: &'a HashMap<String, LazyTable>, position: &Position, dtz: bool) -> Option<&'a Table> {
+        let lazy = tables.get(&material_key(position, false)).or_else(|| tables.get(&material_key(position, true)))?;
+        lazy.table.get_or_init(|| Table::open(&lazy.path, dtz).ok()).as_ref()
+    }
+
+    // the wdl as a number from -2 for a loss to 2 for a win, taking en passant into account, which the tables don't
+    fn wdl(&self, position: &mut Position) -> Option<i32> {
+        let (mut wdl, _) = self.probe_ab(position, -2, 2)?;
+        let Some(best_en_passant) = self.best_en_passant(position)? else {
+            return Some(wdl);
+        };
+        if best_en_passant >= wdl {
+            wdl = best_en_passant;
+        } else if wdl == 0 && self.only_en_passant(position) {
+            // a drawn position where the only legal move is a losing en passant capture is lost
+            wdl = best_en_passant;
+        }
+        Some(wdl)
+    }
+
+    fn dtz(&self, position: &mut Position) -> Option<i32> {
+        let mut dtz = self.dtz_without_en_passant(position)?;
+        let Some(best_en_passant) = self.best_en_passant(position)? else {
+            return Some(dtz);
+        };
+        let en_passant = WDL_TO_DTZ[(best_en_passant + 2) as usize];
+        let better = if dtz < -100 {
+            en_passant >= 0
+        } else if dtz < 0 {
+            !(-100..0).contains(&en_passant)
+        } else if dtz > 100 {
+            en_passant > 0
+        } else if dtz > 0 {
+            en_passant == 1
+        } else {
+            en_passant >= 0 || self.only_en_passant(position)
+        };
+        if better {
+            dtz = en_passant;
+        }
+        Some(dtz)
+    }
+
+    // the best wdl of the en passant captures, if there are any
+    fn best_en_passant(&self, position: &mut Position) -> Option<Option<i32>> {
+        let mut best = None;
+        for mov in position.legal_moves() {
+            if !is_en_passant(position, mov) {
+                continue;
+            }
+            let undo = position.make_move(mov);
+            let result = self.probe_ab(position, -2, 2);
+            position.unmake_move(mov, undo);
+            let wdl = -result?.0;
+            best = Some(best.map_or(wdl, |best: i32| best.max(wdl)));
+        }
+        Some(best)
+    }
+
+    fn only_en_passant(&self, position: &mut Position) -> bool {
+        position.legal_moves().into_iter().all(|mov| is_en_passant(position, mov))
+    }
+
+    // the wdl searching captures first, since the tables assume the best capture has been played where one wins or
+    // draws. the second value is 2 if a capture is best and 1 otherwise
+    fn probe_ab(&self, position: &mut Position, mut alpha: i32, beta: i32) -> Option<(i32, u8)> {
+        for mov in position.legal_moves() {
+            if position.piece_at(mov.to).is_none() {
+                continue;
+            }
+            let undo = position.make_move(mov);
+            let result = self.probe_ab(position, -beta, -alpha);
+            position.unmake_move(mov, undo);
+            let score = -result?.0;
+            if score > alpha {
+                if score >= beta {
+                    return Some((score, 2));
+                }
+                alpha = score;
+            }
+        }
+        let score = self.probe_wdl_table(position)?;
+        Some(if alpha >= score { (alpha, 1 + (alpha > 0) as u8) } else { (score, 1) })
+    }
+
+    fn dtz_without_en_passant(&self, position: &mut Position) -> Option<i32> {
+        let (wdl, success) = self.probe_ab(position, -2, 2)?;
+        if wdl == 0 {
+            return Some(0);
+        }
+        if success == 2 {
+            return Some(WDL_TO_DTZ[(wdl + 2) as usize]);
+        }
+        let moves = position.legal_moves();
+        if wdl > 0 {
+            // a pawn move that keeps the win resets the count straight away
+            for &mov in &moves {
+                if !is_pawn_push(position, mov) {
+                    continue;
+                }
+                let undo = position.make_move(mov);
+                let result = self.wdl(position);
+                position.unmake_move(mov, undo);
+                if -result? == wdl {
+                    return Some(if wdl == 2 { 1 } else { 101 });
+                }
+            }
+        }
+        if let Some(dtz) = self.probe_dtz_table(position, wdl)? {
+            return Some(WDL_TO_DTZ[(wdl + 2) as usize] + if wdl > 0 { dtz } else { -dtz });
+        }
+        // the table only has the other player to move, so look a move ahead
+        if wdl > 0 {
+            let mut best = i32::MAX;
+            for mov in moves {
+                if position.piece_at(mov.from).is_some_and(|(_, piece_type)| piece_type == PieceType::Pawn)
+                    || position.piece_at(mov.to).is_some()
+                {
+                    continue;
+                }
+                let undo = position.make_move(mov);
+                let result = self.dtz(position);
+                let mate = position.in_check(position.side()) && !position.has_legal_move();
+                position.unmake_move(mov, undo);
+                let dtz = -result?;
+                if dtz == 1 && mate {
+                    best = 1;
+                } else if dtz > 0 && dtz + 1 < best {
+                    best = dtz + 1;
+                }
+            }
+            Some(best)
+        } else {
+            let mut best = -1;
+            for mov in moves {
+                let undo = position.make_move(mov);
+                let result = if position.half_move_clock() == 0 {
+                    if wdl == -2 {
+                        Some(-1)
+                    } else {
+                        self.probe_ab(position, 1, 2).map(|(score, _)| if score == 2 { 0 } else { -101 })
+                    }
+                } else {
+                    self.dtz(position).map(|dtz| -dtz - 1)
+                };
+                position.unmake_move(mov, undo);
+                best = best.min(result?);
+            }
+            Some(best)
+        }
+    }
+
+    fn probe_wdl_table(&self, position: &Position) -> Option<i32> {
+        if position.occupied() == position.pieces(Player::One, PieceType::King) | position.pieces(Player::Two, PieceType::King) {
+            return Some(0);
+        }
+        let table = Self::table(&self.wdl, position, false)?;
+        let (side, (file, squares)) = table.squares(position)?;
+        let data = table.files[file].get(side)?;
+        Some(data.pairs.decompress(&table.data, data.encode(table, squares)?)? as i32 - 2)
+    }
+
+    // None inside if the table only has the other player to move
+    fn probe_dtz_table(&self, position: &Position, wdl: i32) -> Option<Option<i32>> {
+        let table = Self::table(&self.dtz, position, true)?;
+        let (side, (file, squares)) = table.squares(position)?;
+        let flags = table.files[file][0].pairs.flags;
+        if (flags & 1) as usize != side && !table.symmetric {
+            return Some(None);
+        }
+        let data = &table.files[file][0];
+        let mut dtz = data.pairs.decompress(&table.data, data.encode(table, squares)?)?;
+        if flags & 2 != 0 {
+            let map = table.map + table.map_index[file][WDL_TO_MAP[(wdl + 2) as usize]];
+            dtz = if flags & 16 == 0 {
+                *table.data.get(map + dtz as usize)? as u32
+            } else {
+                read_u16(&table.data, table.map + 2 * (table.map_index[file][WDL_TO_MAP[(wdl + 2) as usize]] + dtz as usize))? as u32
+            };
+        }
+        if flags & PA_FLAGS[(wdl + 2) as usize] == 0 || wdl & 1 != 0 {
+            dtz *= 2;
+        }
+        Some(Some(dtz as i32))
+    }
+}
+
+// one wdl or dtz table, with data for each pawn file when it has pawns and for each player to move when it is a wdl
+// table that splits them
+struct Table {
+    data: Vec<u8>,
+    // the material of the first player to move in the table, which decides when positions are flipped to probe it
+    key: String,
+    symmetric: bool,
+    has_pawns: bool,
+    pieces: usize,
+    // how many pawns the leading color, which the pawn files are picked by, and the other color have
+    pawns: [usize; 2],
+    // 0 if at least three pieces are the only ones of their kind, and they are placed together, otherwise 2
+    encoding: u8,
+    files: Vec<Vec<SideData>>,
+    // where a dtz table's maps from stored values to plies start, and where each file's four maps are in them
+    map: usize,
+    map_index: Vec<[usize; 4]>,
+}
+
+struct SideData {
+    pieces: Vec<u8>,
+    // how many pieces are placed together from each index, which is how many of a kind there are
+    norm: Vec<usize>,
+    factor: [u64; MAX_PIECES],
+    pairs: Pairs,
+}
+
+impl Table {
+    fn open(path: &Path, dtz: bool) -> io::Result<Self> {
+        let name = path.file_stem().and_then(|name| name.to_str()).filter(|name| is_table_name(name)).ok_or_else(corrupt)?;
+        Self::from_bytes(name, fs::read(path)?, dtz)
+    }
+
+    fn from_bytes(name: &str, data: Vec<u8>, dtz: bool) -> io::Result<Self> {
+        let magic = if dtz { DTZ_MAGIC } else { WDL_MAGIC };
+        if !data.starts_with(&magic) || data.len() < 6 {
+            return Err(corrupt());
+        }
+        let (white, black) = name.split_once('v').ok_or_else(corrupt)?;
+        let mut pawns = [white.matches('P').count(), black.matches('P').count()];
+        if pawns[1] > 0 && (pawns[0] == 0 || pawns[1] < pawns[0]) {
+            pawns.swap(0, 1);
+        }
+        let unique = PIECE_LETTERS
+            .iter()
+            .flat_map(|&letter| [white, black].map(|side| side.matches(letter).count()))
+            .filter(|&count| count == 1)
+            .count();
+        let mut table = Self {
+            data: Vec::new(),
+            key: String::new(),
+            symmetric: white == black,
+            has_pawns: pawns[0] > 0,
+            pieces: name.len() - 1,
+            pawns,
+            encoding: if unique >= 3 { 0 } else { 2 },
+            files: Vec::new(),
+            map: 0,
+            map_index: Vec::new(),
+        };
+        if table.pieces > MAX_PIECES {
+            return Err(corrupt());
+        }
+        table.read(&data, dtz)?;
+        table.key = recalc_key(&table.files[0][0].pieces);
+        table.data = data;
+        Ok(table)
+    }
+
+    // the layout is the pieces of each file, the compression of each file and side, their index tables, their size
+    // tables and then their compressed blocks, aligned to 64 bytes
+    fn read(&mut self, data: &[u8], dtz: bool) -> io::Result<()> {
+        let split = !dtz && read_u8(data, 4)? & 1 != 0;
+        let sides = if split { 2 } else { 1 };
+        let files = if self.has_pawns { 4 } else { 1 };
+        let mut at = 5;
+        let mut pieces = Vec::new();
+        for file in 0..files {
+            let order_bytes = if self.has_pawns && self.pawns[1] > 0 { 2 } else { 1 };
+            let mut file_pieces = Vec::new();
+            for side in 0..if dtz { 1 } else { 2 } {
+                let nibble = |byte: u8| if side == 0 { byte & 0x0f } else { byte >> 4 };
+                let order = nibble(read_u8(data, at)?) as usize;
+                let order2 = if order_bytes == 2 { nibble(read_u8(data, at + 1)?) as usize } else { 0x0f };
+                let codes = (0..self.pieces).map(|i| read_u8(data, at + order_bytes + i).map(nibble)).collect::<io::Result<Vec<u8>>>()?;
+                let norm = self.norm(&codes);
+                let (factor, size) = self.factors(&norm, order, order2, file);
+                file_pieces.push((codes, norm, factor, size));
+            }
+            at += self.pieces + order_bytes;
+            pieces.push(file_pieces);
+        }
+        at += at & 1;
+
+        let mut all_pairs = Vec::new();
+        for file_pieces in &pieces {
+            let mut file_pairs = Vec::new();
+            for (_, _, _, size) in file_pieces.iter().take(sides) {
+                let (pairs, next) = Pairs::read(data, at, *size, !dtz)?;
+                file_pairs.push(pairs);
+                at = next;
+            }
+            all_pairs.push(file_pairs);
+        }
+
+        if dtz {
+            self.map = at;
+            for file_pairs in &all_pairs {
+                let mut map_index = [0; 4];
+                let flags = file_pairs[0].flags;
+                if flags & 2 != 0 {
+                    if flags & 16 == 0 {
+                        for index in &mut map_index {
+                            *index = at + 1 - self.map;
+                            at += 1 + read_u8(data, at)? as usize;
+                        }
+                    } else {
+                        at += at & 1;
+                        for index in &mut map_index {
+                            *index = (at + 2 - self.map) / 2;
+                            at += 2 + 2 * read_u16(data, at).ok_or_else(corrupt)? as usize;
+                        }
+                    }
+                }
+                self.map_index.push(map_index);
+            }
+            at += at & 1;
+        }
+
+        for file_pairs in &mut all_pairs {
+            for pairs in file_pairs.iter_mut() {
+                pairs.index_table = at;
+                at += pairs.sizes[0];
+            }
+        }
+        for file_pairs in &mut all_pairs {
+            for pairs in file_pairs.iter_mut() {
+                pairs.size_table = at;
+                at += pairs.sizes[1];
+            }
+        }
+        for file_pairs in &mut all_pairs {
+            for pairs in file_pairs.iter_mut() {
+                at = (at + 0x3f) & !0x3f;
+                pairs.blocks = at;
+                at += pairs.sizes[2];
+            }
+        }
+        if at > data.len() {
+            return Err(corrupt());
+        }
+
+        self.files = pieces
+            .into_iter()
+            .zip(all_pairs)
+            .map(|(file_pieces, file_pairs)| {
+                let mut file_pairs = file_pairs.into_iter();
+                let first = file_pairs.next();
+                let second = file_pairs.next();
+                file_pieces
+                    .into_iter()
+                    .zip([first, second])
+                    .filter_map(|((pieces, norm, factor, _), pairs)| pairs.map(|pairs| SideData { pieces, norm, factor, pairs }))
+                    .collect()
+            })
+            .collect();
+        Ok(())
+    }
+
+    fn norm(&self, pieces: &[u8]) -> Vec<usize> {
+        let mut norm = vec![0; self.pieces];
+        let mut i = if self.has_pawns {
+            norm[0] = self.pawns[0];
+            if self.pawns[1] > 0 {
+                norm[self.pawns[0]] = self.pawns[1];
+            }
+            self.pawns[0] + self.pawns[1]
+        } else {
+            norm[0] = if self.encoding == 0 { 3 } else { 2 };
+            norm[0]
+        };
+        while i < self.pieces {
+            norm[i] = pieces[i..].iter().take_while(|&&piece| piece == pieces[i]).count();
+            i += norm[i];
+        }
+        norm
+    }
+
+    // what each group of pieces' index is multiplied by, in the order the table gives, and how many indices there are
+    fn factors(&self, norm: &[usize], order: usize, order2: usize, file: usize) -> ([u64; MAX_PIECES], u64) {
+        let mut factor = [0; MAX_PIECES];
+        let mut i = norm[0];
+        if self.has_pawns && order2 < 0x0f {
+            i += norm[i];
+        }
+        let mut free = 64 - i as u64;
+        let mut size = 1;
+        let mut k = 0;
+        while i < self.pieces || k == order || (self.has_pawns && k == order2) {
+            if k == order {
+                factor[0] = size;
+                size *= if !self.has_pawns {
+                    if self.encoding == 0 { 31332 } else { 462 }
+                } else {
+                    PAWN_FACTOR[norm[0] - 1][file]
+                };
+            } else if self.has_pawns && k == order2 {
+                factor[norm[0]] = size;
+                size *= binomial(48 - norm[0] as u64, norm[norm[0]] as u64);
+            } else {
+                factor[i] = size;
+                size *= binomial(free, norm[i] as u64);
+                free -= norm[i] as u64;
+                i += norm[i];
+            }
+            k += 1;
+        }
+        (factor, size)
+    }
+
+    // the player whose data to probe, 0 for the first, the pawn file, and the pieces' squares in the table's order.
+    // positions where the material is the other way round to the table are probed with the colors swapped
+    fn squares(&self, position: &Position) -> Option<(usize, (usize, Vec<u8>))> {
+        let white = position.side() == Player::One;
+        let (flip_color, flip_rank, side) = if self.symmetric {
+            if white { (0, 0, 0) } else { (8, 0x38, 0) }
+        } else if material_key(position, false) != self.key {
+            (8, 0x38, white as usize)
+        } else {
+            (0, 0, !white as usize)
+        };
+        // tables without pawns are the same either way up
+        let flip_rank = if self.has_pawns { flip_rank } else { 0 };
+        let mut placed = Vec::with_capacity(self.pieces);
+        let place = |placed: &mut Vec<u8>, code: u8| -> Option<()> {
+            let code = code ^ flip_color;
+            let player = if code & 8 == 0 { Player::One } else { Player::Two };
+            let piece_type = *PIECE_TYPES.get(((code & 7) as usize).checked_sub(1)?)?;
+            // tables count squares from a1 rather than a8
+            for square in squares(position.pieces(player, piece_type).swap_bytes()) {
+                if placed.len() >= self.pieces {
+                    return None;
+                }
+                placed.push(square ^ flip_rank);
+            }
+            Some(())
+        };
+        let mut file = 0;
+        if self.has_pawns {
+            place(&mut placed, self.files[0][0].pieces[0])?;
+            file = pawn_file(&mut placed, self.pawns[0]);
+        }
+        let pieces = &self.files[file][side.min(self.files[file].len() - 1)].pieces;
+        while placed.len() < self.pieces {
+            let before = placed.len();
+            let code = pieces[before];
+            place(&mut placed, code)?;
+            if placed.len() == before {
+                return None;
+            }
+        }
+        Some((side, (file, placed)))
+    }
+}
+
+impl SideData {
+    fn encode(&self, table: &Table, mut squares: Vec<u8>) -> Option<u64> {
+        if squares.len() != table.pieces {
+            return None;
+        }
+        Some(if table.has_pawns {
+            encode_pawn(&self.norm, &mut squares, &self.factor, table.pawns)
+        } else {
+            encode_piece(&self.norm, &mut squares, &self.factor, table.encoding)
+        })
+    }
+}
+
+// the values of a table are compressed by replacing common pairs of symbols with new symbols, over and over, and then
+// giving the symbols canonical huffman codes. the codes are split into blocks, with an index of which block every
+// so many values are in
+struct Pairs {
+    flags: u8,
+    index_bits: u32,
+    block_size: u32,
+    // the shortest code length, or the table's only value when there are no index bits
+    min_len: u8,
+    // where the first symbol of each code length is kept
+    offsets: usize,
+    // the first code of each length, shifted to the top of 64 bits
+    base: Vec<u64>,
+    // how many values each symbol stands for, less one
+    sym_len: Vec<u8>,
+    sym_pat: usize,
+    index_table: usize,
+    size_table: usize,
+    blocks: usize,
+    // the bytes of the index table, the size table and the blocks
+    sizes: [usize; 3],
+}
+
+impl Pairs {
+    fn read(data: &[u8], at: usize, size: u64, wdl: bool) -> io::Result<(Self, usize)> {
+        let flags = read_u8(data, at)?;
+        let mut pairs = Self {
+            flags,
+            index_bits: 0,
+            block_size: 0,
+            min_len: 0,
+            offsets: 0,
+            base: Vec::new(),
+            sym_len: Vec::new(),
+            sym_pat: 0,
+            index_table: 0,
+            size_table: 0,
+            blocks: 0,
+            sizes: [0; 3],
+        };
+        if flags & 0x80 != 0 {
+            if wdl {
+                pairs.min_len = read_u8(data, at + 1)?;
+            }
+            return Ok((pairs, at + 2));
+        }
+        pairs.block_size = read_u8(data, at + 1)? as u32;
+        pairs.index_bits = read_u8(data, at + 2)? as u32;
+        let real_blocks = read_u32(data, at + 4).ok_or_else(corrupt)? as usize;
+        let blocks = real_blocks + read_u8(data, at + 3)? as usize;
+        let max_len = read_u8(data, at + 8)?;
+        pairs.min_len = read_u8(data, at + 9)?;
+        if pairs.min_len == 0 || max_len < pairs.min_len || max_len >= 64 || !(1..64).contains(&pairs.index_bits) || pairs.block_size >= 32 {
+            return Err(corrupt());
+        }
+        let lengths = (max_len - pairs.min_len + 1) as usize;
+        pairs.offsets = at + 10;
+        let symbols = read_u16(data, at + 10 + 2 * lengths).ok_or_else(corrupt)? as usize;
+        pairs.sym_pat = at + 12 + 2 * lengths;
+        let next = pairs.sym_pat + 3 * symbols + (symbols & 1);
+        if next > data.len() {
+            return Err(corrupt());
+        }
+        let indices = size.div_ceil(1 << pairs.index_bits) as usize;
+        pairs.sizes = [6 * indices, 2 * blocks, real_blocks << pairs.block_size];
+
+        pairs.sym_len = vec![0; symbols];
+        let mut state = vec![SymbolState::Unread; symbols];
+        for symbol in 0..symbols {
+            pairs.symbol_length(data, symbol, &mut state)?;
+        }
+
+        let offset = |i: usize| read_u16(data, at + 10 + 2 * i).map(|offset| offset as u64).ok_or_else(corrupt);
+        pairs.base = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            pairs.base[i] = (pairs.base[i + 1].wrapping_add(offset(i)?).wrapping_sub(offset(i + 1)?)) / 2;
+        }
+        for (i, base) in pairs.base.iter_mut().enumerate() {
+            *base <<= 64 - (pairs.min_len as usize + i);
+        }
+        Ok((pairs, next))
+    }
+
+    // symbols are 12-bit pairs of other symbols, or a value when the second is 0xfff
+    fn symbol(data: &[u8], sym_pat: usize, symbol: usize) -> io::Result<(usize, usize)> {
+        let w = data.get(sym_pat + 3 * symbol..sym_pat + 3 * symbol + 3).ok_or_else(corrupt)?;
+        Ok(((((w[1] & 0x0f) as usize) << 8) | w[0] as usize, ((w[2] as usize) << 4) | (w[1] >> 4) as usize))
+    }
+
+    fn symbol_length(&mut self, data: &[u8], symbol: usize, state: &mut [SymbolState]) -> io::Result<()> {
+        match state[symbol] {
+            SymbolState::Read => return Ok(()),
+            SymbolState::Reading => return Err(corrupt()),
+            SymbolState::Unread => state[symbol] = SymbolState::Reading,
+        }
+        let (left, right) = Self::symbol(data, self.sym_pat, symbol)?;
+        if right != 0xfff {
+            if left >= state.len() || right >= state.len() {
+                return Err(corrupt());
+            }
+            self.symbol_length(data, left, state)?;
+            self.symbol_length(data, right, state)?;
+            self.sym_len[symbol] = self.sym_len[left].wrapping_add(self.sym_len[right]).wrapping_add(1);
+        }
+        state[symbol] = SymbolState::Read;
+        Ok(())
+    }
+
+    fn decompress(&self, data: &[u8], index: u64) -> Option<u32> {
+        if self.index_bits == 0 {
+            return Some(self.min_len as u32);
+        }
+        // the index table gives the block and position of the value in the middle of every 2^index_bits
+        let main = (index >> self.index_bits) as usize;
+        let mut literal = (index & ((1 << self.index_bits) - 1)) as i64 - (1 << (self.index_bits - 1));
+        let entry = self.index_table + 6 * main;
+        let mut block = read_u32(data, entry)? as usize;
+        literal += read_u16(data, entry + 4)? as i64;
+        let block_length = |block: usize| read_u16(data, self.size_table + 2 * block).map(|size| size as i64 + 1);
+        while literal < 0 {
+            block = block.checked_sub(1)?;
+            literal += block_length(block)?;
+        }
+        while literal >= block_length(block)? {
+            literal -= block_length(block)?;
+            block += 1;
+        }
+
+        let mut at = self.blocks + (block << self.block_size);
+        let mut code = u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?);
+        at += 8;
+        let mut used = 0;
+        let min_len = self.min_len as usize;
+        let mut symbol = loop {
+            let mut length = min_len;
+            while code < *self.base.get(length - min_len)? {
+                length += 1;
+            }
+            let symbol = read_u16(data, self.offsets + 2 * (length - min_len))? as usize
+                + ((code - self.base[length - min_len]) >> (64 - length)) as usize;
+            let values = *self.sym_len.get(symbol)? as i64 + 1;
+            if literal < values {
+                break symbol;
+            }
+            literal -= values;
+            code <<= length;
+            used += length;
+            if used >= 32 {
+                used -= 32;
+                code |= (u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?) as u64) << used;
+                at += 4;
+            }
+        };
+        while self.sym_len[symbol] != 0 {
+            let (left, right) = Self::symbol(data, self.sym_pat, symbol).ok()?;
+            let values = self.sym_len[left] as i64 + 1;
+            if literal < values {
+                symbol = left;
+            } else {
+                literal -= values;
+                symbol = right;
+            }
+        }
+        data.get(self.sym_pat + 3 * symbol).map(|&value| value as u32)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum SymbolState {
+    Unread,
+    Reading,
+    Read,
+}
+
+fn corrupt() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "not a syzygy table")
+}
+
+fn read_u8(data: &[u8], at: usize) -> io::Result<u8> {
+    data.get(at).copied().ok_or_else(corrupt)
+}
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+// table names are the white pieces, a v and the black pieces, strongest first, like KQvKR
+fn is_table_name(name: &str) -> bool {
+    let Some((white, black)) = name.split_once('v') else {
+        return false;
+    };
+    [white, black].iter().all(|side| side.starts_with('K') && side.chars().all(|c| PIECE_LETTERS.contains(&c)))
+        && name.len() - 1 <= MAX_PIECES
+}
+
+// the material of the position as a table name, white first unless it is mirrored
+fn material_key(position: &Position, mirrored: bool) -> String {
+    let players = if mirrored { [Player::Two, Player::One] } else { [Player::One, Player::Two] };
+    let [first, second] = players.map(|player| {
+        PIECE_LETTERS
+            .iter()
+            .zip(PIECE_TYPES.iter().rev())
+            .flat_map(|(&letter, &piece_type)| std::iter::repeat_n(letter, position.pieces(player, piece_type).count_ones() as usize))
+            .collect::<String>()
+    });
+    format!("{first}v{second}")
+}
+
+// the table name of the pieces a table lists, which is sometimes the other way round to its file name
+fn recalc_key(pieces: &[u8]) -> String {
+    let side = |color: u8| {
+        PIECE_LETTERS
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &letter)| std::iter::repeat_n(letter, pieces.iter().filter(|&&piece| piece == (6 - i as u8) | color).count()))
+            .collect::<String>()
+    };
+    format!("{}v{}", side(0), side(8))
+}
+
+fn is_en_passant(position: &Position, mov: Move) -> bool {
+    Some(mov.to) == position.en_passant() && position.piece_at(mov.from).is_some_and(|(_, piece_type)| piece_type == PieceType::Pawn)
+}
+
+fn is_pawn_push(position: &Position, mov: Move) -> bool {
+    position.piece_at(mov.from).is_some_and(|(_, piece_type)| piece_type == PieceType::Pawn) && position.captured(mov).is_none()
+}
+
+// squares from a1, with files and ranks from 0
+const fn rank(square: u8) -> i8 {
+    (square >> 3) as i8
+}
+
+const fn file(square: u8) -> i8 {
+    (square & 7) as i8
+}
+
+// how far a square is above the a1-h8 diagonal
+const fn off_diagonal(square: u8) -> i8 {
+    rank(square) - file(square)
+}
+
+const fn flip_diagonal(square: u8) -> u8 {
+    ((square >> 3) | (square << 3)) & 63
+}
+
+// the squares of a1-d1-d4 that the first piece is moved to, off the diagonal first
+const TRIANGLE: [u64; 64] = [
+    6, 0, 1, 2, 2, 1, 0, 6,
+    0, 7, 3, 4, 4, 3, 7, 0,
+    1, 3, 8, 5, 5, 8, 3, 1,
+    2, 4, 5, 9, 9, 5, 4, 2,
+    2, 4, 5, 9, 9, 5, 4, 2,
+    1, 3, 8, 5, 5, 8, 3, 1,
+    0, 7, 3, 4, 4, 3, 7, 0,
+    6, 0, 1, 2, 2, 1, 0, 6,
+];
+const INV_TRIANGLE: [u8; 10] = [1, 2, 3, 10, 11, 19, 0, 9, 18, 27];
+const FILE_TO_FILE: [usize; 8] = [0, 1, 2, 3, 3, 2, 1, 0];
+const KK_INDEX: [[i16; 64]; 10] = kk_index();
+const LOWER: [u64; 64] = lower();
+const DIAGONAL: [u64; 64] = diagonal();
+const FLAP: [usize; 64] = flap();
+const PAWN_TWIST: [u64; 64] = pawn_twist();
+const PAWN_INDEX: [[u64; 24]; 6] = pawn_index().0;
+const PAWN_FACTOR: [[u64; 4]; 6] = pawn_index().1;
+
+// the two kings numbered together, with the first in the triangle and the second below the diagonal if the first is on it
+const fn kk_index() -> [[i16; 64]; 10] {
+    let mut table = [[-1; 64]; 10];
+    let mut index = 0;
+    let mut pass = 0;
+    while pass < 2 {
+        let mut i = 0;
+        while i < 10 {
+            let king = INV_TRIANGLE[i];
+            let mut square = 0;
+            while square < 64 {
+                let apart = (file(king) - file(square)).abs() > 1 || (rank(king) - rank(square)).abs() > 1;
+                // kings on the diagonal are numbered after every other pair
+                let counted = if pass == 0 { i < 6 || off_diagonal(square) < 0 } else { i >= 6 && off_diagonal(square) == 0 };
+                if apart && counted {
+                    table[i][square as usize] = index;
+                    index += 1;
+                }
+                square += 1;
+            }
+            i += 1;
+        }
+        pass += 1;
+    }
+    table
+}
+
+// the squares below the diagonal numbered from 0 to 27, mirrored above it, and the diagonal from 28
+const fn lower() -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut index = 0;
+    let mut square = 0;
+    while square < 64 {
+        if off_diagonal(square) < 0 {
+            table[square as usize] = index;
+            table[flip_diagonal(square) as usize] = index;
+            index += 1;
+        } else if off_diagonal(square) == 0 {
+            table[square as usize] = 28 + rank(square) as u64;
+        }
+        square += 1;
+    }
+    table
+}
+
+// the a1-h8 diagonal from 0 and the h1-a8 diagonal from 8
+const fn diagonal() -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        if off_diagonal(square) == 0 {
+            table[square as usize] = rank(square) as u64;
+        } else if file(square) + rank(square) == 7 {
+            table[square as usize] = 8 + rank(square) as u64;
+        }
+        square += 1;
+    }
+    table
+}
+
+// the pawn squares of the a to d files, mirrored onto e to h
+const fn flap() -> [usize; 64] {
+    let mut table = [0; 64];
+    let mut square = 8;
+    while square < 56 {
+        table[square as usize] = FILE_TO_FILE[file(square) as usize] * 6 + rank(square) as usize - 1;
+        square += 1;
+    }
+    table
+}
+
+// the pawn squares ordered from the centre files out and from the seventh rank back
+const fn pawn_twist() -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut square = 8;
+    while square < 56 {
+        let column = if file(square) >= 4 { (file(square) - 4) as u64 * 12 } else { (3 - file(square)) as u64 * 12 + 1 };
+        table[square as usize] = column + 2 * (6 - rank(square) as u64);
+        square += 1;
+    }
+    table
+}
+
+const fn pawn_index() -> ([[u64; 24]; 6], [[u64; 4]; 6]) {
+    let mut index = [[0; 24]; 6];
+    let mut factor = [[0; 4]; 6];
+    let mut pawns = 0;
+    while pawns < 6 {
+        let mut sum = 0;
+        let mut j = 0;
+        while j < 24 {
+            if j > 0 && j % 6 == 0 {
+                factor[pawns][j / 6 - 1] = sum;
+                sum = 0;
+            }
+            index[pawns][j] = sum;
+            // the leading pawn on the j-th square of its file, with the rest placed after it
+            let square = (j % 6 + 1) * 8 + j / 6;
+            sum += if pawns == 0 { 1 } else { binomial(PAWN_TWIST[square], pawns as u64) };
+            j += 1;
+        }
+        factor[pawns][3] = sum;
+        pawns += 1;
+    }
+    (index, factor)
+}
+
+const fn binomial(n: u64, k: u64) -> u64 {
+    if k > n {
+        return 0;
+    }
+    let mut result = 1;
+    let mut i = 0;
+    while i < k {
+        result = result * (n - i) / (i + 1);
+        i += 1;
+    }
+    result
+}
+
+// the file group of the leading pawn furthest from the centre, which is swapped to the front
+fn pawn_file(squares: &mut [u8], leading: usize) -> usize {
+    for i in 1..leading {
+        if FLAP[squares[0] as usize] > FLAP[squares[i] as usize] {
+            squares.swap(0, i);
+        }
+    }
+    FILE_TO_FILE[file(squares[0]) as usize]
+}
+
+// the index of the pieces of a group of the same kind, counting the squares the earlier pieces aren't on
+fn encode_group(squares: &mut [u8], from: usize, count: usize, skip: u8) -> u64 {
+    squares[from..from + count].sort_unstable();
+    (from..from + count)
+        .map(|m| {
+            let square = squares[m];
+            let below = squares[..from].iter().filter(|&&earlier| square > earlier).count() as u64;
+            binomial(square as u64 - below - skip as u64, (m - from + 1) as u64)
+        })
+        .sum()
+}
+
+fn encode_piece(norm: &[usize], squares: &mut [u8], factor: &[u64], encoding: u8) -> u64 {
+    // the first piece is moved into a1-d1-d4 by mirroring the board, and the first piece off the diagonal below it
+    if squares[0] & 0x04 != 0 {
+        squares.iter_mut().for_each(|square| *square ^= 0x07);
+    }
+    if squares[0] & 0x20 != 0 {
+        squares.iter_mut().for_each(|square| *square ^= 0x38);
+    }
+    let leading = if encoding == 0 { 3 } else { 2 };
+    if let Some(i) = squares.iter().position(|&square| off_diagonal(square) != 0) {
+        if i < leading && off_diagonal(squares[i]) > 0 {
+            squares.iter_mut().for_each(|square| *square = flip_diagonal(*square));
+        }
+    }
+    let s = |i: usize| squares[i] as u64;
+    let mut index = if encoding == 0 {
+        let i = (s(1) > s(0)) as u64;
+        let j = (s(2) > s(0)) as u64 + (s(2) > s(1)) as u64;
+        if off_diagonal(squares[0]) != 0 {
+            TRIANGLE[s(0) as usize] * 63 * 62 + (s(1) - i) * 62 + (s(2) - j)
+        } else if off_diagonal(squares[1]) != 0 {
+            6 * 63 * 62 + DIAGONAL[s(0) as usize] * 28 * 62 + LOWER[s(1) as usize] * 62 + s(2) - j
+        } else if off_diagonal(squares[2]) != 0 {
+            6 * 63 * 62 + 4 * 28 * 62 + DIAGONAL[s(0) as usize] * 7 * 28 + (DIAGONAL[s(1) as usize] - i) * 28 + LOWER[s(2) as usize]
+        } else {
+            6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + DIAGONAL[s(0) as usize] * 7 * 6 + (DIAGONAL[s(1) as usize] - i) * 6
+                + (DIAGONAL[s(2) as usize] - j)
+        }
+    } else {
+        KK_INDEX[TRIANGLE[s(0) as usize] as usize][s(1) as usize] as u64
+    };
+    index *= factor[0];
+    let mut i = leading;
+    while i < squares.len() {
+        index += encode_group(squares, i, norm[i], 0) * factor[i];
+        i += norm[i];
+    }
+    index
+}
+
+fn encode_pawn(norm: &[usize], squares: &mut [u8], factor: &[u64], pawns: [usize; 2]) -> u64 {
+    if squares[0] & 0x04 != 0 {
+        squares.iter_mut().for_each(|square| *square ^= 0x07);
+    }
+    for i in 1..pawns[0] {
+        for j in i + 1..pawns[0] {
+            if PAWN_TWIST[squares[i] as usize] < PAWN_TWIST[squares[j] as usize] {
+                squares.swap(i, j);
+            }
+        }
+    }
+    let t = pawns[0] - 1;
+    let mut index = PAWN_INDEX[t][FLAP[squares[0] as usize]];
+    for i in (1..=t).rev() {
+        index += binomial(PAWN_TWIST[squares[i] as usize], (t - i + 1) as u64);
+    }
+    index *= factor[0];
+    let mut i = pawns[0];
+    if pawns[1] > 0 {
+        // the other color's pawns can't be on the first or last rank
+        index += encode_group(squares, i, pawns[1], 8) * factor[i];
+        i += pawns[1];
+    }
+    while i < squares.len() {
+        index += encode_group(squares, i, norm[i], 0) * factor[i];
+        i += norm[i];
+    }
+    index
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{
+        endgame::{EndgameResult, EndgameTables},
+        game::Game,
+    };
+
+    // the published 3-piece tables for the endgames kpk promotes into, and the bare minor pieces
+    pub(crate) fn fixtures() -> Tablebase {
+        Tablebase::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/syzygy")).unwrap()
+    }
+
+    fn placements() -> impl Iterator<Item = [u8; 3]> {
+        (0..64u8).flat_map(|white_king| {
+            (0..64u8).flat_map(move |queen| (0..64u8).map(move |black_king| [white_king, queen, black_king])).filter(
+                move |&[white_king, queen, black_king]| {
+                    white_king != queen
+                        && queen != black_king
+                        && ((white_king & 7).abs_diff(black_king & 7) > 1 || (white_king >> 3).abs_diff(black_king >> 3) > 1)
+                },
+            )
+        })
+    }
+
+    fn kqk_index(squares: [u8; 3]) -> usize {
+        encode_piece(&[3, 0, 0], &mut squares.to_vec(), &[1; MAX_PIECES], 0) as usize
+    }
+
+    #[test]
+    fn index_tables() {
+        let mut kings = KK_INDEX.iter().flatten().filter(|&&index| index >= 0).copied().collect::<Vec<i16>>();
+        kings.sort();
+        assert_eq!(kings, (0..462).collect::<Vec<i16>>());
+        // b1 with the other king on d1, d2 and a3, and a1 with it on c1, c3, d3 and h8
+        assert_eq!([KK_INDEX[0][3], KK_INDEX[0][11], KK_INDEX[0][16]], [0, 5, 10]);
+        assert_eq!([KK_INDEX[6][2], KK_INDEX[6][18], KK_INDEX[6][19], KK_INDEX[6][63]], [339, 441, 351, 446]);
+        assert_eq!([LOWER[0], LOWER[1], LOWER[8], LOWER[10], LOWER[63]], [28, 0, 0, 7, 35]);
+        assert_eq!([DIAGONAL[7], DIAGONAL[9], DIAGONAL[56]], [8, 1, 15]);
+        assert_eq!(PAWN_TWIST[8..16], [47, 35, 23, 11, 10, 22, 34, 46]);
+        assert_eq!(PAWN_TWIST[48..56], [37, 25, 13, 1, 0, 12, 24, 36]);
+        assert_eq!(FLAP[8..16], [0, 6, 12, 18, 18, 12, 6, 0]);
+    }
+
+    #[test]
+    fn encodes_symmetric_positions_alike() {
+        let mirrors: [fn(u8) -> u8; 4] = [|square| square, |square| square ^ 7, |square| square ^ 56, flip_diagonal];
+        for [white_king, queen, black_king] in placements().step_by(7) {
+            let index = kqk_index([white_king, queen, black_king]);
+            assert!(index < 31332);
+            for mirror in mirrors {
+                assert_eq!(kqk_index([mirror(white_king), mirror(queen), mirror(black_king)]), index);
+            }
+        }
+        // KPvK with the pawn mirrored onto the other side of the board
+        let pawn_index = |squares: [u8; 3]| encode_pawn(&[1, 1, 1], &mut squares.to_vec(), &[1, 1806, 1806 * 63, 0, 0, 0, 0], [1, 0]);
+        for pawn in 8..56 {
+            assert_eq!(pawn_index([pawn, 4, 60]), pawn_index([pawn ^ 7, 3, 59]));
+            assert!(pawn_index([pawn, 4, 60]) < 1806 * 63 * 62);
+        }
+    }
+
+    #[test]
+    fn probes_published_tables() {
+        let tablebase = fixtures();
+        assert_eq!(tablebase.max_pieces(), 3);
+        let probe = |fen: &str| {
+            let game = Game::from_fen(fen).unwrap();
+            (tablebase.probe_wdl(game.position()), tablebase.probe_dtz(game.position()))
+        };
+        // mates in one
+        assert_eq!(probe("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1"), (Some(Wdl::Win), Some(1)));
+        assert_eq!(probe("6k1/8/6K1/8/8/8/8/R7 w - - 0 1"), (Some(Wdl::Win), Some(1)));
+        assert_eq!(probe("8/8/8/8/8/2k5/8/3Q3K w - - 0 1"), (Some(Wdl::Win), Some(15)));
+        assert_eq!(probe("2k5/8/8/8/8/8/8/K4R2 b - - 0 1"), (Some(Wdl::Loss), Some(-28)));
+        // black's pieces are looked up with the colors swapped
+        assert_eq!(probe("8/8/8/8/4k3/8/8/K6r w - - 0 1"), (Some(Wdl::Loss), Some(-14)));
+        // the lone king takes the undefended queen
+        assert_eq!(probe("8/8/8/8/8/8/1q6/K5k1 w - - 0 1"), (Some(Wdl::Draw), Some(0)));
+        // with the kings in opposition the pawn only wins if white has the move
+        assert_eq!(probe("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"), (Some(Wdl::Win), Some(9)));
+        assert_eq!(probe("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"), (Some(Wdl::Draw), Some(0)));
+        // a king on a key square wins either way, straight away with a pawn move when there is one
+        assert_eq!(probe("3k4/8/3K4/8/3P4/8/8/8 w - - 0 1"), (Some(Wdl::Win), Some(1)));
+        assert_eq!(probe("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), (Some(Wdl::Loss), Some(-4)));
+        assert_eq!(probe("k7/8/8/P7/8/8/8/K7 w - - 0 1"), (Some(Wdl::Draw), Some(0)));
+        assert_eq!(probe("8/8/8/8/8/8/8/K1k5 w - - 0 1"), (Some(Wdl::Draw), Some(0)));
+        assert_eq!(probe("8/8/8/8/8/8/1R6/K1k4r w - - 0 1"), (None, None));
+    }
+
+    // the engine's own tables of the same endgames, solved without the tablebases, give the same results and, with
+    // no pawn to reset the count, the plies to mate as the dtz
+    #[test]
+    fn agrees_with_the_endgame_tables() {
+        let tablebase = fixtures();
+        for piece_type in [PieceType::Queen, PieceType::Rook, PieceType::Pawn] {
+            for [white_king, piece, black_king] in placements().step_by(211) {
+                if piece_type == PieceType::Pawn && !(8..56).contains(&piece) {
+                    continue;
+                }
+                for side in [Player::One, Player::Two] {
+                    let pieces = [(white_king, Player::One, PieceType::King), (piece, Player::One, piece_type), (black_king, Player::Two, PieceType::King)];
+                    let position = Position::from_pieces(&pieces, side);
+                    if position.in_check(side.other()) {
+                        continue;
+                    }
+                    let (wdl, dtz) = (tablebase.probe_wdl(&position).unwrap(), tablebase.probe_dtz(&position).unwrap());
+                    match EndgameTables::get().probe(&position).unwrap() {
+                        EndgameResult::Draw => assert_eq!((wdl, dtz), (Wdl::Draw, 0)),
+                        EndgameResult::Mate(winner, plies) => {
+                            assert_eq!(wdl, if winner == side { Wdl::Win } else { Wdl::Loss });
+                            // a player who is already checkmated has a dtz of -1
+                            let plies = if winner == side { plies as i32 } else { -(plies.max(1) as i32) };
+                            if piece_type != PieceType::Pawn {
+                                assert_eq!(dtz, plies);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ranks_root_moves() {
+        let tablebase = fixtures();
+        // the queen is lost to checks next to the king, every other move keeps the win
+        let game = Game::from_fen("8/8/8/8/8/2k5/8/3Q3K w - - 0 1").unwrap();
+        let moves = tablebase.root_moves(game.position()).unwrap();
+        assert_eq!(moves.len(), game.position().clone().legal_moves().len());
+        for (mov, wdl, dtz) in moves {
+            let mut next = *game.position();
+            next.make_move(mov);
+            let hangs = tablebase.probe_wdl(&next) == Some(Wdl::Draw);
+            assert_eq!(wdl, if hangs { Wdl::Draw } else { Wdl::Win });
+            if wdl == Wdl::Win {
+                assert_eq!(tablebase.probe_dtz(&next), Some(-(dtz as i32) + 1));
+            }
+        }
+    }
+}
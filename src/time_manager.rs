@@ -0,0 +1,152 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+// the number of moves a clock is assumed to have to last for when the time control doesn't say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+// how long the engine may think about its moves, and the clock each player's moves are timed by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeControl {
+    // the same budget for every move
+    MoveTime(Duration),
+    // a clock for the rest of the game, or for a period of moves after which the period's time is added again
+    Clock {
+        remaining: Duration,
+        bonus: Bonus,
+        moves_to_go: Option<u32>,
+        // the moves in each period and the time added for the next one, once moves_to_go has counted down
+        period: Option<(u32, Duration)>,
+    },
+}
+
+// how a clock makes up for the time each move takes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bonus {
+    // fischer: added after every move
+    Increment(Duration),
+    // bronstein: the time a move took is given back, up to the delay
+    Delay(Duration),
+}
+
+impl TimeControl {
+    // a clock for the rest of the game, topped up by the increment after every move
+    pub fn clock(remaining: Duration, increment: Duration) -> Self {
+        TimeControl::Clock { remaining, bonus: Bonus::Increment(increment), moves_to_go: None, period: None }
+    }
+
+    // a clock that gets the same time again after every moves_per_period moves, or that lasts the game without them
+    pub fn periods(time: Duration, bonus: Bonus, moves_per_period: Option<u32>) -> Self {
+        let moves_per_period = moves_per_period.filter(|&moves| moves > 0);
+        TimeControl::Clock { remaining: time, bonus, moves_to_go: moves_per_period, period: moves_per_period.map(|moves| (moves, time)) }
+    }
+
+    // the time left on a clock
+    pub fn remaining(&self) -> Option<Duration> {
+        match *self {
+            TimeControl::MoveTime(_) => None,
+            TimeControl::Clock { remaining, .. } => Some(remaining),
+        }
+    }
+
+    // the time to spend on the next move, never more than half of what is left on the clock. a delay is counted on
+    // being given back in full
+    pub fn budget(&self) -> Duration {
+        match *self {
+            TimeControl::MoveTime(budget) => budget,
+            TimeControl::Clock { remaining, bonus: Bonus::Increment(bonus) | Bonus::Delay(bonus), moves_to_go, .. } => {
+                let moves_to_go = moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+                (remaining / moves_to_go + bonus / 2).min(remaining / 2)
+            }
+        }
+    }
+
+    // the time control for the next move after spending the given time on this one, None if the clock ran out
+    // before the move was made
+    pub fn after_move(&self, elapsed: Duration) -> Option<Self> {
+        let TimeControl::Clock { remaining, bonus, moves_to_go, period } = *self else {
+            return Some(*self);
+        };
+        if elapsed >= remaining {
+            return None;
+        }
+        let mut remaining = remaining - elapsed
+            + match bonus {
+                Bonus::Increment(increment) => increment,
+                Bonus::Delay(delay) => elapsed.min(delay),
+            };
+        let moves_to_go = match (moves_to_go, period) {
+            (Some(1), Some((moves, time))) => {
+                remaining += time;
+                Some(moves)
+            }
+            (moves_to_go, _) => moves_to_go.map(|moves| moves.saturating_sub(1).max(1)),
+        };
+        Some(TimeControl::Clock { remaining, bonus, moves_to_go, period })
+    }
+}
+
+// minutes and seconds, with hours for long games and tenths of a second once time is short. the time for each move
+// for a move time
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let time = match *self {
+            TimeControl::MoveTime(time) | TimeControl::Clock { remaining: time, .. } => time,
+        };
+        let seconds = time.as_secs();
+        if seconds >= 3600 {
+            write!(f, "{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+        } else if seconds >= 10 {
+            write!(f, "{}:{:02}", seconds / 60, seconds % 60)
+        } else {
+            write!(f, "0:{:02}.{}", seconds, time.subsec_millis() / 100)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budgets() {
+        let second = Duration::from_secs(1);
+        assert_eq!(TimeControl::MoveTime(second).budget(), second);
+        assert_eq!(TimeControl::MoveTime(second).after_move(second * 5), Some(TimeControl::MoveTime(second)));
+
+        let clock = TimeControl::Clock { remaining: second * 60, bonus: Bonus::Increment(second), moves_to_go: Some(20), period: None };
+        assert_eq!(clock.budget(), Duration::from_millis(3500));
+        let clock = clock.after_move(second * 10).unwrap();
+        assert_eq!(clock, TimeControl::Clock { remaining: second * 51, bonus: Bonus::Increment(second), moves_to_go: Some(19), period: None });
+
+        // a nearly empty clock still leaves time for the moves after this one
+        assert_eq!(TimeControl::clock(second, second * 10).budget(), second / 2);
+        assert_eq!(TimeControl::clock(second, Duration::ZERO).after_move(second * 2), None);
+    }
+
+    #[test]
+    fn clocks() {
+        let second = Duration::from_secs(1);
+        let clock = TimeControl::periods(second * 60, Bonus::Increment(second * 2), None);
+        assert_eq!(clock.after_move(second * 5).unwrap().remaining(), Some(second * 57));
+        assert_eq!(clock.after_move(second * 60), None);
+
+        // a delay only gives back as much time as the move took
+        let clock = TimeControl::periods(second * 60, Bonus::Delay(second * 3), None);
+        assert_eq!(clock.after_move(second).unwrap().remaining(), Some(second * 60));
+        assert_eq!(clock.after_move(second * 5).unwrap().remaining(), Some(second * 58));
+        assert_eq!(clock.budget(), TimeControl::clock(second * 60, second * 3).budget());
+
+        // the base time again once the period's moves are made
+        let mut clock = TimeControl::periods(second * 60, Bonus::Increment(Duration::ZERO), Some(2));
+        clock = clock.after_move(second * 10).unwrap();
+        assert_eq!(clock.budget(), second * 25);
+        clock = clock.after_move(second * 10).unwrap();
+        assert_eq!((clock.remaining(), clock.budget()), (Some(second * 100), second * 50));
+
+        assert_eq!(TimeControl::clock(second * 3725, Duration::ZERO).to_string(), "1:02:05");
+        assert_eq!(TimeControl::clock(second * 300, Duration::ZERO).to_string(), "5:00");
+        assert_eq!(TimeControl::clock(Duration::from_millis(9450), Duration::ZERO).to_string(), "0:09.4");
+    }
+}
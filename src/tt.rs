@@ -0,0 +1,148 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::position::{Move, PieceType};
+
+pub const DEFAULT_TABLE_SIZE_MB: usize = 16;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Bound {
+    Exact,
+    // the score is at least this, the search failed high
+    Lower,
+    // the score is at most this, the search failed low
+    Upper,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Entry {
+    pub score: f32,
+    pub depth: u8,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+// a fixed size hash table shared between search threads without locking. each slot stores the
+// key xored with its data, so a slot torn by two threads writing at once reads back as a miss
+pub struct TranspositionTable {
+    slots: Vec<(AtomicU64, AtomicU64)>,
+}
+
+impl TranspositionTable {
+    pub fn new(megabytes: usize) -> Self {
+        let slots = (megabytes * 1024 * 1024 / 16).max(1).next_power_of_two();
+        Self {
+            slots: (0..slots).map(|_| (AtomicU64::new(0), AtomicU64::new(0))).collect(),
+        }
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<Entry> {
+        let (key, data) = self.slot(hash);
+        let data = data.load(Ordering::Relaxed);
+        if data == 0 || key.load(Ordering::Relaxed) ^ data != hash {
+            return None;
+        }
+        Some(unpack(data))
+    }
+
+    // keeps the deeper result for the same position, but always replaces other positions
+    pub fn store(&self, hash: u64, entry: Entry) {
+        let (key, data) = self.slot(hash);
+        let old = data.load(Ordering::Relaxed);
+        if old != 0 && key.load(Ordering::Relaxed) ^ old == hash {
+            let old = unpack(old);
+            if old.depth > entry.depth && entry.bound != Bound::Exact {
+                return;
+            }
+        }
+        let packed = pack(&entry);
+        key.store(hash ^ packed, Ordering::Relaxed);
+        data.store(packed, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for (key, data) in &self.slots {
+            key.store(0, Ordering::Relaxed);
+            data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn slot(&self, hash: u64) -> &(AtomicU64, AtomicU64) {
+        &self.slots[hash as usize & (self.slots.len() - 1)]
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_TABLE_SIZE_MB)
+    }
+}
+
+// score in the low 32 bits, then depth, bound and the best move's squares and promotion, with bit 63 marking the slot as used
+fn pack(entry: &Entry) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 0,
+        Bound::Lower => 1,
+        Bound::Upper => 2,
+    };
+    let best_move = match entry.best_move {
+        Some(mov) => {
+            let promotion = match mov.promotion {
+                None => 0,
+                Some(PieceType::Knight) => 1,
+                Some(PieceType::Bishop) => 2,
+                Some(PieceType::Rook) => 3,
+                Some(_) => 4,
+            };
+            1 << 15 | promotion << 12 | (mov.from as u64) << 6 | mov.to as u64
+        }
+        None => 0,
+    };
+    entry.score.to_bits() as u64 | (entry.depth as u64) << 32 | bound << 40 | best_move << 42 | 1 << 63
+}
+
+fn unpack(data: u64) -> Entry {
+    let bound = match (data >> 40) & 3 {
+        0 => Bound::Exact,
+        1 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+    let best_move = (data >> 42) & 0xFFFF;
+    let promotion = match (best_move >> 12) & 7 {
+        0 => None,
+        1 => Some(PieceType::Knight),
+        2 => Some(PieceType::Bishop),
+        3 => Some(PieceType::Rook),
+        _ => Some(PieceType::Queen),
+    };
+    Entry {
+        score: f32::from_bits(data as u32),
+        depth: (data >> 32) as u8,
+        bound,
+        best_move: (best_move >> 15 == 1).then(|| Move::new(((best_move >> 6) & 63) as u8, (best_move & 63) as u8, promotion)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_and_probes_entries() {
+        let table = TranspositionTable::new(1);
+        let entry = Entry { score: -3.5, depth: 4, bound: Bound::Lower, best_move: Some(Move::new(52, 36, None)) };
+        table.store(0xDEAD_BEEF, entry);
+        assert_eq!(table.probe(0xDEAD_BEEF), Some(entry));
+        assert_eq!(table.probe(0xDEAD_BEEE), None);
+
+        // a shallower bound doesn't replace a deeper result for the same position
+        let shallow = Entry { score: 1.0, depth: 2, bound: Bound::Upper, best_move: None };
+        table.store(0xDEAD_BEEF, shallow);
+        assert_eq!(table.probe(0xDEAD_BEEF), Some(entry));
+        let exact = Entry { score: f32::MIN, depth: 1, bound: Bound::Exact, best_move: Some(Move::new(15, 7, Some(PieceType::Knight))) };
+        table.store(0xDEAD_BEEF, exact);
+        assert_eq!(table.probe(0xDEAD_BEEF), Some(exact));
+
+        table.clear();
+        assert_eq!(table.probe(0xDEAD_BEEF), None);
+    }
+}
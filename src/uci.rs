@@ -0,0 +1,485 @@
+use std::{
+    io::{self, BufRead},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    book::{Book, DEFAULT_BOOK_DEPTH},
+    eval::EvalParams,
+    evaluator::{EvaluatorKind, EVALUATOR_KINDS},
+    fen::START_FEN,
+    game::{parse_coord, Cache, Game, DEFAULT_SEARCH_DEPTH, MAX_SEARCH_DEPTH},
+    model::Model,
+    piece::Move,
+    position::PieceType,
+    search::{Score, SearchConfig, SearchResult},
+    syzygy::Tablebase,
+    time_manager::{Bonus, TimeControl},
+    tt::{TranspositionTable, DEFAULT_TABLE_SIZE_MB},
+};
+
+const MAX_TABLE_SIZE_MB: usize = 4096;
+const MAX_BOOK_DEPTH: u32 = 100;
+
+struct UciEngine {
+    fen: String,
+    moves: Vec<String>,
+    search_depth: u8,
+    eval: EvaluatorKind,
+    eval_file: Option<String>,
+    eval_params: Arc<EvalParams>,
+    model_dir: Option<String>,
+    model: Option<Model>,
+    // whether the engine plays from its own book rather than leave the opening to the gui
+    own_book: bool,
+    book_file: Option<String>,
+    book: Option<Arc<Book>>,
+    book_depth: u32,
+    syzygy_path: Option<String>,
+    tablebase: Option<Arc<Tablebase>>,
+    cache: Cache,
+    search_config: SearchConfig,
+    search: Option<(JoinHandle<()>, Arc<AtomicBool>)>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct GoParams {
+    depth: Option<u8>,
+    movetime: Option<u64>,
+    wtime: Option<u64>,
+    btime: Option<u64>,
+    winc: Option<u64>,
+    binc: Option<u64>,
+    movestogo: Option<u32>,
+    infinite: bool,
+}
+
+pub fn uci_loop(eval: EvaluatorKind, eval_params: EvalParams, book: Option<Book>, book_depth: u32, tablebase: Option<Tablebase>, search_depth: Option<u8>, model_dir: Option<String>) {
+    let mut engine = UciEngine::new(eval, eval_params, search_depth, model_dir);
+    engine.own_book = book.is_some();
+    engine.book = book.map(Arc::new);
+    engine.book_depth = book_depth;
+    engine.tablebase = tablebase.map(Arc::new);
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !engine.handle_command(&line) {
+            break;
+        }
+    }
+    engine.stop_search();
+}
+
+impl UciEngine {
+    fn new(eval: EvaluatorKind, eval_params: EvalParams, search_depth: Option<u8>, model_dir: Option<String>) -> Self {
+        Self {
+            fen: START_FEN.to_string(),
+            moves: Vec::new(),
+            search_depth: search_depth.unwrap_or(DEFAULT_SEARCH_DEPTH),
+            eval,
+            eval_file: None,
+            eval_params: Arc::new(eval_params),
+            model_dir,
+            model: None,
+            own_book: false,
+            book_file: None,
+            book: None,
+            book_depth: DEFAULT_BOOK_DEPTH,
+            syzygy_path: None,
+            tablebase: None,
+            cache: Arc::new(TranspositionTable::default()),
+            search_config: SearchConfig::default(),
+            search: None,
+        }
+    }
+
+    // returns false once the gui asks the engine to quit
+    fn handle_command(&mut self, line: &str) -> bool {
+        let tokens = line.split_whitespace().collect::<Vec<&str>>();
+        let Some((&command, args)) = tokens.split_first() else {
+            return true;
+        };
+        match command {
+            "uci" => {
+                println!("id name {} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+                println!("id author natekimball");
+                println!("option name Depth type spin default {} min 1 max {MAX_SEARCH_DEPTH}", self.search_depth);
+                println!("option name Hash type spin default {DEFAULT_TABLE_SIZE_MB} min 1 max {MAX_TABLE_SIZE_MB}");
+                println!("option name OwnBook type check default {}", self.own_book);
+                println!("option name BookFile type string default {}", self.book_file.as_deref().unwrap_or("<empty>"));
+                println!("option name BookDepth type spin default {} min 0 max {MAX_BOOK_DEPTH}", self.book_depth);
+                println!("option name SyzygyPath type string default {}", self.syzygy_path.as_deref().unwrap_or("<empty>"));
+                let kinds = EVALUATOR_KINDS.map(|kind| format!("var {kind}")).join(" ");
+                println!("option name Eval type combo default {} {kinds}", self.eval);
+                println!("option name EvalFile type string default {}", self.eval_file.as_deref().unwrap_or("<empty>"));
+                println!("option name ModelDir type string default {}", self.model_dir.as_deref().unwrap_or("<empty>"));
+                for (name, enabled) in search_options(&mut self.search_config) {
+                    println!("option name {name} type check default {enabled}");
+                }
+                println!("uciok");
+            }
+            "isready" => {
+                self.load_model();
+                println!("readyok");
+            }
+            "ucinewgame" => {
+                self.stop_search();
+                self.cache.clear();
+                self.fen = START_FEN.to_string();
+                self.moves.clear();
+            }
+            "position" => self.set_position(args),
+            "go" => self.go(GoParams::parse(args)),
+            "stop" => self.stop_search(),
+            "setoption" => self.set_option(args),
+            "quit" => return false,
+            // unknown commands are ignored, as the protocol requires
+            _ => (),
+        }
+        true
+    }
+
+    fn set_position(&mut self, args: &[&str]) {
+        let moves_start = args.iter().position(|&arg| arg == "moves").unwrap_or(args.len());
+        let fen = match args.first() {
+            Some(&"startpos") => START_FEN.to_string(),
+            Some(&"fen") => args[1..moves_start].join(" "),
+            _ => {
+                println!("info string expected startpos or fen");
+                return;
+            }
+        };
+        let moves = args.get(moves_start + 1..).unwrap_or_default().iter().map(|mov| mov.to_string()).collect::<Vec<String>>();
+        match build_game(&fen, &moves, self.search_depth, self.cache.clone()) {
+            Ok(_) => {
+                self.fen = fen;
+                self.moves = moves;
+            }
+            Err(err) => println!("info string {err}"),
+        }
+    }
+
+    fn set_option(&mut self, args: &[&str]) {
+        let value_start = args.iter().position(|&arg| arg == "value").unwrap_or(args.len());
+        let name = args.get(1..value_start).unwrap_or_default().join(" ");
+        let value = args.get(value_start + 1..).unwrap_or_default().join(" ");
+        match name.to_ascii_lowercase().as_str() {
+            "depth" => match value.parse() {
+                Ok(depth) if depth > 0 => self.search_depth = depth,
+                _ => println!("info string invalid depth {value}"),
+            },
+            "hash" => match value.parse() {
+                Ok(megabytes) if (1..=MAX_TABLE_SIZE_MB).contains(&megabytes) => {
+                    self.stop_search();
+                    self.cache = Arc::new(TranspositionTable::new(megabytes));
+                }
+                _ => println!("info string invalid hash size {value}"),
+            },
+            "eval" => match value.parse() {
+                Ok(eval) => {
+                    self.eval = eval;
+                    self.cache.clear();
+                }
+                Err(err) => println!("info string {err}"),
+            },
+            "evalfile" => {
+                let eval_file = if value.is_empty() || value == "<empty>" { None } else { Some(value) };
+                let eval_params = eval_file.as_deref().map_or(Ok(EvalParams::default()), EvalParams::from_file);
+                match eval_params {
+                    Ok(eval_params) => {
+                        self.eval_file = eval_file;
+                        self.eval_params = Arc::new(eval_params);
+                        self.cache.clear();
+                    }
+                    Err(err) => println!("info string invalid evaluation file {err}"),
+                }
+            }
+            "ownbook" => self.own_book = value == "true",
+            "bookfile" => {
+                if value.is_empty() || value == "<empty>" {
+                    self.book_file = None;
+                    self.book = None;
+                } else {
+                    match Book::open(&value) {
+                        Ok(book) => {
+                            self.book_file = Some(value);
+                            self.book = Some(Arc::new(book));
+                        }
+                        Err(err) => println!("info string invalid book file {value}: {err}"),
+                    }
+                }
+            }
+            "bookdepth" => match value.parse() {
+                Ok(depth) if depth <= MAX_BOOK_DEPTH => self.book_depth = depth,
+                _ => println!("info string invalid book depth {value}"),
+            },
+            "syzygypath" => {
+                if value.is_empty() || value == "<empty>" {
+                    self.syzygy_path = None;
+                    self.tablebase = None;
+                } else {
+                    match Tablebase::open(&value) {
+                        Ok(tablebase) => {
+                            self.syzygy_path = Some(value);
+                            self.tablebase = Some(Arc::new(tablebase));
+                        }
+                        Err(err) => println!("info string invalid syzygy path {value}: {err}"),
+                    }
+                }
+            }
+            "modeldir" => {
+                self.model_dir = if value.is_empty() || value == "<empty>" { None } else { Some(value) };
+                self.model = None;
+                self.cache.clear();
+            }
+            _ => {
+                let option = search_options(&mut self.search_config)
+                    .into_iter()
+                    .find(|(option, _)| option.eq_ignore_ascii_case(&name));
+                match option {
+                    Some((_, enabled)) => *enabled = value == "true",
+                    None => println!("info string unknown option {name}"),
+                }
+            }
+        }
+    }
+
+    fn load_model(&mut self) {
+        if self.eval.uses_model() && self.model.is_none() {
+            self.model = Some(Model::new(self.model_dir.clone()));
+        }
+    }
+
+    fn go(&mut self, params: GoParams) {
+        self.stop_search();
+        self.load_model();
+        let (eval, model, eval_params) = (self.eval, self.model.clone(), self.eval_params.clone());
+        let book = if self.own_book { self.book.clone() } else { None };
+        let book_depth = self.book_depth;
+        let tablebase = self.tablebase.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let (fen, moves, search_depth, cache) = (self.fen.clone(), self.moves.clone(), self.search_depth, self.cache.clone());
+        let search_config = self.search_config;
+        let search_stop = stop.clone();
+        let handle = thread::spawn(move || {
+            let game = build_game(&fen, &moves, search_depth, cache);
+            match game {
+                Ok(mut game) => {
+                    game.set_search_config(search_config);
+                    game.set_evaluator(eval.evaluator(model.as_ref(), &eval_params));
+                    game.set_book(book, book_depth);
+                    game.set_tablebase(tablebase);
+                    search(&mut game, &params, search_depth, search_stop);
+                }
+                Err(err) => {
+                    println!("info string {err}");
+                    println!("bestmove 0000");
+                }
+            }
+        });
+        self.search = Some((handle, stop));
+    }
+
+    fn stop_search(&mut self) {
+        if let Some((handle, stop)) = self.search.take() {
+            stop.store(true, Ordering::Relaxed);
+            handle.join().unwrap();
+        }
+    }
+}
+
+impl GoParams {
+    fn parse(args: &[&str]) -> Self {
+        let mut params = Self::default();
+        let mut args = args.iter();
+        while let Some(&arg) = args.next() {
+            match arg {
+                "depth" => params.depth = next_value(&mut args),
+                "movetime" => params.movetime = next_value(&mut args),
+                "wtime" => params.wtime = next_value(&mut args),
+                "btime" => params.btime = next_value(&mut args),
+                "winc" => params.winc = next_value(&mut args),
+                "binc" => params.binc = next_value(&mut args),
+                "movestogo" => params.movestogo = next_value(&mut args),
+                "infinite" => params.infinite = true,
+                _ => (),
+            }
+        }
+        params
+    }
+
+    // time allotted for this move, if the search is limited by time at all
+    fn budget(&self, white: bool) -> Option<Duration> {
+        if let Some(movetime) = self.movetime {
+            return Some(TimeControl::MoveTime(Duration::from_millis(movetime)).budget());
+        }
+        let (time, increment) = if white {
+            (self.wtime?, self.winc.unwrap_or(0))
+        } else {
+            (self.btime?, self.binc.unwrap_or(0))
+        };
+        let clock = TimeControl::Clock {
+            remaining: Duration::from_millis(time),
+            bonus: Bonus::Increment(Duration::from_millis(increment)),
+            moves_to_go: self.movestogo,
+            period: None,
+        };
+        Some(clock.budget())
+    }
+}
+
+// the search techniques that can be switched off from the gui, to play versions of the engine against each other
+fn search_options(config: &mut SearchConfig) -> [(&'static str, &mut bool); 5] {
+    [
+        ("MoveOrdering", &mut config.move_ordering),
+        ("PrincipalVariationSearch", &mut config.principal_variation),
+        ("NullMovePruning", &mut config.null_move),
+        ("LateMoveReductions", &mut config.late_move_reductions),
+        ("CheckExtensions", &mut config.check_extensions),
+    ]
+}
+
+fn next_value<T: FromStr>(args: &mut std::slice::Iter<&str>) -> Option<T> {
+    args.next().and_then(|value| value.parse().ok())
+}
+
+fn build_game<'a>(fen: &str, moves: &[String], search_depth: u8, cache: Cache) -> Result<Game<'a>, String> {
+    let mut game = Game::engine(None, Some(search_depth), Some(cache));
+    game.load_fen(fen).map_err(|err| format!("invalid fen: {err}"))?;
+    for mov in moves {
+        let legal = parse_move(&game, mov).is_some_and(|mov| game.apply_move(mov.from, mov.to, mov.promotion_letter()));
+        if !legal {
+            return Err(format!("illegal move {mov}"));
+        }
+    }
+    Ok(game)
+}
+
+// iteratively deepens the search until the depth or time limit is hit, then reports the best move
+fn search(game: &mut Game, params: &GoParams, search_depth: u8, stop: Arc<AtomicBool>) {
+    game.set_stop_flag(stop.clone());
+    let budget = params.budget(game.is_maximizing());
+    let max_depth = params.depth.unwrap_or(if budget.is_some() || params.infinite { MAX_SEARCH_DEPTH } else { search_depth });
+    let report = |result: &SearchResult| {
+        let score = match result.score {
+            Score::Centipawns(centipawns) => format!("cp {centipawns}"),
+            Score::Mate(moves) => format!("mate {moves}"),
+        };
+        println!(
+            "info depth {} score {score} nodes {} nps {} time {} pv {}",
+            result.depth,
+            result.nodes,
+            result.nps(),
+            result.elapsed.as_millis(),
+            result.pv.iter().map(ToString::to_string).collect::<Vec<String>>().join(" ")
+        );
+    };
+    let best_move = game
+        .book_move()
+        .or_else(|| game.iterative_deepening(max_depth, budget, report).map(|result| result.best_move))
+        .or_else(|| game.get_possible_moves(game.current_player()).first().copied());
+    while params.infinite && !stop.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(10));
+    }
+    match best_move {
+        Some(mov) => println!("bestmove {mov}"),
+        None => println!("bestmove 0000"),
+    }
+}
+
+// a move in long algebraic notation, of the kind it would be in the game's position
+pub fn parse_move(game: &Game, mov: &str) -> Option<Move> {
+    if !mov.is_ascii() || !(4..=5).contains(&mov.len()) {
+        return None;
+    }
+    let from = parse_coord(&mov[0..2])?;
+    let to = parse_coord(&mov[2..4])?;
+    let promotion = match mov.chars().nth(4) {
+        Some(promotion) => Some(PieceType::promotion(promotion)?),
+        None => None,
+    };
+    Some(game.move_between(from, to, promotion))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{piece::MoveKind, player::Player};
+
+    #[test]
+    fn parse_and_format_moves() {
+        let mut game = Game::from_fen("8/P6k/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(parse_move(&game, "a1b2"), Some(Move::new((0, 7), (1, 6), None, MoveKind::Normal)));
+        assert_eq!(parse_move(&game, "a7a8n"), Some(Move::new((0, 1), (0, 0), Some(PieceType::Knight), MoveKind::Normal)));
+        assert_eq!(parse_move(&game, "a7a8x"), None);
+        assert_eq!(parse_move(&game, "e2"), None);
+        assert_eq!(parse_move(&game, "i2i4"), None);
+        let start = Game::two_player_game(false);
+        assert_eq!(parse_move(&start, "e2e4").map(|mov| mov.kind), Some(MoveKind::Double((4, 4))));
+
+        let moves = game.get_possible_moves(Player::One).iter().map(ToString::to_string).collect::<Vec<String>>();
+        for mov in ["a7a8q", "a7a8r", "a7a8b", "a7a8n", "a1b1"] {
+            assert!(moves.contains(&mov.to_string()), "{mov} is missing from {moves:?}");
+        }
+    }
+
+    #[test]
+    fn position_command() {
+        let mut engine = UciEngine::new(EvaluatorKind::Heuristic, EvalParams::default(), None, None);
+        engine.handle_command("position startpos moves e2e4 e7e5 g1f3");
+        assert_eq!(engine.moves, vec!["e2e4", "e7e5", "g1f3"]);
+
+        engine.handle_command("position fen 8/P6k/8/8/8/8/8/K7 w - - 0 1 moves a7a8n");
+        assert_eq!(engine.fen, "8/P6k/8/8/8/8/8/K7 w - - 0 1");
+        let mut game = build_game(&engine.fen, &engine.moves, 2, engine.cache.clone()).unwrap();
+        assert_eq!(game.to_fen(), "N7/7k/8/8/8/8/8/K7 b - - 0 1");
+
+        // illegal moves leave the previous position in place
+        engine.handle_command("position startpos moves e2e5");
+        assert_eq!(engine.moves, vec!["a7a8n"]);
+    }
+
+    #[test]
+    fn search_options() {
+        let mut engine = UciEngine::new(EvaluatorKind::Heuristic, EvalParams::default(), None, None);
+        engine.handle_command("setoption name NullMovePruning value false");
+        engine.handle_command("setoption name latemovereductions value false");
+        let config = SearchConfig { null_move: false, late_move_reductions: false, ..SearchConfig::default() };
+        assert_eq!(engine.search_config, config);
+
+        engine.handle_command("setoption name Eval value material");
+        assert_eq!(engine.eval, EvaluatorKind::Material);
+        engine.handle_command("setoption name Eval value stockfish");
+        assert_eq!(engine.eval, EvaluatorKind::Material);
+        // a file that can't be read leaves the terms as they were
+        engine.handle_command("setoption name EvalFile value /nonexistent/eval.txt");
+        assert_eq!(engine.eval_file, None);
+        assert_eq!(*engine.eval_params, EvalParams::default());
+    }
+
+    #[test]
+    fn go_params() {
+        let params = GoParams::parse(&["wtime", "60000", "btime", "30000", "winc", "1000", "movestogo", "20"]);
+        assert_eq!(params.wtime, Some(60000));
+        assert_eq!(params.budget(true), Some(Duration::from_millis(3500)));
+        assert_eq!(params.budget(false), Some(Duration::from_millis(1500)));
+        assert_eq!(GoParams::parse(&["movetime", "200"]).budget(false), Some(Duration::from_millis(200)));
+        assert_eq!(GoParams::parse(&["depth", "3"]).budget(true), None);
+    }
+
+    #[test]
+    fn search_finds_mate_in_one() {
+        let cache = Arc::new(TranspositionTable::new(1));
+        let mut game = build_game("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &[], 2, cache).unwrap();
+        let (mov, _) = game.search(2).unwrap();
+        assert_eq!(mov.to_string(), "a1a8");
+        assert_eq!(game.current_player(), Player::One);
+    }
+}
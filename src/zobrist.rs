@@ -0,0 +1,43 @@
+use crate::{player::Player, position::PieceType};
+
+// 12 piece kinds on 64 squares, then the side to move, 4 castling rights and 8 en passant files
+const PIECE_KEYS: usize = 12 * 64;
+const SIDE_KEY: usize = PIECE_KEYS;
+const CASTLING_KEYS: usize = SIDE_KEY + 1;
+const EN_PASSANT_KEYS: usize = CASTLING_KEYS + 4;
+const KEYS: [u64; EN_PASSANT_KEYS + 8] = generate_keys();
+
+// splitmix64, so the keys are the same on every run and every platform
+const fn generate_keys() -> [u64; EN_PASSANT_KEYS + 8] {
+    let mut keys = [0; EN_PASSANT_KEYS + 8];
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut i = 0;
+    while i < keys.len() {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+pub(crate) fn piece(player: Player, piece_type: PieceType, square: u8) -> u64 {
+    let kind = piece_type as usize + 6 * player.index();
+    KEYS[kind * 64 + square as usize]
+}
+
+pub(crate) fn side() -> u64 {
+    KEYS[SIDE_KEY]
+}
+
+// rights is a mask of white kingside, white queenside, black kingside and black queenside
+pub(crate) fn castling(rights: u8) -> u64 {
+    (0..4).filter(|i| rights & (1 << i) != 0).fold(0, |key, i| key ^ KEYS[CASTLING_KEYS + i])
+}
+
+// keyed on the file of the square passed over by a pawn that just moved two squares
+pub(crate) fn en_passant(target: Option<u8>) -> u64 {
+    target.map_or(0, |square| KEYS[EN_PASSANT_KEYS + square as usize % 8])
+}